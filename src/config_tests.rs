@@ -1,9 +1,15 @@
 #[cfg(test)]
 mod tests {
-    use crate::{ArrConfig, Config, PutioConfig};
+    use crate::{
+        download_system::cleanup::{LocalCleanup, RemoteCleanup},
+        notify::{EventKind, NotifierKind},
+        services::{arr::ArrKind, putio},
+        ArrConfig, ArrInstanceConfig, CleanupConfig, Config, ExtractConfig, FileFilterConfig,
+        NotificationsConfig, PostDownloadConfig, PutioConfig, SeedingConfig,
+    };
     use figment::{
         providers::{Format, Serialized, Toml},
-        Figment,
+        Figment, Jail,
     };
     use std::fs;
     use tempfile::NamedTempFile;
@@ -13,27 +19,50 @@ mod tests {
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "testpass".to_string(),
             polling_interval: 10,
             port: 9091,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec!["sample".to_string(), "extras".to_string()],
             uid: 1000,
             username: "testuser".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "test_key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: Some(ArrConfig {
                 url: "http://localhost:8989".to_string(),
                 api_key: "sonarr_key".to_string(),
+                category: None,
             }),
             radarr: Some(ArrConfig {
                 url: "http://localhost:7878".to_string(),
                 api_key: "radarr_key".to_string(),
+                category: None,
             }),
             whisparr: None,
+            lidarr: None,
+            readarr: None,
+            arr: vec![],
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -48,12 +77,17 @@ mod tests {
         let json = r#"{
             "bind_address": "127.0.0.1",
             "download_directory": "/tmp/downloads",
+            "download_segments": 4,
             "download_workers": 8,
             "loglevel": "debug",
+            "max_download_attempts": 3,
+            "max_retry_delay": 300,
+            "min_segment_size_mb": 32,
             "orchestration_workers": 20,
             "password": "mypass",
             "polling_interval": 30,
             "port": 8080,
+            "retry_delay": 5,
             "skip_directories": ["sample"],
             "uid": 1001,
             "username": "myuser",
@@ -68,15 +102,25 @@ mod tests {
             "whisparr": null
         }"#;
 
-        let config: Config = serde_json::from_str(json).unwrap();
+        let config: Config = crate::config_defaults()
+            .merge(Serialized::defaults(
+                serde_json::from_str::<serde_json::Value>(json).unwrap(),
+            ))
+            .extract()
+            .unwrap();
         assert_eq!(config.bind_address, "127.0.0.1");
         assert_eq!(config.download_directory, "/tmp/downloads");
+        assert_eq!(config.download_segments, 4);
         assert_eq!(config.download_workers, 8);
         assert_eq!(config.loglevel, "debug");
+        assert_eq!(config.max_download_attempts, 3);
+        assert_eq!(config.max_retry_delay, 300);
+        assert_eq!(config.min_segment_size_mb, 32);
         assert_eq!(config.orchestration_workers, 20);
         assert_eq!(config.password, "mypass");
         assert_eq!(config.polling_interval, 30);
         assert_eq!(config.port, 8080);
+        assert_eq!(config.retry_delay, 5);
         assert_eq!(config.skip_directories, vec!["sample"]);
         assert_eq!(config.uid, 1001);
         assert_eq!(config.username, "myuser");
@@ -90,6 +134,11 @@ mod tests {
     fn test_putio_config() {
         let putio = PutioConfig {
             api_key: "test_api_key_123".to_string(),
+            timeout: 10,
+            max_retries: 3,
+            api_url: putio::API_URL.to_string(),
+            upload_url: putio::UPLOAD_URL.to_string(),
+            folder: None,
         };
 
         let json = serde_json::to_string(&putio).unwrap();
@@ -101,6 +150,7 @@ mod tests {
         let arr = ArrConfig {
             url: "http://localhost:8989/sonarr".to_string(),
             api_key: "sonarr_key_456".to_string(),
+            category: None,
         };
 
         let json = serde_json::to_string(&arr).unwrap();
@@ -113,35 +163,91 @@ mod tests {
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "pass".to_string(),
             polling_interval: 10,
             port: 9091,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec![],
             uid: 1000,
             username: "user".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: Some(ArrConfig {
                 url: "http://sonarr:8989".to_string(),
                 api_key: "sonarr_key".to_string(),
+                category: None,
             }),
             radarr: Some(ArrConfig {
                 url: "http://radarr:7878".to_string(),
                 api_key: "radarr_key".to_string(),
+                category: None,
             }),
             whisparr: Some(ArrConfig {
                 url: "http://whisparr:6969".to_string(),
                 api_key: "whisparr_key".to_string(),
+                category: None,
+            }),
+            lidarr: Some(ArrConfig {
+                url: "http://lidarr:8686".to_string(),
+                api_key: "lidarr_key".to_string(),
+                category: None,
             }),
+            readarr: Some(ArrConfig {
+                url: "http://readarr:8787".to_string(),
+                api_key: "readarr_key".to_string(),
+                category: None,
+            }),
+            arr: vec![ArrInstanceConfig {
+                name: "sonarr-4k".to_string(),
+                kind: ArrKind::Sonarr,
+                url: "http://sonarr-4k:8989".to_string(),
+                api_key: "sonarr_4k_key".to_string(),
+                category: None,
+            }],
         };
 
         assert!(config.sonarr.is_some());
         assert!(config.radarr.is_some());
         assert!(config.whisparr.is_some());
+
+        let services = config.arr_services();
+        assert_eq!(
+            services.iter().map(|s| s.kind).collect::<Vec<_>>(),
+            vec![
+                ArrKind::Sonarr,
+                ArrKind::Radarr,
+                ArrKind::Whisparr,
+                ArrKind::Lidarr,
+                ArrKind::Readarr,
+                ArrKind::Sonarr
+            ]
+        );
+        assert_eq!(services[3].name, "Lidarr");
+        assert_eq!(services[3].url, "http://lidarr:8686");
+        assert_eq!(services[4].api_key, "readarr_key");
+        assert_eq!(services[5].name, "sonarr-4k");
+        assert_eq!(services[5].url, "http://sonarr-4k:8989");
     }
 
     #[test]
@@ -149,26 +255,48 @@ mod tests {
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "pass".to_string(),
             polling_interval: 10,
             port: 9091,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec![],
             uid: 1000,
             username: "user".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: None,
             radarr: None,
             whisparr: None,
+            lidarr: None,
+            readarr: None,
+            arr: vec![],
         };
 
         assert!(config.sonarr.is_none());
         assert!(config.radarr.is_none());
         assert!(config.whisparr.is_none());
+        assert!(config.arr_services().is_empty());
     }
 
     #[test]
@@ -185,6 +313,11 @@ polling_interval = 10
 skip_directories = ["sample", "extras"]
 orchestration_workers = 10
 download_workers = 4
+download_segments = 1
+min_segment_size_mb = 64
+max_download_attempts = 5
+retry_delay = 10
+max_retry_delay = 600
 
 [putio]
 api_key = "test_api_key"
@@ -197,7 +330,7 @@ api_key = "sonarr_key"
         let temp_file = NamedTempFile::new().unwrap();
         fs::write(temp_file.path(), toml_content).unwrap();
 
-        let config: Config = Figment::new()
+        let config: Config = crate::config_defaults()
             .merge(Toml::file(temp_file.path()))
             .extract()
             .unwrap();
@@ -212,18 +345,7 @@ api_key = "sonarr_key"
 
     #[test]
     fn test_config_with_defaults() {
-        let config: Config = Figment::new()
-            .join(Serialized::default("bind_address", "0.0.0.0"))
-            .join(Serialized::default("download_workers", 4))
-            .join(Serialized::default("orchestration_workers", 10))
-            .join(Serialized::default("loglevel", "info"))
-            .join(Serialized::default("polling_interval", 10))
-            .join(Serialized::default("port", 9091))
-            .join(Serialized::default("uid", 1000))
-            .join(Serialized::default(
-                "skip_directories",
-                vec!["sample", "extras"],
-            ))
+        let config: Config = crate::config_defaults()
             .join(Serialized::default("username", "default_user"))
             .join(Serialized::default("password", "default_pass"))
             .join(Serialized::default("download_directory", "/tmp"))
@@ -231,17 +353,29 @@ api_key = "sonarr_key"
                 "putio",
                 PutioConfig {
                     api_key: "default_key".to_string(),
+                    timeout: 10,
+                    max_retries: 3,
+                    api_url: putio::API_URL.to_string(),
+                    upload_url: putio::UPLOAD_URL.to_string(),
+                    folder: None,
                 },
             ))
             .extract()
             .unwrap();
 
         assert_eq!(config.bind_address, "0.0.0.0");
+        assert_eq!(config.download_segments, 1);
         assert_eq!(config.download_workers, 4);
         assert_eq!(config.orchestration_workers, 10);
         assert_eq!(config.loglevel, "info");
+        assert_eq!(config.max_download_attempts, 5);
+        assert_eq!(config.max_retry_delay, 600);
+        assert_eq!(config.min_segment_size_mb, 64);
+        assert!(!config.only_owned_transfers);
+        assert_eq!(config.retry_delay, 10);
         assert_eq!(config.polling_interval, 10);
         assert_eq!(config.port, 9091);
+        assert_eq!(config.shutdown_grace_period, 30);
         assert_eq!(config.uid, 1000);
         assert_eq!(config.skip_directories, vec!["sample", "extras"]);
     }
@@ -251,21 +385,42 @@ api_key = "sonarr_key"
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "pass".to_string(),
             polling_interval: 10,
             port: 9091,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec![],
             uid: 1000,
             username: "user".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: None,
             radarr: None,
             whisparr: None,
+            lidarr: None,
+            readarr: None,
+            arr: vec![],
         };
 
         assert!(config.skip_directories.is_empty());
@@ -276,12 +431,19 @@ api_key = "sonarr_key"
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "pass".to_string(),
             polling_interval: 10,
             port: 9091,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec![
                 "sample".to_string(),
                 "extras".to_string(),
@@ -290,12 +452,26 @@ api_key = "sonarr_key"
             ],
             uid: 1000,
             username: "user".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: None,
             radarr: None,
             whisparr: None,
+            lidarr: None,
+            readarr: None,
+            arr: vec![],
         };
 
         assert_eq!(config.skip_directories.len(), 4);
@@ -310,21 +486,42 @@ api_key = "sonarr_key"
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "pass".to_string(),
             polling_interval: 10,
             port: 8080,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec![],
             uid: 1000,
             username: "user".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: None,
             radarr: None,
             whisparr: None,
+            lidarr: None,
+            readarr: None,
+            arr: vec![],
         };
 
         assert_eq!(config.port, 8080);
@@ -338,21 +535,42 @@ api_key = "sonarr_key"
             let config = Config {
                 bind_address: addr.to_string(),
                 download_directory: "/downloads".to_string(),
+                download_segments: 1,
                 download_workers: 4,
                 loglevel: "info".to_string(),
+                max_download_attempts: 5,
+                max_retry_delay: 600,
+                min_segment_size_mb: 64,
+                only_owned_transfers: false,
                 orchestration_workers: 10,
                 password: "pass".to_string(),
                 polling_interval: 10,
                 port: 9091,
+                retry_delay: 10,
+                shutdown_grace_period: 30,
                 skip_directories: vec![],
                 uid: 1000,
                 username: "user".to_string(),
+                cleanup: CleanupConfig::default(),
+                extract: ExtractConfig::default(),
+                file_filter: FileFilterConfig::default(),
+                notifications: NotificationsConfig::default(),
+                post_download: PostDownloadConfig::default(),
                 putio: PutioConfig {
                     api_key: "key".to_string(),
+                    timeout: 10,
+                    max_retries: 3,
+                    api_url: putio::API_URL.to_string(),
+                    upload_url: putio::UPLOAD_URL.to_string(),
+                    folder: None,
                 },
+                seeding: SeedingConfig::default(),
                 sonarr: None,
                 radarr: None,
                 whisparr: None,
+                lidarr: None,
+                readarr: None,
+                arr: vec![],
             };
 
             assert_eq!(config.bind_address, addr);
@@ -367,21 +585,42 @@ api_key = "sonarr_key"
             let config = Config {
                 bind_address: "0.0.0.0".to_string(),
                 download_directory: "/downloads".to_string(),
+                download_segments: 1,
                 download_workers: 4,
                 loglevel: level.to_string(),
+                max_download_attempts: 5,
+                max_retry_delay: 600,
+                min_segment_size_mb: 64,
+                only_owned_transfers: false,
                 orchestration_workers: 10,
                 password: "pass".to_string(),
                 polling_interval: 10,
                 port: 9091,
+                retry_delay: 10,
+                shutdown_grace_period: 30,
                 skip_directories: vec![],
                 uid: 1000,
                 username: "user".to_string(),
+                cleanup: CleanupConfig::default(),
+                extract: ExtractConfig::default(),
+                file_filter: FileFilterConfig::default(),
+                notifications: NotificationsConfig::default(),
+                post_download: PostDownloadConfig::default(),
                 putio: PutioConfig {
                     api_key: "key".to_string(),
+                    timeout: 10,
+                    max_retries: 3,
+                    api_url: putio::API_URL.to_string(),
+                    upload_url: putio::UPLOAD_URL.to_string(),
+                    folder: None,
                 },
+                seeding: SeedingConfig::default(),
                 sonarr: None,
                 radarr: None,
                 whisparr: None,
+                lidarr: None,
+                readarr: None,
+                arr: vec![],
             };
 
             assert_eq!(config.loglevel, level);
@@ -393,21 +632,42 @@ api_key = "sonarr_key"
         let config = Config {
             bind_address: "0.0.0.0".to_string(),
             download_directory: "/downloads".to_string(),
+            download_segments: 1,
             download_workers: 4,
             loglevel: "info".to_string(),
+            max_download_attempts: 5,
+            max_retry_delay: 600,
+            min_segment_size_mb: 64,
+            only_owned_transfers: false,
             orchestration_workers: 10,
             password: "testpass".to_string(),
             polling_interval: 10,
             port: 9091,
+            retry_delay: 10,
+            shutdown_grace_period: 30,
             skip_directories: vec!["sample".to_string()],
             uid: 1000,
             username: "testuser".to_string(),
+            cleanup: CleanupConfig::default(),
+            extract: ExtractConfig::default(),
+            file_filter: FileFilterConfig::default(),
+            notifications: NotificationsConfig::default(),
+            post_download: PostDownloadConfig::default(),
             putio: PutioConfig {
                 api_key: "test_key".to_string(),
+                timeout: 10,
+                max_retries: 3,
+                api_url: putio::API_URL.to_string(),
+                upload_url: putio::UPLOAD_URL.to_string(),
+                folder: None,
             },
+            seeding: SeedingConfig::default(),
             sonarr: None,
             radarr: None,
             whisparr: None,
+            lidarr: None,
+            readarr: None,
+            arr: vec![],
         };
 
         let cloned = config.clone();
@@ -421,6 +681,11 @@ api_key = "sonarr_key"
     fn test_putio_config_clone() {
         let putio = PutioConfig {
             api_key: "test_key".to_string(),
+            timeout: 10,
+            max_retries: 3,
+            api_url: putio::API_URL.to_string(),
+            upload_url: putio::UPLOAD_URL.to_string(),
+            folder: None,
         };
 
         let cloned = putio.clone();
@@ -432,10 +697,365 @@ api_key = "sonarr_key"
         let arr = ArrConfig {
             url: "http://localhost:8989".to_string(),
             api_key: "test_key".to_string(),
+            category: None,
         };
 
         let cloned = arr.clone();
         assert_eq!(arr.url, cloned.url);
         assert_eq!(arr.api_key, cloned.api_key);
     }
+
+    #[test]
+    fn test_config_putio_urls_default() {
+        let json = r#"{"api_key": "key"}"#;
+        let putio: PutioConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(putio.api_url, "https://api.put.io/v2");
+        assert_eq!(putio.upload_url, "https://upload.put.io/v2");
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn test_config_putio_urls_from_env() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.toml",
+                r#"
+username = "user"
+password = "pass"
+download_directory = "/downloads"
+bind_address = "0.0.0.0"
+port = 9091
+loglevel = "info"
+uid = 1000
+polling_interval = 10
+skip_directories = []
+orchestration_workers = 10
+download_workers = 4
+download_segments = 1
+min_segment_size_mb = 64
+max_download_attempts = 5
+retry_delay = 10
+max_retry_delay = 600
+
+[putio]
+api_key = "key"
+api_url = "https://putio.example.com/v2"
+upload_url = "https://upload.example.com/v2"
+"#,
+            )?;
+            jail.set_env("PUTIO_API_URL", "http://127.0.0.1:8080/v2");
+            jail.set_env("PUTIO_API_KEY", "ignored");
+
+            let config: Config = crate::config_defaults()
+                .merge(Toml::file("config.toml"))
+                .merge(crate::putio_env())
+                .extract()?;
+            assert_eq!(config.putio.api_url, "http://127.0.0.1:8080/v2");
+            assert_eq!(config.putio.upload_url, "https://upload.example.com/v2");
+            assert_eq!(config.putio.api_key, "key");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_config_cleanup_toml() {
+        let toml_content = r#"
+[cleanup]
+local = "move"
+completed_directory = "/completed"
+remote = "keep"
+dry_run = true
+"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), toml_content).unwrap();
+
+        let cleanup: CleanupConfig = crate::config_defaults()
+            .merge(Toml::file(temp_file.path()))
+            .extract_inner("cleanup")
+            .unwrap();
+        assert_eq!(cleanup.local, LocalCleanup::Move);
+        assert_eq!(cleanup.completed_directory, Some("/completed".to_string()));
+        assert_eq!(cleanup.delete_after_hours, 24);
+        assert_eq!(cleanup.remote, RemoteCleanup::Keep);
+        assert!(cleanup.dry_run);
+    }
+
+    #[test]
+    fn test_config_cleanup_defaults() {
+        let cleanup: CleanupConfig = crate::config_defaults().extract_inner("cleanup").unwrap();
+        assert_eq!(cleanup.local, LocalCleanup::Delete);
+        assert_eq!(cleanup.completed_directory, None);
+        assert_eq!(cleanup.delete_after_hours, 24);
+        assert_eq!(cleanup.remote, RemoteCleanup::Delete);
+        assert!(!cleanup.dry_run);
+    }
+
+    #[test]
+    fn test_config_validate_move_requires_completed_directory() {
+        let mut config: Config = crate::config_defaults()
+            .merge(Serialized::defaults(serde_json::json!({
+                "bind_address": "0.0.0.0",
+                "download_directory": "/downloads",
+                "download_segments": 1,
+                "download_workers": 4,
+                "loglevel": "info",
+                "max_download_attempts": 5,
+                "max_retry_delay": 600,
+                "min_segment_size_mb": 64,
+                "orchestration_workers": 10,
+                "password": "pass",
+                "polling_interval": 10,
+                "port": 9091,
+                "retry_delay": 10,
+                "skip_directories": [],
+                "uid": 1000,
+                "username": "user",
+                "putio": {"api_key": "key"},
+            })))
+            .extract()
+            .unwrap();
+        assert!(config.validate().is_ok());
+        config.cleanup.local = LocalCleanup::Move;
+        assert!(config.validate().is_err());
+        config.cleanup.completed_directory = Some("/completed".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_notifications_toml() {
+        let toml_content = r#"
+[notifications]
+import_timeout = 120
+
+[[notifications.notifiers]]
+kind = "discord"
+url = "https://discord.com/api/webhooks/123/abc"
+events = ["failed", "import_stalled"]
+
+[[notifications.notifiers]]
+kind = "exec"
+command = "/config/notify.sh"
+"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), toml_content).unwrap();
+
+        let notifications: NotificationsConfig = Figment::new()
+            .merge(Toml::file(temp_file.path()))
+            .extract_inner("notifications")
+            .unwrap();
+        assert_eq!(notifications.import_timeout, 120);
+        assert_eq!(notifications.notifiers.len(), 2);
+        let discord = &notifications.notifiers[0];
+        assert_eq!(discord.kind, NotifierKind::Discord);
+        assert_eq!(
+            discord.events,
+            vec![EventKind::Failed, EventKind::ImportStalled]
+        );
+        let exec = &notifications.notifiers[1];
+        assert_eq!(exec.kind, NotifierKind::Exec);
+        assert_eq!(exec.command, Some("/config/notify.sh".to_string()));
+        assert!(exec.events.is_empty());
+    }
+
+    #[test]
+    fn test_config_notifications_defaults() {
+        let notifications: NotificationsConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(notifications.import_timeout, 24 * 60);
+        assert!(notifications.notifiers.is_empty());
+    }
+
+    #[test]
+    fn test_config_extract_toml() {
+        let toml_content = r#"
+[extract]
+enabled = true
+unrar = "/usr/local/bin/unrar"
+delete_archives = false
+"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), toml_content).unwrap();
+
+        let extract: ExtractConfig = Figment::new()
+            .merge(Toml::file(temp_file.path()))
+            .extract_inner("extract")
+            .unwrap();
+        assert!(extract.enabled);
+        assert_eq!(extract.unrar, "/usr/local/bin/unrar");
+        assert_eq!(extract.timeout, 3600);
+        assert!(!extract.delete_archives);
+    }
+
+    #[test]
+    fn test_config_extract_defaults() {
+        let extract: ExtractConfig = serde_json::from_str("{}").unwrap();
+        assert!(!extract.enabled);
+        assert_eq!(extract.unrar, "unrar");
+        assert!(extract.delete_archives);
+    }
+
+    #[test]
+    fn test_config_validate_notifiers() {
+        let mut config: Config = crate::config_defaults()
+            .merge(Serialized::defaults(serde_json::json!({
+                "bind_address": "0.0.0.0",
+                "download_directory": "/downloads",
+                "download_segments": 1,
+                "download_workers": 4,
+                "loglevel": "info",
+                "max_download_attempts": 5,
+                "max_retry_delay": 600,
+                "min_segment_size_mb": 64,
+                "orchestration_workers": 10,
+                "password": "pass",
+                "polling_interval": 10,
+                "port": 9091,
+                "retry_delay": 10,
+                "skip_directories": [],
+                "uid": 1000,
+                "username": "user",
+                "putio": {"api_key": "key"},
+                "notifications": {
+                    "notifiers": [{"kind": "slack"}, {"kind": "exec"}],
+                },
+            })))
+            .extract()
+            .unwrap();
+        assert!(config.validate().is_err());
+        config.notifications.notifiers[0].url = Some("https://hooks.slack.com/x".to_string());
+        assert!(config.validate().is_err());
+        config.notifications.notifiers[1].command = Some("true".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_file_filter_defaults() {
+        let filter = FileFilterConfig::default();
+        assert_eq!(filter.include_file_types, vec!["VIDEO"]);
+        assert!(filter.include_extensions.contains(&"srt".to_string()));
+        assert!(filter.include_extensions.contains(&"nfo".to_string()));
+        assert!(filter.exclude_globs.is_empty());
+    }
+
+    #[test]
+    fn test_config_file_filter_toml_keeps_unset_defaults() {
+        let toml_content = r#"
+username = "user"
+password = "pass"
+download_directory = "/downloads"
+bind_address = "0.0.0.0"
+port = 9091
+loglevel = "info"
+uid = 1000
+polling_interval = 10
+skip_directories = []
+orchestration_workers = 10
+download_workers = 4
+download_segments = 1
+min_segment_size_mb = 64
+max_download_attempts = 5
+retry_delay = 10
+max_retry_delay = 600
+
+[file_filter]
+include_file_types = ["VIDEO", "AUDIO"]
+exclude_globs = ["*sample*"]
+
+[putio]
+api_key = "key"
+"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), toml_content).unwrap();
+
+        let config: Config = crate::config_defaults()
+            .merge(Toml::file(temp_file.path()))
+            .extract()
+            .unwrap();
+        assert_eq!(
+            config.file_filter.include_file_types,
+            vec!["VIDEO", "AUDIO"]
+        );
+        assert_eq!(
+            config.file_filter.include_extensions,
+            FileFilterConfig::default().include_extensions
+        );
+        assert_eq!(config.file_filter.exclude_globs, vec!["*sample*"]);
+    }
+
+    #[test]
+    fn test_config_toml_arr_instances() {
+        let toml_content = r#"
+username = "user"
+password = "pass"
+download_directory = "/downloads"
+bind_address = "0.0.0.0"
+port = 9091
+loglevel = "info"
+uid = 1000
+polling_interval = 10
+skip_directories = []
+orchestration_workers = 10
+download_workers = 4
+download_segments = 1
+min_segment_size_mb = 64
+max_download_attempts = 5
+retry_delay = 10
+max_retry_delay = 600
+
+[putio]
+api_key = "key"
+
+[radarr]
+url = "http://radarr:7878"
+api_key = "radarr_key"
+
+[[arr]]
+name = "sonarr-hd"
+kind = "sonarr"
+url = "http://sonarr-hd:8989"
+api_key = "hd_key"
+
+[[arr]]
+name = "sonarr-anime"
+kind = "sonarr"
+url = "http://sonarr-anime:8989"
+api_key = "anime_key"
+category = "anime"
+"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), toml_content).unwrap();
+
+        let config: Config = crate::config_defaults()
+            .merge(Toml::file(temp_file.path()))
+            .extract()
+            .unwrap();
+        let services = config.arr_services();
+        assert_eq!(
+            services.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            vec!["Radarr", "sonarr-hd", "sonarr-anime"]
+        );
+        assert_eq!(services[1].kind, ArrKind::Sonarr);
+        assert_eq!(services[2].url, "http://sonarr-anime:8989");
+        assert_eq!(services[2].api_key, "anime_key");
+        assert_eq!(services[0].category, None);
+        assert_eq!(services[2].category, Some("anime".to_string()));
+        assert_eq!(config.category_directory(None), "/downloads");
+        assert_eq!(config.category_directory(Some("anime")), "/downloads/anime");
+    }
+
+    #[test]
+    fn test_config_toml_arr_instance_requires_kind() {
+        let toml_content = r#"
+[[arr]]
+name = "sonarr-hd"
+url = "http://sonarr-hd:8989"
+api_key = "hd_key"
+"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), toml_content).unwrap();
+
+        let result: Result<Vec<ArrInstanceConfig>, _> = Figment::new()
+            .merge(Toml::file(temp_file.path()))
+            .extract_inner("arr");
+        assert!(result.is_err());
+    }
 }
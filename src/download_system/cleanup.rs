@@ -0,0 +1,171 @@
+use super::transfer::{DownloadTarget, Transfer};
+use crate::AppData;
+use anyhow::{bail, Result};
+use chrono::{TimeDelta, Utc};
+use colored::*;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// What happens to the downloaded files of a transfer once it has been imported.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalCleanup {
+    #[default]
+    Delete,
+    Keep,
+    /// Move to `completed_directory`.
+    Move,
+    /// Delete `delete_after_hours` after the import.
+    DeleteAfter,
+}
+
+/// What happens to the put.io files of a transfer once it's done seeding.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteCleanup {
+    #[default]
+    Delete,
+    Keep,
+}
+
+/// Cleans up the downloaded `target` of an imported transfer.
+pub async fn local(app_data: &AppData, target: &DownloadTarget) -> Result<()> {
+    let cleanup = &app_data.config.cleanup;
+    match cleanup.local {
+        LocalCleanup::Delete => remove(target, cleanup.dry_run),
+        LocalCleanup::Keep => {
+            info!("{}: keeping local files", target);
+            Ok(())
+        }
+        LocalCleanup::Move => {
+            let Some(completed) = &cleanup.completed_directory else {
+                bail!("no completed_directory to move to");
+            };
+            let to = completed_path(&app_data.config.download_directory, completed, &target.to);
+            if cleanup.dry_run {
+                info!("{}: would move to {} (dry run)", target, to.display());
+                return Ok(());
+            }
+            move_path(Path::new(&target.to), &to)?;
+            info!("{}: moved to {}", target, to.display());
+            Ok(())
+        }
+        LocalCleanup::DeleteAfter => {
+            let hours = cleanup.delete_after_hours;
+            info!("{}: deleting in {} hours", target, hours);
+            let delete_at = Utc::now() + TimeDelta::hours(hours as i64);
+            app_data.state.schedule_deletion(target, delete_at);
+            Ok(())
+        }
+    }
+}
+
+/// Deletes the downloads whose `delete_after_hours` have passed.
+pub fn delete_due(app_data: &AppData) {
+    for target in app_data.state.take_due_deletions() {
+        if let Err(e) = remove(&target, app_data.config.cleanup.dry_run) {
+            warn!("{}: unable to delete: {}", target, e);
+        }
+    }
+}
+
+/// Removes `transfer` from put.io once it's done seeding, along with its files unless they are
+/// to be kept.
+pub async fn remote(app_data: &AppData, transfer: &Transfer) -> Result<()> {
+    let cleanup = &app_data.config.cleanup;
+    let delete_files = cleanup.remote == RemoteCleanup::Delete;
+    if cleanup.dry_run {
+        info!("{}: would remove from put.io (dry run)", transfer);
+        if delete_files {
+            info!("{}: would delete remote files (dry run)", transfer);
+        }
+        return Ok(());
+    }
+
+    app_data.putio.remove_transfer(transfer.transfer_id).await?;
+    info!("{}: removed from put.io", transfer);
+    if !delete_files {
+        info!("{}: keeping remote files", transfer);
+        return Ok(());
+    }
+    match transfer.file_id {
+        Some(file_id) => match app_data.putio.delete_file(file_id).await {
+            Ok(_) => {
+                info!("{}: deleted remote files", transfer);
+            }
+            Err(_) => {
+                warn!("{}: unable to delete remote files", transfer);
+            }
+        },
+        None => warn!("{}: no remote files to delete", transfer),
+    }
+    Ok(())
+}
+
+fn remove(target: &DownloadTarget, dry_run: bool) -> Result<()> {
+    let path = Path::new(&target.to);
+    if dry_run {
+        info!("{}: would delete (dry run)", target);
+        return Ok(());
+    }
+    match fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => fs::remove_dir_all(path)?,
+        Ok(_) => fs::remove_file(path)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            warn!("{}: already gone", target);
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    }
+    info!("{}: {}", target, "deleted".red());
+    Ok(())
+}
+
+/// Where `path` ends up in `completed_directory`, keeping its place relative to the download
+/// directory, so categories stay apart.
+pub fn completed_path(download_directory: &str, completed_directory: &str, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let relative = path
+        .strip_prefix(download_directory)
+        .ok()
+        .or_else(|| path.file_name().map(Path::new))
+        .unwrap_or(path);
+    Path::new(completed_directory).join(relative)
+}
+
+/// Moves `from` to `to`, copying when they're on different file systems.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        bail!("{} already exists", to.display());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_path(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)?;
+    } else {
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
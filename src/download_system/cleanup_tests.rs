@@ -0,0 +1,230 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        cleanup::*,
+        transfer::{DownloadTarget, TargetType, Transfer},
+    };
+    use crate::{
+        test_support::{
+            self,
+            putio::{FakePutio, ROOT},
+        },
+        AppData, CleanupConfig, Config,
+    };
+    use actix_web::web;
+    use chrono::{TimeDelta, Utc};
+    use std::{fs, path::Path};
+    use tempfile::TempDir;
+
+    fn create_app_data(
+        putio: &FakePutio,
+        dir: &Path,
+        cleanup: CleanupConfig,
+    ) -> web::Data<AppData> {
+        let config = test_support::config(&putio.url(), &putio.upload_url());
+        test_support::with_config(Config {
+            download_directory: dir.join("downloads").to_string_lossy().to_string(),
+            cleanup,
+            ..config
+        })
+    }
+
+    /// Creates a downloaded `downloads/tv/Show/episode.mkv`.
+    fn create_download(dir: &Path) -> DownloadTarget {
+        let show = dir.join("downloads").join("tv").join("Show");
+        fs::create_dir_all(&show).unwrap();
+        fs::write(show.join("episode.mkv"), b"episode").unwrap();
+        DownloadTarget {
+            from: None,
+            to: show.to_string_lossy().to_string(),
+            target_type: TargetType::Directory,
+            top_level: true,
+            transfer_hash: "abcd1234".to_string(),
+        }
+    }
+
+    fn create_transfer(app_data: web::Data<AppData>, id: u64, file_id: i64) -> Transfer {
+        Transfer {
+            name: "Show".to_string(),
+            file_id: Some(file_id),
+            hash: Some("abcd1234".to_string()),
+            transfer_id: id,
+            targets: None,
+            app_data,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_local_delete() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, dir.path(), CleanupConfig::default());
+        let target = create_download(dir.path());
+
+        local(&app_data, &target).await.unwrap();
+        assert!(!Path::new(&target.to).exists());
+        // Deleting what's already gone is fine.
+        local(&app_data, &target).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_local_keep() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let cleanup = CleanupConfig {
+            local: LocalCleanup::Keep,
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let target = create_download(dir.path());
+
+        local(&app_data, &target).await.unwrap();
+        assert!(Path::new(&target.to).join("episode.mkv").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_local_move() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let completed = dir.path().join("completed");
+        let cleanup = CleanupConfig {
+            local: LocalCleanup::Move,
+            completed_directory: Some(completed.to_string_lossy().to_string()),
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let target = create_download(dir.path());
+
+        local(&app_data, &target).await.unwrap();
+        assert!(!Path::new(&target.to).exists());
+        let episode = completed.join("tv").join("Show").join("episode.mkv");
+        assert_eq!(fs::read(episode).unwrap(), b"episode");
+
+        // Never overwrite what's already there.
+        let target = create_download(dir.path());
+        assert!(local(&app_data, &target).await.is_err());
+        assert!(Path::new(&target.to).exists());
+    }
+
+    #[actix_web::test]
+    async fn test_local_delete_after() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let cleanup = CleanupConfig {
+            local: LocalCleanup::DeleteAfter,
+            delete_after_hours: 0,
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let target = create_download(dir.path());
+
+        local(&app_data, &target).await.unwrap();
+        assert!(Path::new(&target.to).exists());
+        assert_eq!(app_data.state.scheduled_deletions().len(), 1);
+
+        delete_due(&app_data);
+        assert!(!Path::new(&target.to).exists());
+        assert!(app_data.state.scheduled_deletions().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_local_delete_after_waits() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let cleanup = CleanupConfig {
+            local: LocalCleanup::DeleteAfter,
+            delete_after_hours: 24,
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let target = create_download(dir.path());
+
+        local(&app_data, &target).await.unwrap();
+        delete_due(&app_data);
+        assert!(Path::new(&target.to).exists());
+        let deletions = app_data.state.scheduled_deletions();
+        assert_eq!(deletions.len(), 1);
+        assert_eq!(deletions[0].target.to, target.to);
+        assert!(deletions[0].delete_at > Utc::now() + TimeDelta::hours(23));
+    }
+
+    #[actix_web::test]
+    async fn test_local_dry_run() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let cleanup = CleanupConfig {
+            dry_run: true,
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let target = create_download(dir.path());
+
+        local(&app_data, &target).await.unwrap();
+        assert!(Path::new(&target.to).join("episode.mkv").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_remote_delete() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, dir.path(), CleanupConfig::default());
+        let file = putio.add_file(ROOT, "Show", "FOLDER", b"");
+        let id = putio.add_transfer("Show", "abcd1234", "COMPLETED");
+
+        remote(&app_data, &create_transfer(app_data.clone(), id, file))
+            .await
+            .unwrap();
+        assert!(putio.transfer(id).is_none());
+        assert!(!putio.file_exists(file));
+    }
+
+    #[actix_web::test]
+    async fn test_remote_keep() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let cleanup = CleanupConfig {
+            remote: RemoteCleanup::Keep,
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let file = putio.add_file(ROOT, "Show", "FOLDER", b"");
+        let id = putio.add_transfer("Show", "abcd1234", "COMPLETED");
+
+        remote(&app_data, &create_transfer(app_data.clone(), id, file))
+            .await
+            .unwrap();
+        assert!(putio.transfer(id).is_none());
+        assert!(putio.file_exists(file));
+    }
+
+    #[actix_web::test]
+    async fn test_remote_dry_run() {
+        let putio = FakePutio::start().await;
+        let dir = TempDir::new().unwrap();
+        let cleanup = CleanupConfig {
+            dry_run: true,
+            ..CleanupConfig::default()
+        };
+        let app_data = create_app_data(&putio, dir.path(), cleanup);
+        let file = putio.add_file(ROOT, "Show", "FOLDER", b"");
+        let id = putio.add_transfer("Show", "abcd1234", "COMPLETED");
+
+        remote(&app_data, &create_transfer(app_data.clone(), id, file))
+            .await
+            .unwrap();
+        assert!(putio.transfer(id).is_some());
+        assert!(putio.file_exists(file));
+    }
+
+    #[test]
+    fn test_completed_path() {
+        assert_eq!(
+            completed_path("/downloads", "/completed", "/downloads/tv/Show"),
+            Path::new("/completed/tv/Show")
+        );
+        assert_eq!(
+            completed_path("/downloads", "/completed", "/elsewhere/Show"),
+            Path::new("/completed/Show")
+        );
+    }
+}
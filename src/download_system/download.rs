@@ -1,48 +1,81 @@
-use super::transfer::{DownloadTarget, TargetType};
-use crate::AppData;
+use super::{
+    progress::ProgressTracker,
+    transfer::{DownloadTarget, TargetType},
+};
+use crate::{metrics::METRICS, AppData, Config};
+use actix_rt::task::JoinHandle;
 use actix_web::web::Data;
 use anyhow::{bail, Context, Result};
 use async_channel::{Receiver, Sender};
 use colored::*;
 use file_owner::PathExt;
 use futures::StreamExt;
-use log::{error, info};
+use log::{error, info, warn};
 use nix::unistd::Uid;
-use std::{fs, path::Path};
+use prometheus::IntCounter;
+use reqwest::{
+    header::{CONTENT_RANGE, RANGE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::SeekFrom, path::Path, sync::Mutex};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
 #[derive(Clone)]
 pub struct Worker {
-    _id: usize,
+    id: usize,
     app_data: Data<AppData>,
     drx: Receiver<DownloadTargetMessage>,
 }
 
 impl Worker {
-    pub fn start(id: usize, app_data: Data<AppData>, drx: Receiver<DownloadTargetMessage>) {
-        let s = Self {
-            _id: id,
-            app_data,
-            drx,
-        };
+    pub fn start(
+        id: usize,
+        app_data: Data<AppData>,
+        drx: Receiver<DownloadTargetMessage>,
+    ) -> JoinHandle<Result<()>> {
+        let s = Self { id, app_data, drx };
 
-        let _join_handle = actix_rt::spawn(async move { s.work().await });
+        actix_rt::spawn(async move { s.work().await })
     }
     async fn work(&self) -> Result<()> {
         loop {
-            // Wait for a DownloadTarget
-            let dtm = self.drx.recv().await?;
+            // Wait for a DownloadTarget, unless we're shutting down. A download in progress is
+            // finished first.
+            let dtm = tokio::select! {
+                biased;
+                _ = self.app_data.shutdown.cancelled() => return Ok(()),
+                dtm = self.drx.recv() => dtm?,
+            };
 
             // Download the target
-            let done_status = match download_target(&self.app_data, &dtm.download_target).await {
-                Ok(_) => DownloadDoneStatus::Success,
-                Err(_) => DownloadDoneStatus::Failed,
-            };
+            let done_status =
+                match download_target(&self.app_data, self.id, &dtm.download_target).await {
+                    Ok(_) => DownloadDoneStatus::Success,
+                    Err(e) => DownloadDoneStatus::Failed(e.to_string()),
+                };
             dtm.tx.send(done_status).await?;
         }
     }
 }
 
-async fn download_target(app_data: &Data<AppData>, target: &DownloadTarget) -> Result<()> {
+async fn download_target(
+    app_data: &Data<AppData>,
+    worker: usize,
+    target: &DownloadTarget,
+) -> Result<()> {
+    // Transfers with a category go into a directory of their own, which may not exist yet.
+    if target.top_level {
+        if let Some(parent) = Path::new(&target.to).parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+                if Uid::effective().is_root() {
+                    parent.set_owner(app_data.config.uid)?;
+                }
+            }
+        }
+    }
+
     match target.target_type {
         TargetType::Directory => {
             if !Path::new(&target.to).exists() {
@@ -57,10 +90,16 @@ async fn download_target(app_data: &Data<AppData>, target: &DownloadTarget) -> R
             // Delete file if already exists
             if !Path::new(&target.to).exists() {
                 info!("{}: download {}", &target, "started".yellow());
-                match fetch(target, app_data.config.uid).await {
+                let options = FetchOptions {
+                    worker,
+                    ..FetchOptions::from(&app_data.config)
+                };
+                match fetch(target, &options, &app_data.progress).await {
                     Ok(_) => info!("{}: download {}", &target, "succeeded".green()),
                     Err(e) => {
                         error!("{}: download {}: {}", &target, "failed".red(), e);
+                        METRICS.download_failures.inc();
+                        app_data.errors.record(&target.to, &e.to_string());
                         bail!(e)
                     }
                 };
@@ -72,25 +111,365 @@ async fn download_target(app_data: &Data<AppData>, target: &DownloadTarget) -> R
     Ok(())
 }
 
-async fn fetch(target: &DownloadTarget, uid: u32) -> Result<()> {
-    let tmp_path = format!("{}.downloading", &target.to);
-    let mut tmp_file = tokio::fs::File::create(&tmp_path).await?;
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// The download worker, for the metrics.
+    pub worker: usize,
+    pub uid: u32,
+    pub segments: usize,
+    pub min_segment_size: u64,
+}
+
+impl From<&Config> for FetchOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            worker: 0,
+            uid: config.uid,
+            segments: config.download_segments,
+            min_segment_size: config.min_segment_size_mb * 1024 * 1024,
+        }
+    }
+}
 
+pub async fn fetch(
+    target: &DownloadTarget,
+    options: &FetchOptions,
+    progress: &ProgressTracker,
+) -> Result<()> {
+    let tmp_path = format!("{}.downloading", &target.to);
     let url = target.from.clone().context("No URL found")?;
-    let mut byte_stream = reqwest::get(url).await?.bytes_stream();
+    let client = reqwest::Client::new();
+    let uid = options.uid;
+    let downloaded = METRICS
+        .downloaded_bytes
+        .with_label_values(&[&options.worker.to_string()]);
+
+    if let Some(plan) = plan_segments(&client, &url, &tmp_path, options).await? {
+        return fetch_segmented(&client, target, &url, &tmp_path, plan, options, progress).await;
+    }
+
+    // Pick up where a previous attempt left off, if there is a partial download.
+    let existing = match tokio::fs::metadata(&tmp_path).await {
+        Ok(m) if m.is_file() => m.len(),
+        _ => 0,
+    };
+
+    let mut request = client.get(&url);
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+    let mut response = request.send().await?;
+
+    if existing > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial might already hold the whole file.
+        if content_range_total(&response) == Some(existing) {
+            info!("{}: partial download already complete", &target);
+            progress.start(target, existing, existing);
+            return finish(&tmp_path, &target.to, uid);
+        }
+        response = client.get(&url).send().await?;
+    }
+
+    if !response.status().is_success() {
+        bail!("url: {}, status: {}", url, response.status());
+    }
+
+    let remaining = response.content_length().unwrap_or(0);
+    let mut tmp_file = if existing > 0 && is_resumed_at(&response, existing) {
+        info!("{}: resuming download at {} bytes", &target, existing);
+        progress.start(target, existing + remaining, existing);
+        tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&tmp_path)
+            .await?
+    } else {
+        if existing > 0 {
+            warn!("{}: server refused range request, restarting", &target);
+        }
+        progress.start(target, remaining, 0);
+        tokio::fs::File::create(&tmp_path).await?
+    };
+
+    let mut byte_stream = response.bytes_stream();
+    while let Some(item) = byte_stream.next().await {
+        let item = item?;
+        tokio::io::copy(&mut item.as_ref(), &mut tmp_file).await?;
+        progress.advance(target, item.len() as u64);
+        downloaded.inc_by(item.len() as u64);
+    }
+    tmp_file.flush().await?;
+    drop(tmp_file);
+    progress.finish(target);
+
+    finish(&tmp_path, &target.to, uid)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Segment {
+    pub start: u64,
+    pub end: u64,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SegmentPlan {
+    pub size: u64,
+    pub segments: Vec<Segment>,
+}
+
+impl SegmentPlan {
+    /// Splits `size` bytes into at most `max_segments` ranges of at least `min_segment_size` bytes.
+    pub fn new(size: u64, max_segments: usize, min_segment_size: u64) -> Self {
+        let count = (size / min_segment_size.max(1)).clamp(1, max_segments.max(1) as u64);
+        let segment_size = size.div_ceil(count);
+        let segments = (0..count)
+            .map(|i| i * segment_size)
+            .take_while(|start| *start < size)
+            .map(|start| Segment {
+                start,
+                end: (start + segment_size).min(size) - 1,
+                done: false,
+            })
+            .collect();
+        Self { size, segments }
+    }
+}
+
+fn segments_path(tmp_path: &str) -> String {
+    format!("{}.segments", tmp_path)
+}
+
+/// Decides whether a file should be fetched in segments. Returns a plan when an earlier
+/// segmented attempt can be continued, even if segments have been turned off since, or when
+/// segments are enabled, the server supports ranges and the file is large enough to be split.
+async fn plan_segments(
+    client: &reqwest::Client,
+    url: &str,
+    tmp_path: &str,
+    options: &FetchOptions,
+) -> Result<Option<SegmentPlan>> {
+    let plan_path = segments_path(tmp_path);
+    let tmp_exists = Path::new(tmp_path).exists();
+
+    if let Ok(contents) = tokio::fs::read(&plan_path).await {
+        if tmp_exists {
+            if let Ok(plan) = serde_json::from_slice::<SegmentPlan>(&contents) {
+                return Ok(Some(plan));
+            }
+            // The preallocated partial can't be trusted without its plan.
+            tokio::fs::remove_file(tmp_path).await?;
+        }
+        tokio::fs::remove_file(&plan_path).await?;
+    } else if tmp_exists {
+        // A partial without a plan belongs to a single stream download, let that resume it.
+        return Ok(None);
+    }
+    if options.segments < 2 {
+        return Ok(None);
+    }
+
+    let response = client.get(url).header(RANGE, "bytes=0-0").send().await?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Ok(None);
+    }
+    let size = match content_range_total(&response) {
+        Some(size) => size,
+        None => return Ok(None),
+    };
+
+    let plan = SegmentPlan::new(size, options.segments, options.min_segment_size);
+    if plan.segments.len() < 2 {
+        return Ok(None);
+    }
+
+    let file = tokio::fs::File::create(tmp_path).await?;
+    file.set_len(size).await?;
+    tokio::fs::write(&plan_path, serde_json::to_vec(&plan)?).await?;
+
+    Ok(Some(plan))
+}
+
+async fn fetch_segmented(
+    client: &reqwest::Client,
+    target: &DownloadTarget,
+    url: &str,
+    tmp_path: &str,
+    plan: SegmentPlan,
+    options: &FetchOptions,
+    progress: &ProgressTracker,
+) -> Result<()> {
+    let plan_path = segments_path(tmp_path);
+    let downloaded = &METRICS
+        .downloaded_bytes
+        .with_label_values(&[&options.worker.to_string()]);
+    let pending: Vec<usize> = plan
+        .segments
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !s.done)
+        .map(|(i, _)| i)
+        .collect();
+    info!(
+        "{}: downloading {} of {} segments",
+        &target,
+        pending.len(),
+        plan.segments.len()
+    );
+    let done = plan
+        .segments
+        .iter()
+        .filter(|s| s.done)
+        .map(|s| s.end - s.start + 1)
+        .sum();
+    progress.start(target, plan.size, done);
+
+    let plan = &Mutex::new(plan);
+    let plan_path = &plan_path;
+    let results = futures::future::join_all(pending.into_iter().map(|i| async move {
+        let segment = plan.lock().unwrap().segments[i].clone();
+        fetch_segment(
+            client, url, tmp_path, &segment, target, progress, downloaded,
+        )
+        .await?;
+
+        // Record progress so an interrupted download only refetches unfinished segments. The
+        // lock is held while writing so concurrent segments can't interleave their updates.
+        let mut plan = plan.lock().unwrap();
+        plan.segments[i].done = true;
+        fs::write(plan_path, serde_json::to_vec(&*plan)?)?;
+        Ok::<(), anyhow::Error>(())
+    }))
+    .await;
+
+    for result in results {
+        result?;
+    }
+
+    tokio::fs::remove_file(plan_path).await?;
+    progress.finish(target);
+    finish(tmp_path, &target.to, options.uid)
+}
+
+async fn fetch_segment(
+    client: &reqwest::Client,
+    url: &str,
+    tmp_path: &str,
+    segment: &Segment,
+    target: &DownloadTarget,
+    progress: &ProgressTracker,
+    downloaded: &IntCounter,
+) -> Result<()> {
+    let response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", segment.start, segment.end))
+        .send()
+        .await?;
 
+    if !is_resumed_at(&response, segment.start) {
+        bail!(
+            "url: {}, status: {}, range {}-{} not honoured",
+            url,
+            response.status(),
+            segment.start,
+            segment.end
+        );
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(tmp_path)
+        .await?;
+    file.seek(SeekFrom::Start(segment.start)).await?;
+
+    let expected = segment.end - segment.start + 1;
+    let mut written = 0;
+    let mut byte_stream = response.bytes_stream();
     while let Some(item) = byte_stream.next().await {
-        tokio::io::copy(&mut item?.as_ref(), &mut tmp_file).await?;
+        let item = item?;
+        written += item.len() as u64;
+        if written > expected {
+            bail!("url: {}, received more data than requested", url);
+        }
+        file.write_all(&item).await?;
+        progress.advance(target, item.len() as u64);
+        downloaded.inc_by(item.len() as u64);
+    }
+    file.flush().await?;
+
+    if written != expected {
+        bail!(
+            "url: {}, segment {}-{} incomplete: {} of {} bytes",
+            url,
+            segment.start,
+            segment.end,
+            written,
+            expected
+        );
     }
+
+    Ok(())
+}
+
+fn finish(tmp_path: &str, to: &str, uid: u32) -> Result<()> {
     if Uid::effective().is_root() {
-        tmp_path.clone().set_owner(uid)?;
+        tmp_path.to_string().set_owner(uid)?;
     }
 
-    fs::rename(&tmp_path, &target.to)?;
+    fs::rename(tmp_path, to)?;
 
     Ok(())
 }
 
+/// A resumed response has to be a 206 whose Content-Range starts exactly where our partial ends.
+fn is_resumed_at(response: &reqwest::Response, offset: u64) -> bool {
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return false;
+    }
+    match response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+    {
+        Some(range) => range.start == Some(offset),
+        None => false,
+    }
+}
+
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+        .and_then(|r| r.total)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// Parses a Content-Range header value, e.g. `bytes 100-199/1000` or `bytes */1000`.
+pub fn parse_content_range(value: &str) -> Option<ContentRange> {
+    let value = value.trim().strip_prefix("bytes ")?;
+    let (range, total) = value.split_once('/')?;
+    let total = match total.trim() {
+        "*" => None,
+        t => Some(t.parse().ok()?),
+    };
+    let (start, end) = match range.trim() {
+        "*" => (None, None),
+        r => {
+            let (start, end) = r.split_once('-')?;
+            (Some(start.parse().ok()?), Some(end.parse().ok()?))
+        }
+    };
+    Some(ContentRange { start, end, total })
+}
+
 #[derive(Debug, Clone)]
 pub struct DownloadTargetMessage {
     pub download_target: DownloadTarget,
@@ -100,5 +479,5 @@ pub struct DownloadTargetMessage {
 #[derive(Debug, Clone)]
 pub enum DownloadDoneStatus {
     Success,
-    Failed,
+    Failed(String),
 }
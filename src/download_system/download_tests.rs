@@ -0,0 +1,405 @@
+#[cfg(test)]
+mod tests {
+    use super::super::download::*;
+    use super::super::progress::ProgressTracker;
+    use super::super::transfer::{DownloadTarget, TargetType};
+    use std::fs;
+    use tempfile::TempDir;
+
+    const BODY: &str = "hello world";
+
+    fn create_target(dir: &TempDir, url: String) -> DownloadTarget {
+        DownloadTarget {
+            from: Some(url),
+            to: dir.path().join("file.mkv").to_string_lossy().to_string(),
+            target_type: TargetType::File,
+            top_level: true,
+            transfer_hash: "abcd1234".to_string(),
+        }
+    }
+
+    fn single_stream() -> FetchOptions {
+        FetchOptions {
+            worker: 0,
+            uid: 1000,
+            segments: 1,
+            min_segment_size: 1,
+        }
+    }
+
+    fn segmented(segments: usize, min_segment_size: u64) -> FetchOptions {
+        FetchOptions {
+            worker: 0,
+            uid: 1000,
+            segments,
+            min_segment_size,
+        }
+    }
+
+    fn segmented_body() -> String {
+        (0..100)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect()
+    }
+
+    async fn mock_segment(
+        server: &mut mockito::ServerGuard,
+        body: &str,
+        start: usize,
+        end: usize,
+    ) -> mockito::Mock {
+        server
+            .mock("GET", "/file")
+            .match_header("range", format!("bytes={}-{}", start, end).as_str())
+            .with_status(206)
+            .with_header(
+                "content-range",
+                &format!("bytes {}-{}/{}", start, end, body.len()),
+            )
+            .with_body(&body[start..=end])
+            .create_async()
+            .await
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_full_download() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .match_header("range", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_body(BODY)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), BODY);
+        assert!(!dir.path().join("file.mkv.downloading").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_resumes_partial_download() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .match_header("range", "bytes=6-")
+            .with_status(206)
+            .with_header("content-range", "bytes 6-10/11")
+            .with_body("world")
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fs::write(format!("{}.downloading", target.to), "hello ").unwrap();
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), BODY);
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_restarts_when_range_ignored() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .match_header("range", "bytes=7-")
+            .with_status(200)
+            .with_body(BODY)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fs::write(format!("{}.downloading", target.to), "garbage").unwrap();
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), BODY);
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_restarts_on_mismatched_content_range() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .match_header("range", "bytes=6-")
+            .with_status(206)
+            .with_header("content-range", "bytes 0-10/11")
+            .with_body(BODY)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fs::write(format!("{}.downloading", target.to), "hello ").unwrap();
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), BODY);
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_completes_when_partial_is_whole_file() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .match_header("range", "bytes=11-")
+            .with_status(416)
+            .with_header("content-range", "bytes */11")
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fs::write(format!("{}.downloading", target.to), BODY).unwrap();
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), BODY);
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_fails_on_server_error() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/file")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        assert!(
+            fetch(&target, &single_stream(), &ProgressTracker::default())
+                .await
+                .is_err()
+        );
+        assert!(!dir.path().join("file.mkv").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_segmented() {
+        let body = segmented_body();
+        let mut server = mockito::Server::new_async().await;
+        let probe = mock_segment(&mut server, &body, 0, 0).await;
+        let mut segments = vec![];
+        for (start, end) in [(0, 24), (25, 49), (50, 74), (75, 99)] {
+            segments.push(mock_segment(&mut server, &body, start, end).await);
+        }
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fetch(&target, &segmented(4, 10), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        probe.assert_async().await;
+        for segment in segments {
+            segment.assert_async().await;
+        }
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), body);
+        assert!(!dir.path().join("file.mkv.downloading.segments").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_segmented_resumes_unfinished_segments() {
+        let body = segmented_body();
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/file")
+            .match_header("range", "bytes=0-49")
+            .expect(0)
+            .create_async()
+            .await;
+        let second = mock_segment(&mut server, &body, 50, 99).await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        let tmp_path = format!("{}.downloading", target.to);
+        let mut partial = body[..50].to_string();
+        partial.push_str(&"\0".repeat(50));
+        fs::write(&tmp_path, partial).unwrap();
+        let mut plan = SegmentPlan::new(100, 2, 10);
+        plan.segments[0].done = true;
+        fs::write(
+            format!("{}.segments", tmp_path),
+            serde_json::to_vec(&plan).unwrap(),
+        )
+        .unwrap();
+
+        fetch(&target, &segmented(2, 10), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), body);
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_single_stream_continues_segmented_partial() {
+        let body = segmented_body();
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/file")
+            .match_header("range", "bytes=0-49")
+            .expect(0)
+            .create_async()
+            .await;
+        let second = mock_segment(&mut server, &body, 50, 99).await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        let tmp_path = format!("{}.downloading", target.to);
+        // Preallocated, so the partial is as large as the whole file.
+        let mut partial = body[..50].to_string();
+        partial.push_str(&"\0".repeat(50));
+        fs::write(&tmp_path, partial).unwrap();
+        let mut plan = SegmentPlan::new(100, 2, 10);
+        plan.segments[0].done = true;
+        fs::write(
+            format!("{}.segments", tmp_path),
+            serde_json::to_vec(&plan).unwrap(),
+        )
+        .unwrap();
+
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), body);
+        assert!(!dir.path().join("file.mkv.downloading.segments").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_single_stream_discards_partial_with_corrupt_plan() {
+        let body = segmented_body();
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .match_header("range", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_body(&body)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        let tmp_path = format!("{}.downloading", target.to);
+        fs::write(&tmp_path, "\0".repeat(100)).unwrap();
+        fs::write(format!("{}.segments", tmp_path), "not json").unwrap();
+
+        fetch(&target, &single_stream(), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), body);
+        assert!(!dir.path().join("file.mkv.downloading.segments").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_segmented_falls_back_without_range_support() {
+        let body = segmented_body();
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/file")
+            .with_status(200)
+            .with_body(&body)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let target = create_target(&dir, format!("{}/file", server.url()));
+        fetch(&target, &segmented(4, 10), &ProgressTracker::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(fs::read_to_string(&target.to).unwrap(), body);
+    }
+
+    #[test]
+    fn test_segment_plan_splits_evenly() {
+        let plan = SegmentPlan::new(100, 4, 10);
+        assert_eq!(plan.size, 100);
+        assert_eq!(
+            plan.segments
+                .iter()
+                .map(|s| (s.start, s.end))
+                .collect::<Vec<_>>(),
+            vec![(0, 24), (25, 49), (50, 74), (75, 99)]
+        );
+        assert!(plan.segments.iter().all(|s| !s.done));
+    }
+
+    #[test]
+    fn test_segment_plan_respects_min_segment_size() {
+        assert_eq!(SegmentPlan::new(100, 8, 40).segments.len(), 2);
+        assert_eq!(SegmentPlan::new(15, 4, 10).segments.len(), 1);
+        assert!(SegmentPlan::new(0, 4, 10).segments.is_empty());
+    }
+
+    #[test]
+    fn test_segment_plan_covers_uneven_sizes() {
+        let plan = SegmentPlan::new(101, 4, 10);
+        assert_eq!(plan.segments.first().unwrap().start, 0);
+        assert_eq!(plan.segments.last().unwrap().end, 100);
+        for pair in plan.segments.windows(2) {
+            assert_eq!(pair[0].end + 1, pair[1].start);
+        }
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/1000"),
+            Some(ContentRange {
+                start: Some(100),
+                end: Some(199),
+                total: Some(1000),
+            })
+        );
+        assert_eq!(
+            parse_content_range("bytes 0-99/*"),
+            Some(ContentRange {
+                start: Some(0),
+                end: Some(99),
+                total: None,
+            })
+        );
+        assert_eq!(
+            parse_content_range("bytes */1000"),
+            Some(ContentRange {
+                start: None,
+                end: None,
+                total: Some(1000),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_content_range_invalid() {
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+        assert_eq!(parse_content_range("bytes abc-def/100"), None);
+        assert_eq!(parse_content_range("bytes 0-99"), None);
+    }
+}
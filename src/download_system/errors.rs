@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{collections::VecDeque, sync::Mutex};
+
+/// How many errors are kept for the status API.
+const CAPACITY: usize = 50;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RecentError {
+    pub at: DateTime<Utc>,
+    /// What the error is about, e.g. a transfer name or a file being downloaded.
+    pub subject: String,
+    pub message: String,
+}
+
+/// Keeps the most recent errors in memory, so they can be shown without digging through the logs.
+#[derive(Default)]
+pub struct RecentErrors {
+    errors: Mutex<VecDeque<RecentError>>,
+}
+
+impl RecentErrors {
+    pub fn record(&self, subject: &str, message: &str) {
+        let mut errors = self.errors.lock().unwrap();
+        if errors.len() == CAPACITY {
+            errors.pop_front();
+        }
+        errors.push_back(RecentError {
+            at: Utc::now(),
+            subject: subject.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// The recorded errors, newest first.
+    pub fn all(&self) -> Vec<RecentError> {
+        self.errors.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
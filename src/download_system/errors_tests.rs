@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use super::super::errors::*;
+
+    #[test]
+    fn test_recent_errors_newest_first() {
+        let errors = RecentErrors::default();
+        assert!(errors.all().is_empty());
+
+        errors.record("Movie", "connection reset");
+        errors.record("/downloads/movie.mkv", "disk full");
+
+        let all = errors.all();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].subject, "/downloads/movie.mkv");
+        assert_eq!(all[0].message, "disk full");
+        assert_eq!(all[1].subject, "Movie");
+        assert!(all[0].at >= all[1].at);
+    }
+
+    #[test]
+    fn test_recent_errors_keeps_the_latest() {
+        let errors = RecentErrors::default();
+        for i in 0..60 {
+            errors.record("Movie", &format!("error {}", i));
+        }
+
+        let all = errors.all();
+        assert_eq!(all.len(), 50);
+        assert_eq!(all[0].message, "error 59");
+        assert_eq!(all[49].message, "error 10");
+    }
+}
@@ -0,0 +1,200 @@
+use super::{
+    filter,
+    hook::run_command,
+    transfer::{DownloadTarget, TargetType, Transfer},
+};
+use crate::{AppData, ExtractConfig};
+use anyhow::{Context, Result};
+use file_owner::PathExt;
+use log::info;
+use nix::unistd::Uid;
+use std::{
+    fs::{self, File},
+    io,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+use zip::ZipArchive;
+
+/// Extracts the downloaded archives of `transfer` next to them. Returns targets for the extracted
+/// video and sidecar files, so the import of those gets waited for instead of the archives. A
+/// transfer that's a single archive is extracted into a directory of its own, which is returned as
+/// well, so it gets cleaned up along with the archive.
+pub async fn extract(app_data: &AppData, transfer: &Transfer) -> Result<Vec<DownloadTarget>> {
+    let config = &app_data.config.extract;
+    if !config.enabled {
+        return Ok(vec![]);
+    }
+
+    let targets = transfer.targets.clone().unwrap_or_default();
+    let mut extracted = Vec::<DownloadTarget>::new();
+    for target in targets
+        .iter()
+        .filter(|t| t.target_type == TargetType::File && is_first_volume(&t.to))
+    {
+        let archive = PathBuf::from(&target.to);
+        let Some(parent) = archive.parent() else {
+            continue;
+        };
+        let destination = if target.top_level {
+            let destination = parent.join(archive.file_stem().unwrap_or_default());
+            fs::create_dir_all(&destination)?;
+            if Uid::effective().is_root() {
+                destination.set_owner(app_data.config.uid)?;
+            }
+            extracted.push(DownloadTarget {
+                from: None,
+                to: destination.to_string_lossy().to_string(),
+                target_type: TargetType::Directory,
+                top_level: false,
+                transfer_hash: target.transfer_hash.clone(),
+            });
+            destination
+        } else {
+            parent.to_path_buf()
+        };
+        info!("{}: extracting", target);
+        let files = if target.to.to_lowercase().ends_with(".zip") {
+            extract_zip(archive, destination.clone()).await
+        } else {
+            extract_rar(config, &archive, &destination).await
+        }
+        .with_context(|| target.to.clone())?;
+
+        for file in files {
+            // unrar lists what it would extract, leave anything that escapes the directory alone.
+            if !file.components().all(|c| matches!(c, Component::Normal(_))) {
+                continue;
+            }
+            if Uid::effective().is_root() {
+                set_owner(&destination, &file, app_data.config.uid)?;
+            }
+            let to = destination.join(&file).to_string_lossy().to_string();
+            let skipped = file.parent().is_some_and(|p| {
+                p.iter().any(|d| {
+                    app_data
+                        .config
+                        .skip_directories
+                        .contains(&d.to_string_lossy().to_lowercase())
+                })
+            });
+            if skipped
+                || !(filter::is_video(&to) || filter::is_sidecar(&to))
+                || targets.iter().chain(&extracted).any(|t| t.to == to)
+            {
+                continue;
+            }
+            extracted.push(DownloadTarget {
+                from: None,
+                to,
+                target_type: TargetType::File,
+                top_level: false,
+                transfer_hash: target.transfer_hash.clone(),
+            });
+        }
+    }
+    Ok(extracted)
+}
+
+/// Deletes the downloaded archives of `transfer`, once what was extracted from them has been
+/// imported.
+pub fn remove_archives(app_data: &AppData, transfer: &Transfer) -> Result<()> {
+    let config = &app_data.config.extract;
+    if !config.enabled || !config.delete_archives {
+        return Ok(());
+    }
+
+    for target in transfer
+        .targets
+        .iter()
+        .flatten()
+        .filter(|t| t.target_type == TargetType::File && filter::is_archive(&t.to))
+    {
+        if app_data.config.cleanup.dry_run {
+            info!("{}: would delete archive (dry run)", target);
+            continue;
+        }
+        match fs::remove_file(&target.to) {
+            Ok(()) => info!("{}: archive deleted", target),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is a ZIP archive or the first volume of a RAR archive, which unrar extracts
+/// the other volumes along with. Volumes are either named like movie.part01.rar or movie.rar,
+/// movie.r00, etc.
+pub fn is_first_volume(path: &str) -> bool {
+    let path = path.to_lowercase();
+    if path.ends_with(".zip") {
+        return true;
+    }
+    let Some(stem) = path.strip_suffix(".rar") else {
+        return false;
+    };
+    match stem.rsplit_once(".part") {
+        Some((_, part)) if !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()) => {
+            part.parse::<u32>() == Ok(1)
+        }
+        _ => true,
+    }
+}
+
+/// Extracts `archive` with unrar, returning the paths of the extracted files relative to
+/// `destination`.
+async fn extract_rar(
+    config: &ExtractConfig,
+    archive: &Path,
+    destination: &Path,
+) -> Result<Vec<PathBuf>> {
+    let timeout = Duration::from_secs(config.timeout);
+    let env = [
+        ("PUTIOARR_ARCHIVE", archive.to_string_lossy().to_string()),
+        // unrar wants a trailing slash to know it's a directory.
+        (
+            "PUTIOARR_DESTINATION",
+            format!("{}/", destination.display()),
+        ),
+    ];
+    let list = format!("{} lb \"$PUTIOARR_ARCHIVE\"", config.unrar);
+    let files = run_command(&list, &env, timeout).await?;
+    let extract = format!(
+        "{} x -o+ -y \"$PUTIOARR_ARCHIVE\" \"$PUTIOARR_DESTINATION\"",
+        config.unrar
+    );
+    run_command(&extract, &env, timeout).await?;
+    Ok(files
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Extracts `archive`, returning the paths of the extracted files relative to `destination`.
+async fn extract_zip(archive: PathBuf, destination: PathBuf) -> Result<Vec<PathBuf>> {
+    actix_rt::task::spawn_blocking(move || {
+        let mut zip = ZipArchive::new(File::open(&archive)?)?;
+        let mut files = vec![];
+        for i in 0..zip.len() {
+            let file = zip.by_index(i)?;
+            if let Some(name) = file.enclosed_name().filter(|_| file.is_file()) {
+                files.push(name);
+            }
+        }
+        zip.extract(&destination)?;
+        Ok(files)
+    })
+    .await?
+}
+
+/// Hands `file` and the directories it was extracted into over to `uid`.
+fn set_owner(destination: &Path, file: &Path, uid: u32) -> Result<()> {
+    let mut path = destination.to_path_buf();
+    for component in file.iter() {
+        path.push(component);
+        path.set_owner(uid)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        extract::*,
+        transfer::{DownloadTarget, TargetType, Transfer},
+    };
+    use crate::{services::putio, test_support, AppData, Config};
+    use actix_web::web;
+    use serde_json::{json, Value};
+    use std::{fs, io::Write, os::unix::fs::PermissionsExt, path::Path};
+    use tempfile::TempDir;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn create_app_data(dir: &TempDir, extract: Value, cleanup: Value) -> web::Data<AppData> {
+        test_support::with_config(Config {
+            download_directory: dir.path().to_string_lossy().to_string(),
+            skip_directories: vec!["sample".to_string()],
+            cleanup: serde_json::from_value(cleanup).unwrap(),
+            extract: serde_json::from_value(extract).unwrap(),
+            ..test_support::config(putio::API_URL, putio::UPLOAD_URL)
+        })
+    }
+
+    fn file_target(path: &Path) -> DownloadTarget {
+        DownloadTarget {
+            from: Some("https://put.io/file".to_string()),
+            to: path.to_string_lossy().to_string(),
+            target_type: TargetType::File,
+            top_level: false,
+            transfer_hash: "abcd1234".to_string(),
+        }
+    }
+
+    /// A transfer of the Movie directory in `dir`, with `files` downloaded into it.
+    fn create_transfer(app_data: &web::Data<AppData>, dir: &TempDir, files: &[&str]) -> Transfer {
+        let movie = dir.path().join("Movie");
+        fs::create_dir_all(&movie).unwrap();
+        let mut targets = vec![DownloadTarget {
+            from: None,
+            to: movie.to_string_lossy().to_string(),
+            target_type: TargetType::Directory,
+            top_level: true,
+            transfer_hash: "abcd1234".to_string(),
+        }];
+        for file in files {
+            let path = movie.join(file);
+            if !path.exists() {
+                fs::write(&path, b"data").unwrap();
+            }
+            targets.push(file_target(&path));
+        }
+        Transfer {
+            name: "Movie".to_string(),
+            file_id: Some(456),
+            hash: Some("abcd1234".to_string()),
+            transfer_id: 42,
+            targets: Some(targets),
+            app_data: app_data.clone(),
+        }
+    }
+
+    fn create_zip(path: &Path, files: &[(&str, &[u8])]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn create_unrar(dir: &TempDir, script: &str) -> String {
+        let path = dir.path().join("unrar");
+        fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    /// An unrar stand-in that lists and extracts `files`, each containing its own name.
+    fn fake_unrar(dir: &TempDir, files: &[&str]) -> String {
+        let script = format!(
+            "files='{}'\nif [ \"$1\" = lb ]; then\n  for f in $files; do echo \"$f\"; done\nelse\n  echo \"$@\" > \"$5/args\"\n  for f in $files; do mkdir -p \"$(dirname \"$5/$f\")\"; echo \"$f\" > \"$5/$f\"; done\nfi\n",
+            files.join(" ")
+        );
+        create_unrar(dir, &script)
+    }
+
+    fn paths(targets: &[DownloadTarget]) -> Vec<String> {
+        targets.iter().map(|t| t.to.clone()).collect()
+    }
+
+    #[test]
+    fn test_is_first_volume() {
+        assert!(is_first_volume("/downloads/Movie/movie.rar"));
+        assert!(is_first_volume("/downloads/Movie/movie.part1.rar"));
+        assert!(is_first_volume("/downloads/Movie/movie.PART001.RAR"));
+        assert!(is_first_volume("/downloads/Movie/movie.zip"));
+        assert!(is_first_volume("/downloads/my.party/movie.rar"));
+        assert!(!is_first_volume("/downloads/Movie/movie.part02.rar"));
+        assert!(!is_first_volume("/downloads/Movie/movie.r00"));
+        assert!(!is_first_volume("/downloads/Movie/movie.mkv"));
+    }
+
+    #[actix_web::test]
+    async fn test_extract_disabled() {
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&dir, json!({}), json!({}));
+        let archive = dir.path().join("Movie").join("movie.zip");
+        create_zip(&archive, &[("movie.mkv", b"movie")]);
+        let transfer = create_transfer(&app_data, &dir, &["movie.zip"]);
+
+        assert!(extract(&app_data, &transfer).await.unwrap().is_empty());
+        assert!(!dir.path().join("Movie").join("movie.mkv").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_extract_zip() {
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&dir, json!({"enabled": true}), json!({}));
+        let movie = dir.path().join("Movie");
+        create_zip(
+            &movie.join("movie.zip"),
+            &[
+                ("Movie.2024/movie.mkv", b"movie"),
+                ("Movie.2024/movie.en.srt", b"subtitle"),
+                ("Movie.2024/Sample/sample.mkv", b"sample"),
+                ("setup.exe", b"setup"),
+            ],
+        );
+        let transfer = create_transfer(&app_data, &dir, &["movie.zip", "extra.mkv"]);
+
+        let extracted = extract(&app_data, &transfer).await.unwrap();
+        assert_eq!(
+            paths(&extracted),
+            vec![
+                movie.join("Movie.2024/movie.mkv").to_string_lossy(),
+                movie.join("Movie.2024/movie.en.srt").to_string_lossy(),
+            ]
+        );
+        assert!(extracted
+            .iter()
+            .all(|t| t.target_type == TargetType::File && !t.top_level && t.from.is_none()));
+        assert_eq!(
+            fs::read(movie.join("Movie.2024/movie.mkv")).unwrap(),
+            b"movie"
+        );
+        // Everything is extracted, only what the *arr imports is waited for.
+        assert!(movie.join("Movie.2024/Sample/sample.mkv").exists());
+        assert!(movie.join("setup.exe").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_extract_top_level_archive_into_its_own_directory() {
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&dir, json!({"enabled": true}), json!({}));
+        let archive = dir.path().join("movie.zip");
+        create_zip(&archive, &[("movie.mkv", b"movie")]);
+        let transfer = Transfer {
+            targets: Some(vec![DownloadTarget {
+                top_level: true,
+                ..file_target(&archive)
+            }]),
+            ..create_transfer(&app_data, &dir, &[])
+        };
+
+        let extracted = extract(&app_data, &transfer).await.unwrap();
+        let movie = dir.path().join("movie");
+        assert_eq!(
+            paths(&extracted),
+            vec![
+                movie.to_string_lossy(),
+                movie.join("movie.mkv").to_string_lossy()
+            ]
+        );
+        assert_eq!(extracted[0].target_type, TargetType::Directory);
+        assert!(!extracted[0].top_level);
+        assert_eq!(fs::read(movie.join("movie.mkv")).unwrap(), b"movie");
+        assert!(!dir.path().join("movie.mkv").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_extract_rar_volumes() {
+        let dir = TempDir::new().unwrap();
+        let unrar = fake_unrar(&dir, &["movie.mkv", "Subs/movie.nl.srt"]);
+        let app_data = create_app_data(&dir, json!({"enabled": true, "unrar": unrar}), json!({}));
+        let movie = dir.path().join("Movie");
+        let transfer = create_transfer(
+            &app_data,
+            &dir,
+            &["movie.part1.rar", "movie.part2.rar", "movie.part3.rar"],
+        );
+
+        let extracted = extract(&app_data, &transfer).await.unwrap();
+        assert_eq!(
+            paths(&extracted),
+            vec![
+                movie.join("movie.mkv").to_string_lossy(),
+                movie.join("Subs/movie.nl.srt").to_string_lossy(),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(movie.join("movie.mkv")).unwrap(),
+            "movie.mkv\n"
+        );
+        // Only the first volume is handed to unrar.
+        assert_eq!(
+            fs::read_to_string(movie.join("args")).unwrap(),
+            format!(
+                "x -o+ -y {} {}/\n",
+                movie.join("movie.part1.rar").display(),
+                movie.display()
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_extract_fails_when_unrar_fails() {
+        let dir = TempDir::new().unwrap();
+        let unrar = create_unrar(&dir, "echo CRC failed >&2\nexit 3\n");
+        let app_data = create_app_data(&dir, json!({"enabled": true, "unrar": unrar}), json!({}));
+        let transfer = create_transfer(&app_data, &dir, &["movie.rar", "movie.r00"]);
+
+        let error = format!("{:#}", extract(&app_data, &transfer).await.unwrap_err());
+        assert!(error.contains("movie.rar"), "{}", error);
+        assert!(error.contains("CRC failed"), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn test_remove_archives() {
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&dir, json!({"enabled": true}), json!({}));
+        let transfer = create_transfer(
+            &app_data,
+            &dir,
+            &["movie.rar", "movie.r00", "movie.mkv", "movie.nfo"],
+        );
+
+        remove_archives(&app_data, &transfer).unwrap();
+        let movie = dir.path().join("Movie");
+        assert!(!movie.join("movie.rar").exists());
+        assert!(!movie.join("movie.r00").exists());
+        assert!(movie.join("movie.mkv").exists());
+        assert!(movie.join("movie.nfo").exists());
+        // Already gone.
+        remove_archives(&app_data, &transfer).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_remove_archives_keeps_them_when_asked() {
+        let dir = TempDir::new().unwrap();
+        let movie = dir.path().join("Movie");
+
+        let app_data = create_app_data(
+            &dir,
+            json!({"enabled": true, "delete_archives": false}),
+            json!({}),
+        );
+        let transfer = create_transfer(&app_data, &dir, &["movie.zip"]);
+        remove_archives(&app_data, &transfer).unwrap();
+        assert!(movie.join("movie.zip").exists());
+
+        let app_data = create_app_data(&dir, json!({"enabled": true}), json!({"dry_run": true}));
+        let transfer = create_transfer(&app_data, &dir, &["movie.zip"]);
+        remove_archives(&app_data, &transfer).unwrap();
+        assert!(movie.join("movie.zip").exists());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{services::putio::FileResponse, Config};
+use std::path::Path;
+
+/// Files the *arrs pick up next to the media file they import. These don't get a history entry of
+/// their own, so we can't wait for them to be imported.
+const SIDECAR_EXTENSIONS: &[&str] = &[
+    "srt", "ass", "ssa", "sub", "idx", "vtt", "sup", "smi", "nfo", "txt", "jpg", "jpeg", "png",
+];
+
+/// Media files the *arrs import, used for files extracted from archives, which put.io knows
+/// nothing about.
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "m4v", "avi", "mov", "wmv", "mpg", "mpeg", "ts", "m2ts", "webm", "flv",
+];
+
+/// Decides which put.io files get downloaded, see `FileFilterConfig`.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    include: Rules,
+    exclude: Rules,
+    /// Download archives too, to extract them.
+    archives: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    file_types: Vec<String>,
+    extensions: Vec<String>,
+    content_types: Vec<String>,
+    globs: Vec<String>,
+}
+
+impl Rules {
+    fn new(
+        file_types: &[String],
+        extensions: &[String],
+        content_types: &[String],
+        globs: &[String],
+    ) -> Self {
+        let lowercase = |v: &[String]| v.iter().map(|s| s.to_lowercase()).collect();
+        Self {
+            file_types: lowercase(file_types),
+            extensions: extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            content_types: lowercase(content_types),
+            globs: lowercase(globs),
+        }
+    }
+
+    fn matches(&self, file: &FileResponse) -> bool {
+        let name = file.name.to_lowercase();
+        let content_type = file.content_type.to_lowercase();
+        self.file_types.contains(&file.file_type.to_lowercase())
+            || extension(&name).is_some_and(|e| self.extensions.contains(&e))
+            || self
+                .content_types
+                .iter()
+                .any(|p| glob_match(p, &content_type))
+            || self.globs.iter().any(|p| glob_match(p, &name))
+    }
+}
+
+impl From<&Config> for FileFilter {
+    fn from(config: &Config) -> Self {
+        let f = &config.file_filter;
+        Self {
+            include: Rules::new(
+                &f.include_file_types,
+                &f.include_extensions,
+                &f.include_content_types,
+                &f.include_globs,
+            ),
+            exclude: Rules::new(
+                &f.exclude_file_types,
+                &f.exclude_extensions,
+                &f.exclude_content_types,
+                &f.exclude_globs,
+            ),
+            archives: config.extract.enabled,
+        }
+    }
+}
+
+impl FileFilter {
+    pub fn matches(&self, file: &FileResponse) -> bool {
+        (self.include.matches(file) || self.archives && is_archive(&file.name))
+            && !self.exclude.matches(file)
+    }
+}
+
+/// Whether `path` is a RAR (including old style .r00 volumes) or ZIP archive.
+pub fn is_archive(path: &str) -> bool {
+    extension(&path.to_lowercase()).is_some_and(|e| {
+        e == "rar"
+            || e == "zip"
+            || (e.len() == 3 && e.starts_with('r') && e[1..].chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// Whether `path` is a video file.
+pub fn is_video(path: &str) -> bool {
+    extension(&path.to_lowercase()).is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Whether `path` is a sidecar file, like a subtitle, rather than something the *arrs import.
+pub fn is_sidecar(path: &str) -> bool {
+    extension(&path.to_lowercase()).is_some_and(|e| SIDECAR_EXTENSIONS.contains(&e.as_str()))
+}
+
+fn extension(name: &str) -> Option<String> {
+    Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+}
+
+/// Matches `text` against `pattern`, where `*` matches any number of characters and `?` exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at, to backtrack to.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
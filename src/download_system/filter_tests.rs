@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use super::super::filter::*;
+    use crate::{
+        services::putio::{self, FileResponse},
+        test_support, Config, ExtractConfig, FileFilterConfig,
+    };
+
+    fn create_file(name: &str, file_type: &str, content_type: &str) -> FileResponse {
+        FileResponse {
+            content_type: content_type.to_string(),
+            id: 1,
+            name: name.to_string(),
+            file_type: file_type.to_string(),
+        }
+    }
+
+    fn create_filter(file_filter: FileFilterConfig, extract: bool) -> FileFilter {
+        FileFilter::from(&Config {
+            extract: ExtractConfig {
+                enabled: extract,
+                ..ExtractConfig::default()
+            },
+            file_filter,
+            ..test_support::config(putio::API_URL, putio::UPLOAD_URL)
+        })
+    }
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_filter_includes_videos_subtitles_and_nfos() {
+        let filter = create_filter(FileFilterConfig::default(), false);
+        assert!(filter.matches(&create_file("movie.mkv", "VIDEO", "video/x-matroska")));
+        assert!(filter.matches(&create_file("movie.en.srt", "TEXT", "text/plain")));
+        assert!(filter.matches(&create_file("movie.ASS", "FILE", "text/plain")));
+        assert!(filter.matches(&create_file("movie.nfo", "TEXT", "text/plain")));
+        assert!(!filter.matches(&create_file("poster.jpg", "IMAGE", "image/jpeg")));
+        assert!(!filter.matches(&create_file("track.flac", "AUDIO", "audio/flac")));
+    }
+
+    #[test]
+    fn test_filter_include_rules() {
+        let filter = create_filter(
+            FileFilterConfig {
+                include_file_types: strings(&["audio"]),
+                include_extensions: strings(&[".nfo"]),
+                include_content_types: strings(&["application/epub*"]),
+                include_globs: strings(&["cover.*"]),
+                ..FileFilterConfig::default()
+            },
+            false,
+        );
+        assert!(filter.matches(&create_file("track.flac", "AUDIO", "audio/flac")));
+        assert!(filter.matches(&create_file("movie.nfo", "TEXT", "text/plain")));
+        assert!(filter.matches(&create_file("book", "FILE", "application/epub+zip")));
+        assert!(filter.matches(&create_file("Cover.JPG", "IMAGE", "image/jpeg")));
+        assert!(!filter.matches(&create_file("movie.mkv", "VIDEO", "video/x-matroska")));
+        assert!(!filter.matches(&create_file("back.jpg", "IMAGE", "image/jpeg")));
+    }
+
+    #[test]
+    fn test_filter_exclude_wins() {
+        let filter = create_filter(
+            FileFilterConfig {
+                exclude_extensions: strings(&["sup"]),
+                exclude_content_types: strings(&["video/x-ms-*"]),
+                exclude_globs: strings(&["*sample*"]),
+                ..FileFilterConfig::default()
+            },
+            false,
+        );
+        assert!(filter.matches(&create_file("movie.mkv", "VIDEO", "video/x-matroska")));
+        assert!(!filter.matches(&create_file(
+            "movie-sample.mkv",
+            "VIDEO",
+            "video/x-matroska"
+        )));
+        assert!(!filter.matches(&create_file("movie.wmv", "VIDEO", "video/x-ms-wmv")));
+        assert!(!filter.matches(&create_file(
+            "movie.sup",
+            "FILE",
+            "application/octet-stream"
+        )));
+    }
+
+    #[test]
+    fn test_filter_archives_when_extracting() {
+        let rar = create_file("movie.part01.rar", "ARCHIVE", "application/x-rar");
+        let volume = create_file("movie.r07", "FILE", "application/octet-stream");
+        let zip = create_file("movie.ZIP", "ARCHIVE", "application/zip");
+        let filter = create_filter(FileFilterConfig::default(), false);
+        assert!(!filter.matches(&rar));
+        assert!(!filter.matches(&volume));
+        assert!(!filter.matches(&zip));
+
+        let filter = create_filter(FileFilterConfig::default(), true);
+        assert!(filter.matches(&rar));
+        assert!(filter.matches(&volume));
+        assert!(filter.matches(&zip));
+        assert!(filter.matches(&create_file("movie.mkv", "VIDEO", "video/x-matroska")));
+        assert!(!filter.matches(&create_file(
+            "setup.exe",
+            "FILE",
+            "application/octet-stream"
+        )));
+
+        let filter = create_filter(
+            FileFilterConfig {
+                exclude_globs: strings(&["*sample*"]),
+                ..FileFilterConfig::default()
+            },
+            true,
+        );
+        assert!(!filter.matches(&create_file("sample.rar", "ARCHIVE", "application/x-rar")));
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive("/downloads/Movie/movie.rar"));
+        assert!(is_archive("/downloads/Movie/movie.part2.RAR"));
+        assert!(is_archive("/downloads/Movie/movie.r00"));
+        assert!(is_archive("/downloads/Movie/movie.zip"));
+        assert!(!is_archive("/downloads/Movie/movie.mkv"));
+        assert!(!is_archive("/downloads/Movie/movie.rm"));
+        assert!(!is_archive("/downloads/Movie/movie.r0a"));
+    }
+
+    #[test]
+    fn test_is_video() {
+        assert!(is_video("/downloads/Movie/movie.mkv"));
+        assert!(is_video("/downloads/Movie/MOVIE.MP4"));
+        assert!(!is_video("/downloads/Movie/movie.srt"));
+        assert!(!is_video("/downloads/Movie"));
+    }
+
+    #[test]
+    fn test_is_sidecar() {
+        assert!(is_sidecar("/downloads/Show/episode.en.srt"));
+        assert!(is_sidecar("/downloads/Show/episode.NFO"));
+        assert!(!is_sidecar("/downloads/Show/episode.mkv"));
+        assert!(!is_sidecar("/downloads/Show"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.mkv", "movie.mkv"));
+        assert!(glob_match("movie.?kv", "movie.mkv"));
+        assert!(glob_match("*sample*", "the.sample.mkv"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("*.mkv", "movie.mp4"));
+        assert!(!glob_match("movie.?", "movie.mkv"));
+        assert!(!glob_match("a*b", "aXbY"));
+    }
+}
@@ -0,0 +1,55 @@
+use super::transfer::Transfer;
+use crate::AppData;
+use anyhow::{bail, Context, Result};
+use log::info;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Runs `command` with `sh`, failing when it exits with an error or takes longer than `timeout`.
+/// Returns what it wrote to stdout.
+pub async fn run_command(
+    command: &str,
+    env: &[(&str, String)],
+    timeout: Duration,
+) -> Result<String> {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(timeout, child)
+        .await
+        .with_context(|| format!("{} timed out after {}s", command, timeout.as_secs()))??;
+    if !output.status.success() {
+        bail!(
+            "{} exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs the configured post-download command for `transfer`, once all of its files have been
+/// downloaded and before the *arr gets to see them.
+pub async fn post_download(app_data: &AppData, transfer: &Transfer) -> Result<()> {
+    let config = &app_data.config.post_download;
+    let Some(command) = &config.command else {
+        return Ok(());
+    };
+
+    info!("{}: running {}", transfer, command);
+    let env = [
+        ("PUTIOARR_TRANSFER_ID", transfer.transfer_id.to_string()),
+        ("PUTIOARR_TRANSFER_NAME", transfer.name.clone()),
+        (
+            "PUTIOARR_TRANSFER_HASH",
+            transfer.hash.clone().unwrap_or_default(),
+        ),
+        ("PUTIOARR_PATH", transfer.get_top_level().to),
+    ];
+    run_command(command, &env, Duration::from_secs(config.timeout)).await?;
+    Ok(())
+}
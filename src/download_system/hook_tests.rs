@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        hook::*,
+        transfer::{DownloadTarget, TargetType, Transfer},
+    };
+    use crate::{services::putio, test_support, AppData, Config, PostDownloadConfig};
+    use actix_web::web;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn create_app_data(command: Option<&str>, timeout: u64) -> web::Data<AppData> {
+        test_support::with_config(Config {
+            post_download: PostDownloadConfig {
+                command: command.map(String::from),
+                timeout,
+            },
+            ..test_support::config(putio::API_URL, putio::UPLOAD_URL)
+        })
+    }
+
+    fn create_transfer(app_data: &web::Data<AppData>) -> Transfer {
+        Transfer {
+            name: "Movie".to_string(),
+            file_id: Some(456),
+            hash: Some("abcd1234".to_string()),
+            transfer_id: 42,
+            targets: Some(vec![DownloadTarget {
+                from: None,
+                to: "/downloads/Movie".to_string(),
+                target_type: TargetType::Directory,
+                top_level: true,
+                transfer_hash: "abcd1234".to_string(),
+            }]),
+            app_data: app_data.clone(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_post_download_without_command() {
+        let app_data = create_app_data(None, 10);
+        post_download(&app_data, &create_transfer(&app_data))
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_post_download_passes_the_transfer() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("out");
+        let command = format!(
+            "echo \"$PUTIOARR_TRANSFER_ID $PUTIOARR_TRANSFER_NAME $PUTIOARR_TRANSFER_HASH $PUTIOARR_PATH\" > {}",
+            out.display()
+        );
+        let app_data = create_app_data(Some(&command), 10);
+
+        post_download(&app_data, &create_transfer(&app_data))
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(out).unwrap(),
+            "42 Movie abcd1234 /downloads/Movie\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_post_download_fails_on_non_zero_exit() {
+        let app_data = create_app_data(Some("echo corrupt archive >&2; exit 2"), 10);
+
+        let error = post_download(&app_data, &create_transfer(&app_data))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("corrupt archive"), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn test_run_command_returns_stdout() {
+        let env = [("GREETING", "hello".to_string())];
+        let output = run_command("echo \"$GREETING\"", &env, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(output, "hello\n");
+    }
+
+    #[actix_web::test]
+    async fn test_run_command_timeout() {
+        let result = run_command("sleep 5", &[], Duration::from_millis(100)).await;
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+    }
+}
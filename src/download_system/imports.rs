@@ -0,0 +1,75 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a target that wasn't found in the whole history of an *arr isn't looked for again.
+const MISSED_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Remembers which targets of a transfer have been imported, so we only ask the *arrs about the
+/// ones that haven't.
+#[derive(Default)]
+pub struct ImportCache {
+    imported: Mutex<HashMap<u64, HashSet<String>>>,
+    /// Transfers an *arr has history for by their download id.
+    known: Mutex<HashSet<u64>>,
+    /// When a target was last looked for in the whole history of an *arr, by transfer, *arr and
+    /// target.
+    missed: Mutex<HashMap<(u64, String, String), Instant>>,
+}
+
+impl ImportCache {
+    pub fn is_imported(&self, transfer_id: u64, target: &str) -> bool {
+        self.imported
+            .lock()
+            .unwrap()
+            .get(&transfer_id)
+            .is_some_and(|t| t.contains(target))
+    }
+
+    pub fn mark_imported(&self, transfer_id: u64, target: &str) {
+        self.imported
+            .lock()
+            .unwrap()
+            .entry(transfer_id)
+            .or_default()
+            .insert(target.to_string());
+    }
+
+    /// Whether an *arr knows `transfer_id` by its download id, so there's no need to search the
+    /// whole history of the others.
+    pub fn is_known(&self, transfer_id: u64) -> bool {
+        self.known.lock().unwrap().contains(&transfer_id)
+    }
+
+    pub fn mark_known(&self, transfer_id: u64) {
+        self.known.lock().unwrap().insert(transfer_id);
+    }
+
+    /// Whether `target` was recently not found in the whole history of `service`.
+    pub fn recently_missed(&self, transfer_id: u64, service: &str, target: &str) -> bool {
+        self.missed
+            .lock()
+            .unwrap()
+            .get(&(transfer_id, service.to_string(), target.to_string()))
+            .is_some_and(|at| at.elapsed() < MISSED_TTL)
+    }
+
+    pub fn mark_missed(&self, transfer_id: u64, service: &str, target: &str) {
+        self.missed.lock().unwrap().insert(
+            (transfer_id, service.to_string(), target.to_string()),
+            Instant::now(),
+        );
+    }
+
+    /// Drops everything we know about `transfer_id`.
+    pub fn forget(&self, transfer_id: u64) {
+        self.imported.lock().unwrap().remove(&transfer_id);
+        self.known.lock().unwrap().remove(&transfer_id);
+        self.missed
+            .lock()
+            .unwrap()
+            .retain(|(id, _, _), _| *id != transfer_id);
+    }
+}
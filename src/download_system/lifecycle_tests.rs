@@ -0,0 +1,920 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{state::TransferStage, transfer::Transfer};
+    use crate::{
+        download_system,
+        http::handlers::{
+            handle_session_get, handle_torrent_add, handle_torrent_get, handle_torrent_remove,
+            handle_torrent_set, handle_torrent_stop,
+        },
+        services::{arr::ArrKind, transmission::TransmissionRequest},
+        test_support::{
+            self,
+            arr::FakeArr,
+            putio::{FakePutio, TransferStep, ROOT},
+            wait_until,
+            webhook::FakeWebhook,
+        },
+        AppData, ArrConfig, ArrInstanceConfig, Config,
+    };
+    use actix_web::web;
+    use serde_json::json;
+    use std::{
+        fs,
+        io::{Cursor, Write},
+        time::Duration,
+    };
+    use tempfile::TempDir;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    const TIMEOUT: Duration = Duration::from_secs(15);
+
+    fn create_config(putio: &FakePutio, arr: &FakeArr, dir: &TempDir) -> Config {
+        Config {
+            download_directory: dir.path().to_string_lossy().to_string(),
+            download_workers: 2,
+            max_download_attempts: 1,
+            max_retry_delay: 1,
+            orchestration_workers: 2,
+            polling_interval: 1,
+            retry_delay: 1,
+            skip_directories: vec!["sample".to_string()],
+            sonarr: Some(ArrConfig {
+                url: arr.url(),
+                api_key: "sonarr_key".to_string(),
+                category: None,
+            }),
+            ..test_support::config(&putio.url(), &putio.upload_url())
+        }
+    }
+
+    fn create_app_data(putio: &FakePutio, arr: &FakeArr, dir: &TempDir) -> web::Data<AppData> {
+        test_support::with_config(create_config(putio, arr, dir))
+    }
+
+    fn stage(app_data: &AppData, transfer_id: u64) -> Option<TransferStage> {
+        app_data.state.get(transfer_id).map(|s| s.stage)
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_lifecycle() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let folder = putio.add_file(ROOT, "Show S01E01", "FOLDER", b"");
+        putio.add_file(folder, "episode.mkv", "VIDEO", b"episode");
+        putio.add_file(folder, "episode.en.srt", "TEXT", b"subtitle");
+        let sample = putio.add_file(folder, "Sample", "FOLDER", b"");
+        putio.add_file(sample, "sample.mkv", "VIDEO", b"sample");
+        putio.add_file(folder, "info.nfo", "TEXT", b"nfo");
+        let id = putio.add_transfer("Show S01E01", "abcd1234", "DOWNLOADING");
+
+        download_system::start(app_data.clone()).await.unwrap();
+
+        // Nothing to download until put.io is done.
+        putio.set_status(id, "SEEDING", Some(folder));
+        let episode = dir.path().join("Show S01E01").join("episode.mkv");
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+        assert_eq!(fs::read(&episode).unwrap(), b"episode");
+        let subtitle = dir.path().join("Show S01E01").join("episode.en.srt");
+        assert_eq!(fs::read(subtitle).unwrap(), b"subtitle");
+        assert!(!dir.path().join("Show S01E01").join("Sample").exists());
+        let nfo = dir.path().join("Show S01E01").join("info.nfo");
+        assert_eq!(fs::read(nfo).unwrap(), b"nfo");
+
+        // The subtitle is imported along with the episode, without a history entry of its own.
+        arr.import_download("abcd1234", &episode.to_string_lossy());
+        wait_until("imported", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        assert!(!dir.path().join("Show S01E01").exists());
+        assert!(putio.transfer(id).is_some());
+
+        putio.set_status(id, "COMPLETED", None);
+        wait_until("removed from put.io", TIMEOUT, || {
+            putio.transfer(id).is_none() && !putio.file_exists(folder)
+        })
+        .await;
+        wait_until("forgotten", TIMEOUT, || app_data.state.get(id).is_none()).await;
+        assert_eq!(putio.removed_transfers(), vec![id]);
+        assert!(putio.deleted_files().contains(&folder));
+    }
+
+    #[actix_web::test]
+    async fn test_listing_files_is_retried() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.max_download_attempts = 2;
+        let app_data = test_support::with_config(config);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+        // Once when checking unfinished transfers on start, once when downloading.
+        putio.fail("files/list", 2);
+
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+        assert_eq!(fs::read(dir.path().join("movie.mkv")).unwrap(), b"movie");
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_fails_when_listing_files_keeps_failing() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+        // Once when checking unfinished transfers on start, once when downloading.
+        putio.fail("files/list", 2);
+
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("failed", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Failed)
+        })
+        .await;
+
+        // The orchestration workers are still around for the next transfer.
+        let other_file = putio.add_file(ROOT, "other.mkv", "VIDEO", b"other");
+        let other = putio.add_transfer("Other", "ijkl9012", "SEEDING");
+        putio.set_status(other, "SEEDING", Some(other_file));
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, other) == Some(TransferStage::Downloaded)
+        })
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_seeding_is_watched_through_put_io_errors() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+        arr.import_download("efgh5678", &dir.path().join("movie.mkv").to_string_lossy());
+        putio.fail("transfers/{id}", 2);
+
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("imported", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        putio.set_status(id, "COMPLETED", None);
+        wait_until("removed from put.io", TIMEOUT, || {
+            putio.transfer(id).is_none()
+        })
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_scripted_transfer_is_downloaded_once_finished() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "IN_QUEUE");
+        putio.script(
+            id,
+            vec![
+                TransferStep {
+                    status: "DOWNLOADING",
+                    file_id: None,
+                },
+                TransferStep {
+                    status: "COMPLETING",
+                    file_id: None,
+                },
+                TransferStep {
+                    status: "COMPLETED",
+                    file_id: Some(file),
+                },
+            ],
+        );
+
+        download_system::start(app_data.clone()).await.unwrap();
+
+        let movie = dir.path().join("movie.mkv");
+        wait_until("downloaded", TIMEOUT, || movie.exists()).await;
+        assert_eq!(fs::read(&movie).unwrap(), b"movie");
+        assert_eq!(putio.transfer(id).unwrap().status, "COMPLETED");
+
+        // Not seeding, so it's cleaned up as soon as it has been imported.
+        arr.import_download("efgh5678", &movie.to_string_lossy());
+        wait_until("removed from put.io", TIMEOUT, || {
+            putio.transfer(id).is_none()
+        })
+        .await;
+        assert!(!movie.exists());
+        assert!(!putio.file_exists(file));
+    }
+
+    #[actix_web::test]
+    async fn test_already_imported_transfer_is_not_downloaded() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+        let movie = dir.path().join("movie.mkv");
+        arr.import(&movie.to_string_lossy());
+
+        download_system::start(app_data.clone()).await.unwrap();
+
+        wait_until("seeding watched", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        putio.set_status(id, "COMPLETED", None);
+        wait_until("removed from put.io", TIMEOUT, || {
+            putio.transfer(id).is_none()
+        })
+        .await;
+        assert!(!movie.exists());
+    }
+
+    #[actix_web::test]
+    async fn test_torrent_add_creates_transfer() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let magnet = web::Json(TransmissionRequest {
+            method: "torrent-add".to_string(),
+            arguments: Some(json!({
+                "filename": "magnet:?xt=urn:btih:abcd1234&dn=Some.Show.S01E02"
+            })),
+        });
+        handle_torrent_add(&app_data, &magnet).await.unwrap();
+        let metainfo = web::Json(TransmissionRequest {
+            method: "torrent-add".to_string(),
+            arguments: Some(json!({ "metainfo": "ZDg6YW5ub3VuY2UwOmU=" })),
+        });
+        handle_torrent_add(&app_data, &metainfo).await.unwrap();
+
+        let transfers = putio.transfers();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].name, Some("Some.Show.S01E02".to_string()));
+        assert_eq!(transfers[0].hash, Some("abcd1234".to_string()));
+        assert_eq!(transfers[0].status, "IN_QUEUE");
+        assert_eq!(putio.uploads(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_torrent_add_stores_base32_magnet_hash_as_hex() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let magnet = web::Json(TransmissionRequest {
+            method: "torrent-add".to_string(),
+            arguments: Some(json!({
+                "filename": "magnet:?xt=urn:btih:YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK&dn=Movie",
+                "labels": ["movies"],
+            })),
+        });
+        handle_torrent_add(&app_data, &magnet).await.unwrap();
+
+        // put.io reports the hash as hex.
+        let hash = "C12FE1C06BBA254A9DC9F519B335AA7C1367A88A";
+        assert_eq!(
+            app_data.torrents.category(Some(hash)),
+            Some("movies".to_string())
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_torrent_remove() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let kept_file = putio.add_file(ROOT, "kept.mkv", "VIDEO", b"kept");
+        let kept = putio.add_transfer("Kept", "abcd1234", "COMPLETED");
+        putio.set_status(kept, "COMPLETED", Some(kept_file));
+        let deleted_file = putio.add_file(ROOT, "deleted.mkv", "VIDEO", b"deleted");
+        let deleted = putio.add_transfer("Deleted", "efgh5678", "COMPLETED");
+        putio.set_status(deleted, "COMPLETED", Some(deleted_file));
+        let untouched = putio.add_transfer("Untouched", "ijkl9012", "DOWNLOADING");
+
+        let remove = |arguments| {
+            web::Json(TransmissionRequest {
+                method: "torrent-remove".to_string(),
+                arguments: Some(arguments),
+            })
+        };
+        // Leaving out the ids would remove everything.
+        assert!(handle_torrent_remove(&app_data, &remove(json!({})))
+            .await
+            .is_err());
+        handle_torrent_remove(&app_data, &remove(json!({ "ids": [kept] })))
+            .await
+            .unwrap();
+        handle_torrent_remove(
+            &app_data,
+            &remove(json!({ "ids": ["EFGH5678"], "delete-local-data": true })),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(putio.removed_transfers(), vec![kept, deleted]);
+        assert!(putio.file_exists(kept_file));
+        assert!(!putio.file_exists(deleted_file));
+        assert!(putio.transfer(untouched).is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_torrent_remove_leaves_transfers_that_are_not_owned() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.only_owned_transfers = true;
+        let app_data = test_support::with_config(config);
+        let manual = putio.add_transfer("Manual", "abcd1234", "SEEDING");
+        let added = putio.add_transfer("Added", "efgh5678", "SEEDING");
+        app_data.torrents.add("efgh5678", None);
+
+        let remove = web::Json(TransmissionRequest {
+            method: "torrent-remove".to_string(),
+            arguments: Some(json!({ "ids": [manual, added] })),
+        });
+        handle_torrent_remove(&app_data, &remove).await.unwrap();
+
+        assert_eq!(putio.removed_transfers(), vec![added]);
+        assert!(putio.transfer(manual).is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_torrent_stop_needs_ids() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+        putio.add_transfer("Downloading", "abcd1234", "DOWNLOADING");
+
+        let stop = web::Json(TransmissionRequest {
+            method: "torrent-stop".to_string(),
+            arguments: Some(json!({})),
+        });
+        let error = handle_torrent_stop(&app_data, &stop).await.unwrap_err();
+        assert_eq!(error.to_string(), "ids argument missing");
+        assert_eq!(putio.transfers().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_is_imported_checks_every_instance() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let anime = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.arr.push(ArrInstanceConfig {
+            name: "sonarr-anime".to_string(),
+            kind: ArrKind::Sonarr,
+            url: anime.url(),
+            api_key: "anime_key".to_string(),
+            category: None,
+        });
+        let app_data = test_support::with_config(config);
+
+        let folder = putio.add_file(ROOT, "Anime S01", "FOLDER", b"");
+        putio.add_file(folder, "episode1.mkv", "VIDEO", b"1");
+        putio.add_file(folder, "episode2.mkv", "VIDEO", b"2");
+        let id = putio.add_transfer("Anime S01", "abcd1234", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(folder));
+        let mut transfer = Transfer {
+            name: "Anime S01".to_string(),
+            file_id: Some(folder),
+            hash: Some("abcd1234".to_string()),
+            transfer_id: id,
+            targets: None,
+            app_data,
+        };
+        transfer.targets = Some(transfer.get_download_targets().await.unwrap());
+        let episode = |n: u32| {
+            dir.path()
+                .join("Anime S01")
+                .join(format!("episode{}.mkv", n))
+                .to_string_lossy()
+                .to_string()
+        };
+
+        assert!(!transfer.is_imported().await);
+        anime.import(&episode(1));
+        // Misses aren't searched for again for a while, only the download is asked about.
+        assert!(!transfer.is_imported().await);
+        assert_eq!(anime.requests(), 4);
+        // As if the misses expired.
+        transfer.app_data.imports.forget(id);
+        assert!(!transfer.is_imported().await);
+        // Each file only has to be imported by one of the instances.
+        arr.import(&episode(2));
+        transfer.app_data.imports.forget(id);
+        assert!(transfer.is_imported().await);
+    }
+
+    #[actix_web::test]
+    async fn test_is_imported_skips_the_whole_history_when_known() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let anime = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.arr.push(ArrInstanceConfig {
+            name: "sonarr-anime".to_string(),
+            kind: ArrKind::Sonarr,
+            url: anime.url(),
+            api_key: "anime_key".to_string(),
+            category: None,
+        });
+        let app_data = test_support::with_config(config);
+
+        let folder = putio.add_file(ROOT, "Show S01", "FOLDER", b"");
+        putio.add_file(folder, "episode1.mkv", "VIDEO", b"1");
+        let id = putio.add_transfer("Show S01", "abcd1234", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(folder));
+        let mut transfer = Transfer {
+            name: "Show S01".to_string(),
+            file_id: Some(folder),
+            hash: Some("abcd1234".to_string()),
+            transfer_id: id,
+            targets: None,
+            app_data,
+        };
+        transfer.targets = Some(transfer.get_download_targets().await.unwrap());
+
+        // Grabbed by one instance, so the other one is only asked about the download.
+        arr.grab("abcd1234");
+        assert!(!transfer.is_imported().await);
+        assert!(!transfer.is_imported().await);
+        assert_eq!(arr.requests(), 2);
+        assert_eq!(anime.requests(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_is_imported_by_download_id() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let folder = putio.add_file(ROOT, "Show S01", "FOLDER", b"");
+        putio.add_file(folder, "episode1.mkv", "VIDEO", b"1");
+        putio.add_file(folder, "episode2.mkv", "VIDEO", b"2");
+        let id = putio.add_transfer("Show S01", "abcd1234", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(folder));
+        let mut transfer = Transfer {
+            name: "Show S01".to_string(),
+            file_id: Some(folder),
+            hash: Some("abcd1234".to_string()),
+            transfer_id: id,
+            targets: None,
+            app_data: app_data.clone(),
+        };
+        transfer.targets = Some(transfer.get_download_targets().await.unwrap());
+
+        arr.grab("abcd1234");
+        // Sonarr sees the download directory under a different path.
+        arr.import_download("abcd1234", "/data/Show S01/episode1.mkv");
+        assert!(!transfer.is_imported().await);
+        assert_eq!(arr.requests(), 1);
+
+        arr.import_download("abcd1234", "/data/Show S01/episode2.mkv");
+        assert!(transfer.is_imported().await);
+        assert_eq!(arr.requests(), 2);
+
+        // Cached, so no need to ask again.
+        assert!(transfer.is_imported().await);
+        assert_eq!(arr.requests(), 2);
+        assert!(app_data.imports.is_imported(
+            id,
+            &dir.path()
+                .join("Show S01")
+                .join("episode1.mkv")
+                .to_string_lossy()
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_category_is_downloaded_to_its_own_directory() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let movies = FakeArr::start(ArrKind::Radarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.sonarr.as_mut().unwrap().category = Some("tv-sonarr".to_string());
+        config.arr.push(ArrInstanceConfig {
+            name: "radarr".to_string(),
+            kind: ArrKind::Radarr,
+            url: movies.url(),
+            api_key: "radarr_key".to_string(),
+            category: Some("radarr".to_string()),
+        });
+        let app_data = test_support::with_config(config);
+
+        let magnet = web::Json(TransmissionRequest {
+            method: "torrent-add".to_string(),
+            arguments: Some(json!({
+                "filename": "magnet:?xt=urn:btih:ABCD1234&dn=Show.S01E01",
+                "labels": ["tv-sonarr"],
+            })),
+        });
+        handle_torrent_add(&app_data, &magnet).await.unwrap();
+
+        let folder = putio.folder(ROOT, "tv-sonarr").unwrap();
+        let id = putio.transfers()[0].id;
+        assert_eq!(putio.transfer(id).unwrap().save_parent_id, Some(folder));
+        let show = putio.add_file(folder, "Show S01E01", "FOLDER", b"");
+        putio.add_file(show, "episode.mkv", "VIDEO", b"episode");
+        putio.set_status(id, "SEEDING", Some(show));
+
+        download_system::start(app_data.clone()).await.unwrap();
+
+        let category_dir = dir.path().join("tv-sonarr");
+        let episode = category_dir.join("Show S01E01").join("episode.mkv");
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+        assert_eq!(fs::read(&episode).unwrap(), b"episode");
+
+        let get = web::Json(TransmissionRequest {
+            method: "torrent-get".to_string(),
+            arguments: Some(json!({ "fields": ["downloadDir", "labels"] })),
+        });
+        let torrents = handle_torrent_get(&app_data.putio, &get, &app_data)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            torrents["torrents"][0],
+            json!({
+                "downloadDir": category_dir.to_string_lossy(),
+                "labels": ["tv-sonarr"],
+            })
+        );
+
+        // Only the instance with the category of the torrent gets asked.
+        arr.import_download("abcd1234", &episode.to_string_lossy());
+        wait_until("imported", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        assert_eq!(movies.requests(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_only_owned_transfers_are_managed() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.only_owned_transfers = true;
+        let app_data = test_support::with_config(config);
+
+        let manual_file = putio.add_file(ROOT, "manual.mkv", "VIDEO", b"manual");
+        let manual = putio.add_transfer("Manual", "abcd1234", "SEEDING");
+        putio.set_status(manual, "COMPLETED", Some(manual_file));
+
+        let magnet = web::Json(TransmissionRequest {
+            method: "torrent-add".to_string(),
+            arguments: Some(json!({ "filename": "magnet:?xt=urn:btih:efgh5678&dn=Movie" })),
+        });
+        handle_torrent_add(&app_data, &magnet).await.unwrap();
+        let added = putio.transfers()[1].id;
+        let movie_file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        putio.set_status(added, "COMPLETED", Some(movie_file));
+
+        download_system::start(app_data.clone()).await.unwrap();
+
+        let movie = dir.path().join("movie.mkv");
+        arr.import_download("efgh5678", &movie.to_string_lossy());
+        wait_until("removed from put.io", TIMEOUT, || {
+            putio.transfer(added).is_none()
+        })
+        .await;
+        assert!(putio.transfer(manual).is_some());
+        assert!(putio.file_exists(manual_file));
+        assert!(!dir.path().join("manual.mkv").exists());
+        assert!(app_data.state.get(manual).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_seeding_stops_at_seed_ratio_limit() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.seeding.time_limit = Some(60);
+        let app_data = test_support::with_config(config);
+
+        let magnet = web::Json(TransmissionRequest {
+            method: "torrent-add".to_string(),
+            arguments: Some(json!({ "filename": "magnet:?xt=urn:btih:abcd1234&dn=Movie" })),
+        });
+        handle_torrent_add(&app_data, &magnet).await.unwrap();
+        let set = web::Json(TransmissionRequest {
+            method: "torrent-set".to_string(),
+            arguments: Some(json!({
+                "ids": ["ABCD1234"],
+                "seedRatioLimit": 1.5,
+                "seedRatioMode": 1,
+            })),
+        });
+        handle_torrent_set(&app_data, &set).await.unwrap();
+
+        let id = putio.transfers()[0].id;
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        putio.set_status(id, "SEEDING", Some(file));
+        putio.set_seeding(id, 0.5, 60);
+
+        let get = web::Json(TransmissionRequest {
+            method: "torrent-get".to_string(),
+            arguments: Some(json!({
+                "fields": ["seedRatioLimit", "seedRatioMode", "seedIdleLimit", "seedIdleMode", "uploadRatio"]
+            })),
+        });
+        let torrents = handle_torrent_get(&app_data.putio, &get, &app_data)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            torrents["torrents"][0],
+            json!({
+                "seedRatioLimit": 1.5,
+                "seedRatioMode": 1,
+                "seedIdleLimit": 60,
+                "seedIdleMode": 0,
+                "uploadRatio": 0.5,
+            })
+        );
+        let session = handle_session_get(&app_data).unwrap().unwrap();
+        assert_eq!(session["idle-seeding-limit"], 60);
+        assert_eq!(session["idle-seeding-limit-enabled"], true);
+
+        arr.import(&dir.path().join("movie.mkv").to_string_lossy());
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("seeding watched", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        assert!(putio.transfer(id).is_some());
+
+        // Still seeding according to put.io, but the goal has been reached.
+        putio.set_seeding(id, 1.5, 120);
+        wait_until("removed from put.io", TIMEOUT, || {
+            putio.transfer(id).is_none()
+        })
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_stops_the_download_system() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+
+        let download_system = download_system::start(app_data.clone()).await.unwrap();
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+
+        app_data.shutdown.cancel();
+        assert!(download_system.drain(TIMEOUT).await);
+
+        // Nothing is watching for the import anymore, it's picked up again on the next start.
+        let movie = dir.path().join("movie.mkv");
+        arr.import_download("efgh5678", &movie.to_string_lossy());
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert_eq!(stage(&app_data, id), Some(TransferStage::Downloaded));
+        assert!(movie.exists());
+        assert!(putio.transfer(id).is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_put_io_outage_is_polled_at_the_polling_interval() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let app_data = create_app_data(&putio, &arr, &dir);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+
+        putio.fail("transfers/list", 1000);
+        let list_errors = || {
+            app_data
+                .errors
+                .all()
+                .iter()
+                .filter(|e| e.subject == "put.io")
+                .count()
+        };
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!((1..=4).contains(&list_errors()));
+
+        // Shutting down stops polling, even while put.io is down.
+        app_data.shutdown.cancel();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let errors = list_errors();
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(list_errors(), errors);
+    }
+
+    #[actix_web::test]
+    async fn test_lifecycle_events_are_notified() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let webhook = FakeWebhook::start().await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.notifications = serde_json::from_value(json!({
+            "notifiers": [{ "kind": "webhook", "url": webhook.url() }],
+        }))
+        .unwrap();
+        let app_data = test_support::with_config(config);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+
+        download_system::start(app_data.clone()).await.unwrap();
+        let movie = dir.path().join("movie.mkv");
+        wait_until("downloaded", TIMEOUT, || movie.exists()).await;
+        arr.import_download("efgh5678", &movie.to_string_lossy());
+        wait_until("imported", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        putio.set_status(id, "COMPLETED", None);
+
+        wait_until("notified", TIMEOUT, || webhook.received().len() == 5).await;
+        // Each event is delivered on its own, so they may arrive out of order.
+        let mut events: Vec<String> = webhook
+            .received()
+            .iter()
+            .map(|e| e["event"].as_str().unwrap().to_string())
+            .collect();
+        events.sort();
+        assert_eq!(
+            events,
+            vec![
+                "downloaded",
+                "imported",
+                "queued",
+                "removed",
+                "seeding_done"
+            ]
+        );
+        assert!(webhook
+            .received()
+            .iter()
+            .all(|e| e["transfer_id"] == id && e["name"] == "Movie"));
+    }
+
+    #[actix_web::test]
+    async fn test_failing_post_download_command_fails_the_transfer() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.post_download = serde_json::from_value(json!({
+            "command": "test -f \"$PUTIOARR_PATH\" && echo bad archive >&2 && exit 1",
+        }))
+        .unwrap();
+        let app_data = test_support::with_config(config);
+
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("failed", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Failed)
+        })
+        .await;
+        let error = app_data.state.get(id).unwrap().error.unwrap();
+        assert!(error.contains("bad archive"), "{}", error);
+
+        let get = web::Json(TransmissionRequest {
+            method: "torrent-get".to_string(),
+            arguments: Some(json!({ "fields": ["id", "errorString", "status"] })),
+        });
+        let torrents = handle_torrent_get(&app_data.putio, &get, &app_data)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(torrents["torrents"][0]["errorString"], error);
+        assert_eq!(torrents["torrents"][0]["status"], 0);
+    }
+
+    #[actix_web::test]
+    async fn test_archives_are_extracted_for_the_arr() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.extract = serde_json::from_value(json!({ "enabled": true })).unwrap();
+        config.cleanup = serde_json::from_value(json!({ "local": "keep" })).unwrap();
+        let app_data = test_support::with_config(config);
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("movie.mkv", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"movie").unwrap();
+        let archive = zip.finish().unwrap().into_inner();
+        let folder = putio.add_file(ROOT, "Movie", "FOLDER", b"");
+        putio.add_file(folder, "movie.zip", "ARCHIVE", &archive);
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(folder));
+
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+        let movie = dir.path().join("Movie").join("movie.mkv");
+        assert_eq!(fs::read(&movie).unwrap(), b"movie");
+
+        // Waits for what was extracted, not the archive.
+        arr.import_download("efgh5678", &movie.to_string_lossy());
+        wait_until("imported", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        assert!(!dir.path().join("Movie").join("movie.zip").exists());
+        assert!(movie.exists());
+    }
+
+    #[actix_web::test]
+    async fn test_single_archive_is_extracted_into_a_directory_that_is_cleaned_up() {
+        let putio = FakePutio::start().await;
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let dir = TempDir::new().unwrap();
+        let mut config = create_config(&putio, &arr, &dir);
+        config.extract = serde_json::from_value(json!({ "enabled": true })).unwrap();
+        let app_data = test_support::with_config(config);
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("movie.mkv", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"movie").unwrap();
+        let archive = zip.finish().unwrap().into_inner();
+        let file = putio.add_file(ROOT, "movie.zip", "ARCHIVE", &archive);
+        let id = putio.add_transfer("Movie", "efgh5678", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(file));
+
+        download_system::start(app_data.clone()).await.unwrap();
+        wait_until("downloaded", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Downloaded)
+        })
+        .await;
+        let movie = dir.path().join("movie").join("movie.mkv");
+        assert_eq!(fs::read(&movie).unwrap(), b"movie");
+
+        arr.import_download("efgh5678", &movie.to_string_lossy());
+        wait_until("imported", TIMEOUT, || {
+            stage(&app_data, id) == Some(TransferStage::Imported)
+        })
+        .await;
+        assert!(!dir.path().join("movie.zip").exists());
+        assert!(!dir.path().join("movie").exists());
+    }
+}
@@ -1,17 +1,101 @@
-use crate::AppData;
+use crate::{metrics::METRICS, AppData};
+use actix_rt::task::JoinHandle;
 use actix_web::web::Data;
 use anyhow::Result;
+use log::{info, warn};
+use serde::Serialize;
+use std::{fs, path::Path, time::Duration};
+use tokio::time::sleep;
 
+pub mod cleanup;
 pub mod download;
+pub mod errors;
+pub mod extract;
+pub mod filter;
+pub mod hook;
+pub mod imports;
 pub mod orchestration;
+pub mod ownership;
+pub mod progress;
+pub mod state;
+pub mod torrents;
 pub mod transfer;
 
+#[cfg(test)]
+mod cleanup_tests;
+#[cfg(test)]
+mod download_tests;
+#[cfg(test)]
+mod errors_tests;
+#[cfg(test)]
+mod extract_tests;
+#[cfg(test)]
+mod filter_tests;
+#[cfg(test)]
+mod hook_tests;
+#[cfg(test)]
+mod lifecycle_tests;
+#[cfg(test)]
+mod ownership_tests;
+#[cfg(test)]
+mod progress_tests;
+#[cfg(test)]
+mod state_tests;
+#[cfg(test)]
+mod torrents_tests;
 #[cfg(test)]
 mod transfer_tests;
 
-pub async fn start(app_data: Data<AppData>) -> Result<()> {
+/// The running download system.
+pub struct DownloadSystem {
+    download_workers: Vec<JoinHandle<Result<()>>>,
+}
+
+impl DownloadSystem {
+    /// Waits at most `grace_period` for the downloads in progress to finish, once
+    /// `AppData::shutdown` has been cancelled. Returns whether they all finished. Unfinished
+    /// downloads resume on the next start.
+    pub async fn drain(self, grace_period: Duration) -> bool {
+        info!(
+            "Waiting up to {}s for downloads in progress",
+            grace_period.as_secs()
+        );
+        let workers = futures::future::join_all(self.download_workers);
+        match tokio::time::timeout(grace_period, workers).await {
+            Ok(_) => {
+                info!("Downloads finished");
+                true
+            }
+            Err(_) => {
+                warn!("Downloads still in progress, they will resume on the next start");
+                false
+            }
+        }
+    }
+}
+
+/// Sleeps for `duration`. Returns false when interrupted by a shutdown.
+pub async fn sleep_unless_shutdown(app_data: &AppData, duration: Duration) -> bool {
+    tokio::select! {
+        _ = app_data.shutdown.cancelled() => false,
+        _ = sleep(duration) => true,
+    }
+}
+
+/// Writes `value` as json to `path`. It's written to a temporary file first, so a crash never
+/// leaves a truncated file behind.
+pub fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_vec_pretty(value)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub async fn start(app_data: Data<AppData>) -> Result<DownloadSystem> {
     let (sender, receiver) = async_channel::unbounded();
     let (download_sender, download_receiver) = async_channel::unbounded();
+    METRICS.watch_queue("transfers", &sender);
+    METRICS.watch_queue("downloads", &download_sender);
     let data = app_data.clone();
     let tx = sender.clone();
     actix_rt::spawn(async { transfer::produce_transfers(data, tx).await });
@@ -24,11 +108,13 @@ pub async fn start(app_data: Data<AppData>) -> Result<()> {
         orchestration::Worker::start(id, data, tx, rx, dtx);
     }
 
-    for id in 0..app_data.config.download_workers {
-        let drx = download_receiver.clone();
-        let data = app_data.clone();
-        download::Worker::start(id, data, drx)
-    }
+    let download_workers = (0..app_data.config.download_workers)
+        .map(|id| {
+            let drx = download_receiver.clone();
+            let data = app_data.clone();
+            download::Worker::start(id, data, drx)
+        })
+        .collect();
 
-    Ok(())
+    Ok(DownloadSystem { download_workers })
 }
@@ -1,20 +1,23 @@
 use crate::{
     download_system::{
+        cleanup,
         download::{DownloadDoneStatus, DownloadTargetMessage},
-        transfer::Transfer,
+        extract, hook,
+        transfer::{DownloadTarget, Transfer},
     },
-    services::putio,
+    metrics::METRICS,
+    notify::{self, notify, Event, EventKind},
+    retry::RetryPolicy,
     AppData,
 };
 use actix_web::web::Data;
 use anyhow::Result;
 use async_channel::{Receiver, Sender};
 use colored::*;
-use log::{info, warn};
-use std::{fs, time::Duration};
-use tokio::{fs::metadata, time::sleep};
+use log::{error, info, warn};
+use std::time::Duration;
 
-use super::transfer::TransferMessage;
+use super::{sleep_unless_shutdown, state::TransferStage, transfer::TransferMessage};
 
 #[derive(Clone)]
 pub struct Worker {
@@ -43,126 +46,297 @@ impl Worker {
         let _join_handle = actix_rt::spawn(async move { s.work().await });
     }
 
+    /// Hands the targets to the download workers and waits for all of them to report back.
+    async fn download_targets(
+        &self,
+        targets: impl Iterator<Item = &DownloadTarget>,
+    ) -> Result<Vec<DownloadDoneStatus>> {
+        // Create a communications channel for the download worker to communicate status back.
+        let mut done_channels = vec![];
+        for target in targets {
+            let (done_tx, done_rx) = async_channel::unbounded();
+            self.dtx
+                .send(DownloadTargetMessage {
+                    download_target: target.clone(),
+                    tx: done_tx,
+                })
+                .await?;
+            done_channels.push(done_rx);
+        }
+
+        // Wait for all the workers having sent back their status.
+        let mut statuses = vec![];
+        for done_rx in done_channels {
+            statuses.push(done_rx.recv().await?);
+        }
+        Ok(statuses)
+    }
+
     async fn work(&self) -> Result<()> {
         loop {
-            let msg = self.rx.recv().await?;
+            let msg = tokio::select! {
+                biased;
+                _ = self.app_data.shutdown.cancelled() => return Ok(()),
+                msg = self.rx.recv() => msg?,
+            };
             let app_data = self.app_data.clone();
             match msg {
                 TransferMessage::QueuedForDownload(t) => {
                     info!("{}: download {}", t, "started".yellow());
-                    let targets = t.get_download_targets().await?;
-                    // Create a communications channel for the download worker to communicate status back.
-                    let done_channels: &Vec<(
-                        Sender<DownloadDoneStatus>,
-                        Receiver<DownloadDoneStatus>,
-                    )> = &targets.iter().map(|_| async_channel::unbounded()).collect();
-
-                    for (i, target) in targets.iter().enumerate() {
-                        let (done_tx, _) = done_channels[i].clone();
-                        self.dtx
-                            .send(DownloadTargetMessage {
-                                download_target: target.clone(),
-                                tx: done_tx,
-                            })
+                    let policy = RetryPolicy::from_config(&app_data.config);
+                    let mut attempt = 0;
+                    let targets = loop {
+                        attempt += 1;
+                        let e = match t.get_download_targets().await {
+                            Ok(targets) => break Some(targets),
+                            Err(e) => e,
+                        };
+                        if !policy.should_retry(attempt) {
+                            let error = format!(
+                                "Unable to get the files to download after {} attempts: {:#}",
+                                attempt, e
+                            );
+                            fail(&app_data, &t, &error);
+                            break None;
+                        }
+                        let delay = policy.delay(attempt);
+                        warn!(
+                            "{}: unable to get the files to download, retrying in {}s (attempt {}/{}): {:#}",
+                            t,
+                            delay.as_secs(),
+                            attempt + 1,
+                            policy.max_attempts,
+                            e
+                        );
+                        if !sleep_unless_shutdown(&app_data, delay).await {
+                            // Picked up again on the next start.
+                            return Ok(());
+                        }
+                    };
+                    let Some(targets) = targets else {
+                        continue;
+                    };
+                    // So the status page can show what's being downloaded.
+                    app_data.state.update(
+                        &Transfer {
+                            targets: Some(targets.clone()),
+                            ..t.clone()
+                        },
+                        TransferStage::QueuedForDownload,
+                    );
+                    // Number of attempts made per target. Targets that succeeded are not retried.
+                    let mut attempts = vec![0u32; targets.len()];
+                    let mut pending: Vec<usize> = (0..targets.len()).collect();
+                    let mut last_error = String::new();
+
+                    loop {
+                        let statuses = self
+                            .download_targets(pending.iter().map(|i| &targets[*i]))
                             .await?;
+
+                        let mut failed = vec![];
+                        for (i, status) in pending.iter().zip(statuses) {
+                            attempts[*i] += 1;
+                            if let DownloadDoneStatus::Failed(e) = status {
+                                last_error = format!("{}: {}", targets[*i].to, e);
+                                failed.push(*i);
+                            }
+                        }
+                        pending = failed;
+
+                        let attempt = pending.iter().map(|i| attempts[*i]).max().unwrap_or(0);
+                        if pending.is_empty() || !policy.should_retry(attempt) {
+                            break;
+                        }
+
+                        let delay = policy.delay(attempt);
+                        warn!(
+                            "{}: {} targets failed, retrying in {}s (attempt {}/{})",
+                            t,
+                            pending.len(),
+                            delay.as_secs(),
+                            attempt + 1,
+                            policy.max_attempts
+                        );
+                        METRICS.download_retries.inc();
+                        if !sleep_unless_shutdown(&app_data, delay).await {
+                            // Picked up again on the next start.
+                            return Ok(());
+                        }
                     }
 
-                    // Wait for all the workers having sent back their status.
-                    let mut all_downloaded = vec![];
-                    for (_, done_rx) in done_channels {
-                        all_downloaded.push(done_rx.recv().await?);
+                    let t = Transfer {
+                        targets: Some(targets),
+                        ..t
+                    };
+                    if !pending.is_empty() {
+                        let attempts = pending.iter().map(|i| attempts[*i]).max().unwrap_or(0);
+                        let error = format!(
+                            "Download failed after {} attempts: {}",
+                            attempts, last_error
+                        );
+                        fail(&app_data, &t, &error);
+                        continue;
                     }
+                    info!("{}: download {}", t, "done".blue());
 
-                    // Check if all are success
-                    if all_downloaded.iter().all(|d| match d {
-                        DownloadDoneStatus::Success => true,
-                        DownloadDoneStatus::Failed => false,
-                    }) {
-                        info!("{}: download {}", t, "done".blue());
-                        self.tx
-                            .send(TransferMessage::Downloaded(Transfer {
+                    let t = match extract::extract(&app_data, &t).await {
+                        Ok(extracted) => {
+                            let mut targets = t.targets.clone().unwrap_or_default();
+                            targets.extend(extracted);
+                            Transfer {
                                 targets: Some(targets),
                                 ..t
-                            }))
-                            .await?;
-                    } else {
-                        // TODO: figure out what to do here..
-                        warn!("{}: not all targets downloaded", t)
+                            }
+                        }
+                        Err(e) => {
+                            fail(&app_data, &t, &format!("Extracting failed: {:#}", e));
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = hook::post_download(&app_data, &t).await {
+                        fail(
+                            &app_data,
+                            &t,
+                            &format!("Post-download command failed: {}", e),
+                        );
+                        continue;
                     }
+                    app_data.state.update(&t, TransferStage::Downloaded);
+                    notify(&app_data, Event::new(EventKind::Downloaded, &t));
+                    self.tx.send(TransferMessage::Downloaded(t)).await?;
                 }
                 TransferMessage::Downloaded(t) => {
                     let tx = self.tx.clone();
-                    actix_rt::spawn(async { watch_for_import(app_data, tx, t).await });
+                    actix_rt::spawn(async move {
+                        let failed = Event::new(EventKind::Failed, &t);
+                        if let Err(e) = watch_for_import(app_data.clone(), tx, t).await {
+                            error!("{}: watching imports failed: {}", failed.name, e);
+                            app_data.errors.record(&failed.name, &e.to_string());
+                            notify(&app_data, failed.with_message(&e.to_string()));
+                        }
+                    });
                 }
                 TransferMessage::Imported(t) => {
-                    actix_rt::spawn(async { watch_seeding(app_data, t).await });
+                    actix_rt::spawn(async move {
+                        let failed = Event::new(EventKind::Failed, &t);
+                        if let Err(e) = watch_seeding(app_data.clone(), t).await {
+                            error!("{}: watching seeding failed: {}", failed.name, e);
+                            app_data.errors.record(&failed.name, &e.to_string());
+                            notify(&app_data, failed.with_message(&e.to_string()));
+                        }
+                    });
                 }
             }
         }
     }
 }
 
+/// Gives up on `transfer`. It stays failed until it's removed from put.io.
+fn fail(app_data: &AppData, transfer: &Transfer, error: &str) {
+    error!("{}: {}", transfer, error);
+    app_data.errors.record(&transfer.name, error);
+    METRICS.transfer_failures.inc();
+    notify(
+        app_data,
+        Event::new(EventKind::Failed, transfer).with_message(error),
+    );
+    app_data.state.fail(transfer, error);
+}
+
 async fn watch_for_import(
     app_data: Data<AppData>,
     tx: Sender<TransferMessage>,
     transfer: Transfer,
 ) -> Result<()> {
     info!("{}: watching imports", transfer);
+    let mut stall_notified = false;
     loop {
         if transfer.is_imported().await {
             info!("{}: imported", transfer);
-            let top_level_target = transfer.get_top_level();
-
-            match metadata(&top_level_target.to).await {
-                Ok(m) if m.is_dir() => {
-                    fs::remove_dir_all(&top_level_target.to).unwrap();
-                    info!("{}: deleted", &top_level_target);
-                }
-                Ok(m) if m.is_file() => {
-                    fs::remove_file(&top_level_target.to).unwrap();
-                    info!("{}: deleted", &top_level_target);
-                }
-                Ok(_) | Err(_) => {
-                    panic!("{}: no idea how to handle", &top_level_target)
+            notify(&app_data, Event::new(EventKind::Imported, &transfer));
+            if let Err(e) = extract::remove_archives(&app_data, &transfer) {
+                error!("{}: unable to delete archives: {}", transfer, e);
+                app_data
+                    .errors
+                    .record(&transfer.name, &format!("Unable to delete archives: {}", e));
+            }
+            for target in transfer.cleanup_targets() {
+                if let Err(e) = cleanup::local(&app_data, &target).await {
+                    error!("{}: unable to clean up: {}", &target, e);
+                    app_data
+                        .errors
+                        .record(&transfer.name, &format!("Unable to clean up: {}", e));
                 }
-            };
+            }
+            app_data.state.update(&transfer, TransferStage::Imported);
             let m = transfer.clone();
             tx.send(TransferMessage::Imported(m)).await?;
 
             break;
         }
-        sleep(Duration::from_secs(app_data.config.polling_interval)).await;
+        let downloaded_at = app_data
+            .state
+            .get(transfer.transfer_id)
+            .map(|s| s.updated_at);
+        if !stall_notified
+            && downloaded_at
+                .is_some_and(|at| notify::import_stalled(&app_data.config.notifications, at))
+        {
+            warn!("{}: still not imported", transfer);
+            notify(&app_data, Event::new(EventKind::ImportStalled, &transfer));
+            stall_notified = true;
+        }
+        let interval = Duration::from_secs(app_data.config.polling_interval);
+        if !sleep_unless_shutdown(&app_data, interval).await {
+            return Ok(());
+        }
     }
-    info!("{}: removed", transfer);
+    info!("{}: cleaned up", transfer);
     Ok(())
 }
 
 async fn watch_seeding(app_data: Data<AppData>, transfer: Transfer) -> Result<()> {
     info!("{}: watching seeding", transfer);
     loop {
-        let putio_transfer =
-            putio::get_transfer(&app_data.config.putio.api_key, transfer.transfer_id)
-                .await?
-                .transfer;
-        if putio_transfer.status != "SEEDING" {
-            info!("{}: stopped seeding", transfer);
-            putio::remove_transfer(&app_data.config.putio.api_key, transfer.transfer_id).await?;
-            info!("{}: removed from put.io", transfer);
-            match putio::delete_file(&app_data.config.putio.api_key, transfer.file_id.unwrap())
-                .await
-            {
-                Ok(_) => {
-                    info!("{}: deleted remote files", transfer);
-                }
-                Err(_) => {
-                    warn!("{}: unable to delete remote files", transfer);
+        let interval = Duration::from_secs(app_data.config.polling_interval);
+        let putio_transfer = match app_data.putio.get_transfer(transfer.transfer_id).await {
+            Ok(response) => response.transfer,
+            // Removed from put.io in the meantime, nothing left to clean up.
+            Err(_) if app_data.state.get(transfer.transfer_id).is_none() => return Ok(()),
+            Err(e) => {
+                warn!("{}: unable to check seeding: {:#}", transfer, e);
+                app_data
+                    .errors
+                    .record(&transfer.name, &format!("Unable to check seeding: {:#}", e));
+                if !sleep_unless_shutdown(&app_data, interval).await {
+                    return Ok(());
                 }
-            };
+                continue;
+            }
+        };
+        let goal_reached = transfer.seed_goal_reached(&putio_transfer);
+        if putio_transfer.status != "SEEDING" || goal_reached {
+            if goal_reached {
+                info!("{}: seed goal reached", transfer);
+            }
+            info!("{}: stopped seeding", transfer);
+            notify(&app_data, Event::new(EventKind::SeedingDone, &transfer));
+            cleanup::remote(&app_data, &transfer).await?;
             break;
         }
-        sleep(Duration::from_secs(app_data.config.polling_interval)).await;
+        if !sleep_unless_shutdown(&app_data, interval).await {
+            return Ok(());
+        }
     }
 
+    app_data.state.remove(transfer.transfer_id);
+    app_data.imports.forget(transfer.transfer_id);
+    if let Some(hash) = &transfer.hash {
+        app_data.progress.forget(hash);
+    }
     info!("{}: done seeding", transfer);
+    notify(&app_data, Event::new(EventKind::Removed, &transfer));
     Ok(())
 }
@@ -0,0 +1,136 @@
+use crate::{
+    services::putio::{PutIOTransfer, PutioClient},
+    AppData,
+};
+use actix_web::web::Data;
+use anyhow::Result;
+use log::{info, warn};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long the owned folders are remembered. Folders created by the proxy itself are picked up
+/// right away, this is for the ones created on put.io.
+const FOLDERS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Remembers the ids of the configured put.io folder and its category subfolders, so they aren't
+/// listed on every poll.
+#[derive(Default)]
+pub struct FolderCache {
+    folders: Mutex<Option<(Instant, Vec<i64>)>>,
+}
+
+impl FolderCache {
+    pub fn get(&self) -> Option<Vec<i64>> {
+        self.folders
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|(at, _)| at.elapsed() < FOLDERS_TTL)
+            .map(|(_, folders)| folders.clone())
+    }
+
+    pub fn set(&self, folders: Vec<i64>) {
+        *self.folders.lock().unwrap() = Some((Instant::now(), folders));
+    }
+
+    /// Forgets the folders, so they're looked up again.
+    pub fn clear(&self) {
+        *self.folders.lock().unwrap() = None;
+    }
+}
+
+/// The put.io folder a new transfer is saved in: the configured folder, with a subfolder for the
+/// category if there is one. Folders are created when needed, `None` means the root folder.
+pub async fn save_folder(app_data: &AppData, category: Option<&str>) -> Result<Option<i64>> {
+    let mut parent_id = None;
+    for name in [app_data.config.putio.folder.as_deref(), category]
+        .into_iter()
+        .flatten()
+    {
+        let parent = parent_id.unwrap_or(0);
+        let id = match find_folder(&app_data.putio, name, parent).await? {
+            Some(id) => id,
+            None => {
+                let folder = app_data.putio.create_folder(name, parent).await?;
+                info!("Created put.io folder {}", name);
+                app_data.folders.clear();
+                folder.id
+            }
+        };
+        parent_id = Some(id);
+    }
+    Ok(parent_id)
+}
+
+async fn find_folder(putio: &PutioClient, name: &str, parent_id: i64) -> Result<Option<i64>> {
+    Ok(putio
+        .list_files(parent_id)
+        .await?
+        .files
+        .into_iter()
+        .find(|f| f.file_type == "FOLDER" && f.name == name)
+        .map(|f| f.id))
+}
+
+/// The configured put.io folder and its category subfolders.
+async fn owned_folders(app_data: &AppData) -> Result<Vec<i64>> {
+    if let Some(folders) = app_data.folders.get() {
+        return Ok(folders);
+    }
+    let Some(name) = &app_data.config.putio.folder else {
+        return Ok(vec![]);
+    };
+    let mut folders = vec![];
+    if let Some(folder) = find_folder(&app_data.putio, name, 0).await? {
+        folders.push(folder);
+        folders.extend(
+            app_data
+                .putio
+                .list_files(folder)
+                .await?
+                .files
+                .into_iter()
+                .filter(|f| f.file_type == "FOLDER")
+                .map(|f| f.id),
+        );
+    }
+    app_data.folders.set(folders.clone());
+    Ok(folders)
+}
+
+/// The transfers putioarr manages. With `only_owned_transfers` these are the ones added through
+/// `torrent-add`, recognized by their hash or by being saved in the configured put.io folder.
+/// Otherwise it's all of them.
+pub async fn owned_transfers(
+    app_data: &Data<AppData>,
+    transfers: Vec<PutIOTransfer>,
+) -> Vec<PutIOTransfer> {
+    if !app_data.config.only_owned_transfers {
+        return transfers;
+    }
+
+    let added = |t: &PutIOTransfer| {
+        t.hash
+            .as_ref()
+            .is_some_and(|h| app_data.torrents.get(h).is_some())
+    };
+    // Only look at the folders when there's a transfer we don't recognize otherwise.
+    let folders = if transfers
+        .iter()
+        .any(|t| !added(t) && t.save_parent_id.is_some())
+    {
+        owned_folders(app_data).await.unwrap_or_else(|e| {
+            warn!("Unable to list the put.io folder: {}", e);
+            vec![]
+        })
+    } else {
+        vec![]
+    };
+
+    transfers
+        .into_iter()
+        .filter(|t| added(t) || t.save_parent_id.is_some_and(|p| folders.contains(&p)))
+        .collect()
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use super::super::ownership::*;
+    use crate::{
+        test_support::{
+            self,
+            putio::{FakePutio, ROOT},
+        },
+        AppData, Config, PutioConfig,
+    };
+    use actix_web::web;
+
+    fn create_app_data(
+        putio: &FakePutio,
+        only_owned_transfers: bool,
+        folder: Option<&str>,
+    ) -> web::Data<AppData> {
+        let config = test_support::config(&putio.url(), &putio.upload_url());
+        test_support::with_config(Config {
+            only_owned_transfers,
+            putio: PutioConfig {
+                folder: folder.map(String::from),
+                ..config.putio
+            },
+            ..config
+        })
+    }
+
+    async fn owned_ids(app_data: &web::Data<AppData>) -> Vec<u64> {
+        let transfers = app_data.putio.list_transfers().await.unwrap().transfers;
+        owned_transfers(app_data, transfers)
+            .await
+            .iter()
+            .map(|t| t.id)
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_save_folder_in_root() {
+        let putio = FakePutio::start().await;
+        let app_data = create_app_data(&putio, false, None);
+
+        assert_eq!(save_folder(&app_data, None).await.unwrap(), None);
+        let tv = save_folder(&app_data, Some("tv")).await.unwrap();
+        assert_eq!(tv, putio.folder(ROOT, "tv"));
+        assert!(tv.is_some());
+        // Existing folders are reused.
+        assert_eq!(save_folder(&app_data, Some("tv")).await.unwrap(), tv);
+    }
+
+    #[actix_web::test]
+    async fn test_save_folder_in_configured_folder() {
+        let putio = FakePutio::start().await;
+        let app_data = create_app_data(&putio, false, Some("putioarr"));
+
+        let folder = save_folder(&app_data, None).await.unwrap();
+        assert_eq!(folder, putio.folder(ROOT, "putioarr"));
+        let tv = save_folder(&app_data, Some("tv")).await.unwrap();
+        assert_eq!(tv, putio.folder(folder.unwrap(), "tv"));
+        assert!(putio.folder(ROOT, "tv").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_all_transfers_are_owned_by_default() {
+        let putio = FakePutio::start().await;
+        let app_data = create_app_data(&putio, false, None);
+        let manual = putio.add_transfer("Manual", "abcd1234", "SEEDING");
+
+        assert_eq!(owned_ids(&app_data).await, vec![manual]);
+    }
+
+    #[actix_web::test]
+    async fn test_only_owned_transfers_by_hash() {
+        let putio = FakePutio::start().await;
+        let app_data = create_app_data(&putio, true, None);
+        putio.add_transfer("Manual", "abcd1234", "SEEDING");
+        let added = putio.add_transfer("Added", "EFGH5678", "SEEDING");
+        app_data.torrents.add("efgh5678", None);
+
+        assert_eq!(owned_ids(&app_data).await, vec![added]);
+    }
+
+    #[actix_web::test]
+    async fn test_only_owned_transfers_by_folder() {
+        let putio = FakePutio::start().await;
+        let app_data = create_app_data(&putio, true, Some("putioarr"));
+        let folder = save_folder(&app_data, None).await.unwrap().unwrap();
+        let tv = save_folder(&app_data, Some("tv")).await.unwrap().unwrap();
+        let elsewhere = putio.add_file(ROOT, "elsewhere", "FOLDER", b"");
+
+        let manual = putio.add_transfer("Manual", "abcd1234", "SEEDING");
+        putio.set_save_parent_id(manual, elsewhere);
+        let in_folder = putio.add_transfer("In folder", "efgh5678", "SEEDING");
+        putio.set_save_parent_id(in_folder, folder);
+        let in_category = putio.add_transfer("In category", "ijkl9012", "SEEDING");
+        putio.set_save_parent_id(in_category, tv);
+        putio.add_transfer("In root", "mnop3456", "SEEDING");
+
+        assert_eq!(owned_ids(&app_data).await, vec![in_folder, in_category]);
+    }
+
+    #[actix_web::test]
+    async fn test_owned_folders_are_cached() {
+        let putio = FakePutio::start().await;
+        let app_data = create_app_data(&putio, true, Some("putioarr"));
+        let folder = save_folder(&app_data, None).await.unwrap().unwrap();
+        let in_folder = putio.add_transfer("In folder", "abcd1234", "SEEDING");
+        putio.set_save_parent_id(in_folder, folder);
+        assert_eq!(owned_ids(&app_data).await, vec![in_folder]);
+
+        // Created on put.io, so it's only seen once the cached folders are forgotten.
+        let movies = putio.add_file(folder, "movies", "FOLDER", b"");
+        let in_movies = putio.add_transfer("In movies", "efgh5678", "SEEDING");
+        putio.set_save_parent_id(in_movies, movies);
+        assert_eq!(owned_ids(&app_data).await, vec![in_folder]);
+        app_data.folders.clear();
+        assert_eq!(owned_ids(&app_data).await, vec![in_folder, in_movies]);
+
+        // Created by the proxy, so it's seen right away.
+        let tv = save_folder(&app_data, Some("tv")).await.unwrap().unwrap();
+        let in_tv = putio.add_transfer("In tv", "ijkl9012", "SEEDING");
+        putio.set_save_parent_id(in_tv, tv);
+        assert_eq!(
+            owned_ids(&app_data).await,
+            vec![in_folder, in_movies, in_tv]
+        );
+    }
+}
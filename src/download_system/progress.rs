@@ -0,0 +1,105 @@
+use super::transfer::DownloadTarget;
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+#[derive(Debug, Clone)]
+pub struct TargetProgress {
+    pub transfer_hash: String,
+    pub total: u64,
+    pub downloaded: u64,
+    /// Bytes fetched since the download (re)started, used to calculate the speed.
+    pub session_bytes: u64,
+    pub started_at: Instant,
+}
+
+impl TargetProgress {
+    pub fn bytes_per_sec(&self) -> u64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (self.session_bytes as f64 / elapsed) as u64
+        } else {
+            0
+        }
+    }
+}
+
+/// Aggregated local download progress of all targets of a transfer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub total: u64,
+    pub downloaded: u64,
+    pub bytes_per_sec: u64,
+}
+
+/// Tracks how far along the download workers are with each file target.
+#[derive(Default)]
+pub struct ProgressTracker {
+    targets: Mutex<HashMap<String, TargetProgress>>,
+}
+
+impl ProgressTracker {
+    /// Registers a target of `total` bytes of which `downloaded` are already on disk.
+    pub fn start(&self, target: &DownloadTarget, total: u64, downloaded: u64) {
+        self.targets.lock().unwrap().insert(
+            target.to.clone(),
+            TargetProgress {
+                transfer_hash: target.transfer_hash.clone(),
+                total,
+                downloaded,
+                session_bytes: 0,
+                started_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn advance(&self, target: &DownloadTarget, bytes: u64) {
+        if let Some(p) = self.targets.lock().unwrap().get_mut(&target.to) {
+            p.downloaded = (p.downloaded + bytes).min(p.total);
+            p.session_bytes += bytes;
+        }
+    }
+
+    pub fn finish(&self, target: &DownloadTarget) {
+        if let Some(p) = self.targets.lock().unwrap().get_mut(&target.to) {
+            p.downloaded = p.total;
+        }
+    }
+
+    pub fn target(&self, target: &DownloadTarget) -> Option<TargetProgress> {
+        self.targets.lock().unwrap().get(&target.to).cloned()
+    }
+
+    pub fn transfer(&self, transfer_hash: &str) -> Option<TransferProgress> {
+        let targets = self.targets.lock().unwrap();
+        let mut matching = targets
+            .values()
+            .filter(|p| p.transfer_hash == transfer_hash)
+            .peekable();
+        matching.peek()?;
+        Some(
+            matching.fold(TransferProgress::default(), |acc, p| TransferProgress {
+                total: acc.total + p.total,
+                downloaded: acc.downloaded + p.downloaded,
+                bytes_per_sec: acc.bytes_per_sec + p.bytes_per_sec(),
+            }),
+        )
+    }
+
+    /// Combined speed of all running downloads.
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.targets
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|p| p.downloaded < p.total)
+            .map(|p| p.bytes_per_sec())
+            .sum()
+    }
+
+    /// Drops all targets of a transfer.
+    pub fn forget(&self, transfer_hash: &str) {
+        self.targets
+            .lock()
+            .unwrap()
+            .retain(|_, p| p.transfer_hash != transfer_hash);
+    }
+}
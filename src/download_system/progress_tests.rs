@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        progress::*,
+        transfer::{DownloadTarget, TargetType},
+    };
+
+    fn create_target(to: &str, hash: &str) -> DownloadTarget {
+        DownloadTarget {
+            from: Some("https://example.com/file.mkv".to_string()),
+            to: to.to_string(),
+            target_type: TargetType::File,
+            top_level: false,
+            transfer_hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_progress_tracker_unknown_transfer() {
+        let tracker = ProgressTracker::default();
+        assert!(tracker.transfer("abcd1234").is_none());
+    }
+
+    #[test]
+    fn test_progress_tracker_advance_and_finish() {
+        let tracker = ProgressTracker::default();
+        let target = create_target("/downloads/a.mkv", "abcd1234");
+        tracker.start(&target, 100, 20);
+        tracker.advance(&target, 30);
+
+        let progress = tracker.target(&target).unwrap();
+        assert_eq!(progress.downloaded, 50);
+        assert_eq!(progress.session_bytes, 30);
+
+        tracker.advance(&target, 1000);
+        assert_eq!(tracker.target(&target).unwrap().downloaded, 100);
+
+        tracker.start(&target, 100, 0);
+        tracker.finish(&target);
+        assert_eq!(tracker.target(&target).unwrap().downloaded, 100);
+    }
+
+    #[test]
+    fn test_progress_tracker_aggregates_per_transfer() {
+        let tracker = ProgressTracker::default();
+        let a = create_target("/downloads/show/a.mkv", "abcd1234");
+        let b = create_target("/downloads/show/b.mkv", "abcd1234");
+        let other = create_target("/downloads/movie.mkv", "ffff0000");
+        tracker.start(&a, 100, 100);
+        tracker.start(&b, 200, 50);
+        tracker.start(&other, 300, 0);
+
+        let progress = tracker.transfer("abcd1234").unwrap();
+        assert_eq!(progress.total, 300);
+        assert_eq!(progress.downloaded, 150);
+    }
+
+    #[test]
+    fn test_progress_tracker_forget() {
+        let tracker = ProgressTracker::default();
+        let a = create_target("/downloads/a.mkv", "abcd1234");
+        let other = create_target("/downloads/movie.mkv", "ffff0000");
+        tracker.start(&a, 100, 0);
+        tracker.start(&other, 100, 0);
+
+        tracker.forget("abcd1234");
+        assert!(tracker.transfer("abcd1234").is_none());
+        assert!(tracker.transfer("ffff0000").is_some());
+    }
+}
@@ -0,0 +1,203 @@
+use super::{
+    transfer::{DownloadTarget, Transfer},
+    write_json,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Where a transfer is in the download pipeline. Mirrors `TransferMessage`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TransferStage {
+    QueuedForDownload,
+    Downloaded,
+    Imported,
+    /// Gave up after retrying, see `TransferState::error`.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferState {
+    pub transfer_id: u64,
+    pub name: String,
+    pub hash: Option<String>,
+    pub file_id: Option<i64>,
+    pub stage: TransferStage,
+    pub targets: Option<Vec<DownloadTarget>>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A downloaded target to delete once `delete_at` has passed, see `LocalCleanup::DeleteAfter`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledDeletion {
+    pub target: DownloadTarget,
+    pub delete_at: DateTime<Utc>,
+}
+
+/// The contents of the state file. Older versions only stored the transfers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StateFile {
+    Transfers(Vec<TransferState>),
+    State {
+        transfers: Vec<TransferState>,
+        #[serde(default)]
+        deletions: Vec<ScheduledDeletion>,
+    },
+}
+
+/// Keeps track of the transfers in the pipeline and persists them, so we can pick up where we
+/// left off after a restart.
+#[derive(Default)]
+pub struct StateStore {
+    path: Option<PathBuf>,
+    transfers: Mutex<HashMap<u64, TransferState>>,
+    deletions: Mutex<Vec<ScheduledDeletion>>,
+}
+
+impl StateStore {
+    /// Loads the state from `path`. A missing file results in an empty store.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (states, deletions) = if path.exists() {
+            let (states, deletions) = match serde_json::from_slice(&fs::read(&path)?)? {
+                StateFile::Transfers(states) => (states, vec![]),
+                StateFile::State {
+                    transfers,
+                    deletions,
+                } => (transfers, deletions),
+            };
+            info!("Loaded {} transfers from {}", states.len(), path.display());
+            (states, deletions)
+        } else {
+            (vec![], vec![])
+        };
+
+        Ok(Self {
+            path: Some(path),
+            transfers: Mutex::new(states.into_iter().map(|s| (s.transfer_id, s)).collect()),
+            deletions: Mutex::new(deletions),
+        })
+    }
+
+    pub fn get(&self, transfer_id: u64) -> Option<TransferState> {
+        self.transfers.lock().unwrap().get(&transfer_id).cloned()
+    }
+
+    pub fn all(&self) -> Vec<TransferState> {
+        self.transfers.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Records that `transfer` reached `stage`.
+    pub fn update(&self, transfer: &Transfer, stage: TransferStage) {
+        self.set(transfer, stage, None);
+    }
+
+    /// Records that `transfer` failed for good.
+    pub fn fail(&self, transfer: &Transfer, error: &str) {
+        self.set(transfer, TransferStage::Failed, Some(error.to_string()));
+    }
+
+    fn set(&self, transfer: &Transfer, stage: TransferStage, error: Option<String>) {
+        let mut transfers = self.transfers.lock().unwrap();
+        let now = Utc::now();
+        let created_at = transfers
+            .get(&transfer.transfer_id)
+            .map(|s| s.created_at)
+            .unwrap_or(now);
+        transfers.insert(
+            transfer.transfer_id,
+            TransferState {
+                transfer_id: transfer.transfer_id,
+                name: transfer.name.clone(),
+                hash: transfer.hash.clone(),
+                file_id: transfer.file_id,
+                stage,
+                targets: transfer.targets.clone(),
+                error,
+                created_at,
+                updated_at: now,
+            },
+        );
+        self.save(&transfers);
+    }
+
+    pub fn remove(&self, transfer_id: u64) {
+        let mut transfers = self.transfers.lock().unwrap();
+        if transfers.remove(&transfer_id).is_some() {
+            self.save(&transfers);
+        }
+    }
+
+    /// Forgets about all transfers that are not in `transfer_ids`.
+    pub fn retain(&self, transfer_ids: &[u64]) {
+        let mut transfers = self.transfers.lock().unwrap();
+        let before = transfers.len();
+        transfers.retain(|id, _| transfer_ids.contains(id));
+        if transfers.len() != before {
+            self.save(&transfers);
+        }
+    }
+
+    /// Remembers to delete `target` at `delete_at`.
+    pub fn schedule_deletion(&self, target: &DownloadTarget, delete_at: DateTime<Utc>) {
+        let transfers = self.transfers.lock().unwrap();
+        let mut deletions = self.deletions.lock().unwrap();
+        deletions.retain(|d| d.target.to != target.to);
+        deletions.push(ScheduledDeletion {
+            target: target.clone(),
+            delete_at,
+        });
+        self.save_all(&transfers, &deletions);
+    }
+
+    /// Forgets and returns the targets whose deletion is due.
+    pub fn take_due_deletions(&self) -> Vec<DownloadTarget> {
+        let transfers = self.transfers.lock().unwrap();
+        let mut deletions = self.deletions.lock().unwrap();
+        let now = Utc::now();
+        let (due, pending): (Vec<_>, Vec<_>) =
+            deletions.drain(..).partition(|d| d.delete_at <= now);
+        *deletions = pending;
+        if !due.is_empty() {
+            self.save_all(&transfers, &deletions);
+        }
+        due.into_iter().map(|d| d.target).collect()
+    }
+
+    pub fn scheduled_deletions(&self) -> Vec<ScheduledDeletion> {
+        self.deletions.lock().unwrap().clone()
+    }
+
+    /// Writes the state to disk, e.g. before exiting.
+    pub fn persist(&self) {
+        self.save(&self.transfers.lock().unwrap());
+    }
+
+    fn save(&self, transfers: &HashMap<u64, TransferState>) {
+        self.save_all(transfers, &self.deletions.lock().unwrap());
+    }
+
+    fn save_all(&self, transfers: &HashMap<u64, TransferState>, deletions: &[ScheduledDeletion]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut states: Vec<&TransferState> = transfers.values().collect();
+        states.sort_by_key(|s| s.transfer_id);
+        let state = json!({ "transfers": states, "deletions": deletions });
+        if let Err(e) = write_json(path, &state) {
+            warn!("Unable to save state to {}: {}", path.display(), e);
+        }
+    }
+}
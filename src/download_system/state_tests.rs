@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        state::*,
+        transfer::{DownloadTarget, TargetType, Transfer},
+    };
+    use crate::{services::putio, test_support};
+    use chrono::{TimeDelta, Utc};
+    use tempfile::TempDir;
+
+    fn create_transfer(transfer_id: u64) -> Transfer {
+        Transfer {
+            name: format!("Transfer {}", transfer_id),
+            file_id: Some(456),
+            hash: Some("abcd1234".to_string()),
+            transfer_id,
+            targets: Some(vec![DownloadTarget {
+                from: None,
+                to: "/tmp/downloads/file.mkv".to_string(),
+                target_type: TargetType::File,
+                top_level: true,
+                transfer_hash: "abcd1234".to_string(),
+            }]),
+            app_data: test_support::app_data(putio::API_URL, putio::UPLOAD_URL),
+        }
+    }
+
+    #[test]
+    fn test_state_store_update_and_get() {
+        let store = StateStore::default();
+        store.update(&create_transfer(1), TransferStage::QueuedForDownload);
+
+        let state = store.get(1).unwrap();
+        assert_eq!(state.transfer_id, 1);
+        assert_eq!(state.name, "Transfer 1");
+        assert_eq!(state.stage, TransferStage::QueuedForDownload);
+        assert!(store.get(2).is_none());
+    }
+
+    #[test]
+    fn test_state_store_update_keeps_created_at() {
+        let store = StateStore::default();
+        let transfer = create_transfer(1);
+        store.update(&transfer, TransferStage::QueuedForDownload);
+        let created_at = store.get(1).unwrap().created_at;
+
+        store.update(&transfer, TransferStage::Downloaded);
+        let state = store.get(1).unwrap();
+        assert_eq!(state.stage, TransferStage::Downloaded);
+        assert_eq!(state.created_at, created_at);
+        assert!(state.updated_at >= created_at);
+    }
+
+    #[test]
+    fn test_state_store_fail_records_error() {
+        let store = StateStore::default();
+        let transfer = create_transfer(1);
+        store.fail(&transfer, "connection reset");
+
+        let state = store.get(1).unwrap();
+        assert_eq!(state.stage, TransferStage::Failed);
+        assert_eq!(state.error, Some("connection reset".to_string()));
+
+        store.update(&transfer, TransferStage::Downloaded);
+        assert!(store.get(1).unwrap().error.is_none());
+    }
+
+    #[test]
+    fn test_state_store_remove_and_retain() {
+        let store = StateStore::default();
+        for id in 1..=3 {
+            store.update(&create_transfer(id), TransferStage::Imported);
+        }
+
+        store.remove(1);
+        assert!(store.get(1).is_none());
+
+        store.retain(&[3]);
+        assert!(store.get(2).is_none());
+        assert!(store.get(3).is_some());
+        assert_eq!(store.all().len(), 1);
+    }
+
+    #[test]
+    fn test_state_store_persists_across_loads() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+
+        let store = StateStore::load(&path).unwrap();
+        assert!(store.all().is_empty());
+        store.update(&create_transfer(1), TransferStage::Downloaded);
+        store.update(&create_transfer(2), TransferStage::Imported);
+        store.remove(2);
+
+        let reloaded = StateStore::load(&path).unwrap();
+        let state = reloaded.get(1).unwrap();
+        assert_eq!(state.stage, TransferStage::Downloaded);
+        assert_eq!(state.hash, Some("abcd1234".to_string()));
+        assert_eq!(state.targets.unwrap()[0].to, "/tmp/downloads/file.mkv");
+        assert!(reloaded.get(2).is_none());
+        assert!(!dir.path().join("state.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_state_store_persists_scheduled_deletions() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        let target = |to: &str| DownloadTarget {
+            from: None,
+            to: to.to_string(),
+            target_type: TargetType::Directory,
+            top_level: true,
+            transfer_hash: "abcd1234".to_string(),
+        };
+
+        let store = StateStore::load(&path).unwrap();
+        store.update(&create_transfer(1), TransferStage::Imported);
+        store.schedule_deletion(&target("/tmp/downloads/due"), Utc::now());
+        store.schedule_deletion(
+            &target("/tmp/downloads/later"),
+            Utc::now() + TimeDelta::hours(1),
+        );
+
+        let reloaded = StateStore::load(&path).unwrap();
+        assert!(reloaded.get(1).is_some());
+        assert_eq!(reloaded.scheduled_deletions().len(), 2);
+        let due = reloaded.take_due_deletions();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].to, "/tmp/downloads/due");
+
+        let reloaded = StateStore::load(&path).unwrap();
+        let deletions = reloaded.scheduled_deletions();
+        assert_eq!(deletions.len(), 1);
+        assert_eq!(deletions[0].target.to, "/tmp/downloads/later");
+    }
+
+    #[test]
+    fn test_state_store_loads_transfers_only_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        let store = StateStore::default();
+        store.update(&create_transfer(1), TransferStage::Downloaded);
+        std::fs::write(&path, serde_json::to_vec(&store.all()).unwrap()).unwrap();
+
+        let loaded = StateStore::load(&path).unwrap();
+        assert_eq!(loaded.get(1).unwrap().stage, TransferStage::Downloaded);
+        assert!(loaded.scheduled_deletions().is_empty());
+    }
+
+    #[test]
+    fn test_state_store_persist() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+
+        let store = StateStore::load(&path).unwrap();
+        store.persist();
+        assert!(StateStore::load(&path).unwrap().all().is_empty());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_state_store_load_rejects_corrupt_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        assert!(StateStore::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_transfer_from_state() {
+        let store = StateStore::default();
+        let transfer = create_transfer(7);
+        store.update(&transfer, TransferStage::Downloaded);
+
+        let restored = Transfer::from_state(transfer.app_data.clone(), &store.get(7).unwrap());
+        assert_eq!(restored.transfer_id, 7);
+        assert_eq!(restored.name, transfer.name);
+        assert_eq!(restored.file_id, transfer.file_id);
+        assert_eq!(restored.targets.unwrap().len(), 1);
+    }
+}
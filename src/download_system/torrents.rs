@@ -0,0 +1,189 @@
+use super::write_json;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// How long a torrent we added may be missing from the put.io transfers before we forget about it.
+const MISSING_GRACE_PERIOD: Duration = Duration::hours(1);
+
+/// What we know about a torrent that was added through `torrent-add`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TorrentInfo {
+    /// Lower case info hash.
+    pub hash: String,
+    /// The category (label) the *arr added the torrent with.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Seed ratio limit set through `torrent-set`.
+    #[serde(default)]
+    pub seed_ratio_limit: SeedLimit<f64>,
+    /// Seeding time limit in minutes set through `torrent-set`.
+    #[serde(default)]
+    pub seed_time_limit: SeedLimit<u64>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// A seed limit of a single torrent, like Transmission's seedRatioMode/seedIdleMode.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedLimit<T> {
+    /// Use the limit from the config.
+    #[default]
+    Global,
+    Limit(T),
+    Unlimited,
+}
+
+impl<T: Copy> SeedLimit<T> {
+    /// Parses the Transmission mode (0 global, 1 single, 2 unlimited) and limit.
+    pub fn from_transmission(mode: Option<u64>, limit: Option<T>) -> Option<Self> {
+        match (mode, limit) {
+            (Some(0), _) => Some(Self::Global),
+            (Some(1) | None, Some(limit)) => Some(Self::Limit(limit)),
+            (Some(2), _) => Some(Self::Unlimited),
+            _ => None,
+        }
+    }
+
+    /// The Transmission mode of this limit.
+    pub fn mode(&self) -> u32 {
+        match self {
+            Self::Global => 0,
+            Self::Limit(_) => 1,
+            Self::Unlimited => 2,
+        }
+    }
+
+    /// The limit that applies, given the `global` one.
+    pub fn resolve(&self, global: Option<T>) -> Option<T> {
+        match self {
+            Self::Global => global,
+            Self::Limit(limit) => Some(*limit),
+            Self::Unlimited => None,
+        }
+    }
+}
+
+/// Keeps track of the torrents added through `torrent-add` by their hash, since that's all we
+/// know about them until put.io creates a transfer.
+#[derive(Default)]
+pub struct TorrentStore {
+    path: Option<PathBuf>,
+    torrents: Mutex<HashMap<String, TorrentInfo>>,
+}
+
+impl TorrentStore {
+    /// Loads the torrents from `path`. A missing file results in an empty store.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let torrents = if path.exists() {
+            let torrents: Vec<TorrentInfo> = serde_json::from_slice(&fs::read(&path)?)?;
+            info!("Loaded {} torrents from {}", torrents.len(), path.display());
+            torrents.into_iter().map(|t| (t.hash.clone(), t)).collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            path: Some(path),
+            torrents: Mutex::new(torrents),
+        })
+    }
+
+    pub fn get(&self, hash: &str) -> Option<TorrentInfo> {
+        self.torrents
+            .lock()
+            .unwrap()
+            .get(&hash.to_lowercase())
+            .cloned()
+    }
+
+    pub fn category(&self, hash: Option<&str>) -> Option<String> {
+        hash.and_then(|h| self.get(h)).and_then(|t| t.category)
+    }
+
+    /// The seed ratio and time limits set for the torrent with `hash`.
+    pub fn seed_limits(&self, hash: Option<&str>) -> (SeedLimit<f64>, SeedLimit<u64>) {
+        match hash.and_then(|h| self.get(h)) {
+            Some(t) => (t.seed_ratio_limit, t.seed_time_limit),
+            None => (SeedLimit::Global, SeedLimit::Global),
+        }
+    }
+
+    pub fn add(&self, hash: &str, category: Option<String>) {
+        let mut torrents = self.torrents.lock().unwrap();
+        let hash = hash.to_lowercase();
+        torrents.insert(
+            hash.clone(),
+            TorrentInfo {
+                hash,
+                category,
+                seed_ratio_limit: SeedLimit::Global,
+                seed_time_limit: SeedLimit::Global,
+                added_at: Utc::now(),
+            },
+        );
+        self.save(&torrents);
+    }
+
+    /// Sets the seed limits of torrent `hash`, leaving the ones that are `None` alone. Returns
+    /// false when we don't know the torrent.
+    pub fn set_seed_limits(
+        &self,
+        hash: &str,
+        ratio: Option<SeedLimit<f64>>,
+        time: Option<SeedLimit<u64>>,
+    ) -> bool {
+        let mut torrents = self.torrents.lock().unwrap();
+        let Some(torrent) = torrents.get_mut(&hash.to_lowercase()) else {
+            return false;
+        };
+        if let Some(ratio) = ratio {
+            torrent.seed_ratio_limit = ratio;
+        }
+        if let Some(time) = time {
+            torrent.seed_time_limit = time;
+        }
+        self.save(&torrents);
+        true
+    }
+
+    pub fn remove(&self, hash: &str) {
+        let mut torrents = self.torrents.lock().unwrap();
+        if torrents.remove(&hash.to_lowercase()).is_some() {
+            self.save(&torrents);
+        }
+    }
+
+    /// Forgets about the torrents that are no longer on put.io. Recently added ones are kept, as
+    /// put.io might not have created their transfer yet.
+    pub fn retain(&self, hashes: &[String]) {
+        let mut torrents = self.torrents.lock().unwrap();
+        let before = torrents.len();
+        let cutoff = Utc::now() - MISSING_GRACE_PERIOD;
+        torrents.retain(|hash, t| {
+            t.added_at > cutoff || hashes.iter().any(|h| h.eq_ignore_ascii_case(hash))
+        });
+        if torrents.len() != before {
+            self.save(&torrents);
+        }
+    }
+
+    fn save(&self, torrents: &HashMap<String, TorrentInfo>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut list: Vec<&TorrentInfo> = torrents.values().collect();
+        list.sort_by_key(|t| t.added_at);
+        if let Err(e) = write_json(path, &list) {
+            warn!("Unable to save torrents to {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use super::super::torrents::*;
+    use chrono::{Duration, Utc};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_torrent_store_add_and_get() {
+        let store = TorrentStore::default();
+        store.add("ABCD1234", Some("tv-sonarr".to_string()));
+        store.add("efgh5678", None);
+
+        let torrent = store.get("abcd1234").unwrap();
+        assert_eq!(torrent.hash, "abcd1234");
+        assert_eq!(torrent.category, Some("tv-sonarr".to_string()));
+        assert_eq!(
+            store.category(Some("ABCD1234")),
+            Some("tv-sonarr".to_string())
+        );
+        assert_eq!(store.category(Some("efgh5678")), None);
+        assert_eq!(store.category(None), None);
+    }
+
+    #[test]
+    fn test_torrent_store_remove() {
+        let store = TorrentStore::default();
+        store.add("abcd1234", None);
+        store.remove("ABCD1234");
+        assert!(store.get("abcd1234").is_none());
+    }
+
+    #[test]
+    fn test_torrent_store_retain_keeps_recent_torrents() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("torrents.json");
+        let old = Utc::now() - Duration::hours(2);
+        fs::write(
+            &path,
+            format!(
+                r#"[{{"hash": "abcd1234", "added_at": "{old}"}}, {{"hash": "efgh5678", "added_at": "{old}"}}]"#,
+                old = old.to_rfc3339()
+            ),
+        )
+        .unwrap();
+        let store = TorrentStore::load(&path).unwrap();
+        store.add("ijkl9012", None);
+
+        store.retain(&["ABCD1234".to_string()]);
+        assert!(store.get("abcd1234").is_some());
+        assert!(store.get("efgh5678").is_none());
+        // Not on put.io yet, but just added.
+        assert!(store.get("ijkl9012").is_some());
+    }
+
+    #[test]
+    fn test_torrent_store_persists_across_loads() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("torrents.json");
+
+        let store = TorrentStore::load(&path).unwrap();
+        store.add("abcd1234", Some("radarr".to_string()));
+
+        let reloaded = TorrentStore::load(&path).unwrap();
+        assert_eq!(reloaded.get("abcd1234"), store.get("abcd1234"));
+        assert!(!dir.path().join("torrents.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_seed_limit_from_transmission() {
+        assert_eq!(
+            SeedLimit::from_transmission(Some(0), Some(2.0)),
+            Some(SeedLimit::Global)
+        );
+        assert_eq!(
+            SeedLimit::from_transmission(Some(1), Some(2.0)),
+            Some(SeedLimit::Limit(2.0))
+        );
+        assert_eq!(
+            SeedLimit::from_transmission(None, Some(30)),
+            Some(SeedLimit::Limit(30))
+        );
+        assert_eq!(
+            SeedLimit::<u64>::from_transmission(Some(2), None),
+            Some(SeedLimit::Unlimited)
+        );
+        assert_eq!(SeedLimit::<u64>::from_transmission(Some(1), None), None);
+        assert_eq!(SeedLimit::<u64>::from_transmission(None, None), None);
+    }
+
+    #[test]
+    fn test_seed_limit_resolve() {
+        assert_eq!(SeedLimit::Global.resolve(Some(1.0)), Some(1.0));
+        assert_eq!(SeedLimit::Global.resolve(None::<f64>), None);
+        assert_eq!(SeedLimit::Limit(2.0).resolve(Some(1.0)), Some(2.0));
+        assert_eq!(SeedLimit::Unlimited.resolve(Some(1.0)), None);
+        assert_eq!(SeedLimit::<u64>::Unlimited.mode(), 2);
+    }
+
+    #[test]
+    fn test_torrent_store_set_seed_limits() {
+        let store = TorrentStore::default();
+        assert!(!store.set_seed_limits("abcd1234", Some(SeedLimit::Limit(2.0)), None));
+
+        store.add("abcd1234", None);
+        assert!(store.set_seed_limits("ABCD1234", Some(SeedLimit::Limit(2.0)), None));
+        assert!(store.set_seed_limits("abcd1234", None, Some(SeedLimit::Unlimited)));
+        assert_eq!(
+            store.seed_limits(Some("abcd1234")),
+            (SeedLimit::Limit(2.0), SeedLimit::Unlimited)
+        );
+        assert_eq!(
+            store.seed_limits(None),
+            (SeedLimit::Global, SeedLimit::Global)
+        );
+    }
+}
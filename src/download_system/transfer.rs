@@ -1,7 +1,14 @@
+use super::{
+    cleanup,
+    filter::{self, FileFilter},
+    ownership, sleep_unless_shutdown,
+    state::{TransferStage, TransferState},
+};
 use crate::{
+    notify::{notify, Event, EventKind},
     services::{
-        arr,
-        putio::{self, PutIOTransfer},
+        arr::{self, ArrService},
+        putio::PutIOTransfer,
     },
     AppData,
 };
@@ -13,7 +20,6 @@ use colored::*;
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, path::Path};
-use tokio::time::sleep;
 
 #[derive(Clone)]
 pub struct Transfer {
@@ -28,56 +34,159 @@ pub struct Transfer {
 impl Transfer {
     pub async fn is_imported(&self) -> bool {
         let targets = self.targets.as_ref().unwrap().clone();
-        let mut check_services = Vec::<(&str, String, String)>::new();
-        if let Some(a) = &self.app_data.config.sonarr {
-            check_services.push(("Sonarr", a.url.clone(), a.api_key.clone()))
-        }
-        if let Some(a) = &self.app_data.config.radarr {
-            check_services.push(("Radarr", a.url.clone(), a.api_key.clone()))
-        }
-        if let Some(a) = &self.app_data.config.whisparr {
-            check_services.push(("Whisparr", a.url.clone(), a.api_key.clone()))
-        }
+        let check_services = self.arr_services();
 
-        let targets = targets
+        let mut targets = targets
             .into_iter()
             .filter(|t| t.target_type == TargetType::File)
             .collect::<Vec<DownloadTarget>>();
-        // .map(|t| t.to.clone())
-        // .collect::<Vec<String>>();
-
-        let mut results = Vec::<bool>::new();
-        for target in targets {
-            let mut service_results = vec![];
-            for (service_name, url, key) in &check_services {
-                let service_result = match arr::check_imported(&target.to, key, url).await {
-                    Ok(r) => r,
+        // Archives are never imported, what gets extracted from them is. Only wait for them when
+        // nothing has been extracted yet.
+        if self.app_data.config.extract.enabled
+            && targets.iter().any(|t| !filter::is_archive(&t.to))
+        {
+            targets.retain(|t| !filter::is_archive(&t.to));
+        }
+        // Subtitles and the like are imported along with the media file, without showing up in
+        // the history. Only wait for them if there's nothing else.
+        if targets.iter().any(|t| !filter::is_sidecar(&t.to)) {
+            targets.retain(|t| !filter::is_sidecar(&t.to));
+        }
+
+        let imports = &self.app_data.imports;
+        let pending = |targets: &[DownloadTarget]| -> Vec<DownloadTarget> {
+            targets
+                .iter()
+                .filter(|t| !imports.is_imported(self.transfer_id, &t.to))
+                .cloned()
+                .collect()
+        };
+        let mut unknown_to = vec![];
+        for service in &check_services {
+            let pending = pending(&targets);
+            if pending.is_empty() {
+                break;
+            }
+
+            // Ask for the history of this download only. The *arr may see the files under a
+            // different path, so match on file names, which are unique within a download anyway.
+            let paths = match &self.hash {
+                Some(hash) => match arr::imported_paths(hash, service).await {
+                    Ok(paths) => paths,
                     Err(e) => {
-                        error!("Error retrieving history from {}: {}", service_name, e);
-                        false
+                        error!("Error retrieving history from {}: {}", service.name, e);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let Some(paths) = paths else {
+                unknown_to.push(service);
+                continue;
+            };
+            imports.mark_known(self.transfer_id);
+            for target in pending {
+                if paths.iter().any(|p| file_name(p) == file_name(&target.to)) {
+                    self.found_imported(&target, service);
+                }
+            }
+        }
+
+        // Not a download any *arr knows about, e.g. because it was added by hand. Look for the
+        // paths in the whole history, which is expensive, so misses aren't looked up again for a
+        // while.
+        if !imports.is_known(self.transfer_id) {
+            for service in unknown_to {
+                for target in pending(&targets) {
+                    if imports.recently_missed(self.transfer_id, &service.name, &target.to) {
+                        continue;
+                    }
+                    match arr::check_imported(&target.to, service).await {
+                        Ok(true) => self.found_imported(&target, service),
+                        Ok(false) => {
+                            imports.mark_missed(self.transfer_id, &service.name, &target.to)
+                        }
+                        Err(e) => error!("Error retrieving history from {}: {}", service.name, e),
                     }
-                };
-                if service_result {
-                    info!(
-                        "{}: found imported by {}",
-                        &target,
-                        service_name.bright_blue()
-                    );
                 }
-                service_results.push(service_result)
             }
-            // Check if ANY of the service_results are true and put the outcome in results
-            results.push(service_results.into_iter().any(|x| x));
         }
         // Check if all targets have been imported
-        results.into_iter().all(|x| x)
+        targets
+            .iter()
+            .all(|t| imports.is_imported(self.transfer_id, &t.to))
     }
 
     pub async fn get_download_targets(&self) -> Result<Vec<DownloadTarget>> {
         info!("{}: generating targets", self);
         let default = "0000".to_string();
         let hash = self.hash.as_ref().unwrap_or(&default).as_str();
-        recurse_download_targets(&self.app_data, self.file_id.unwrap(), hash, None, true).await
+        let filter = FileFilter::from(&self.app_data.config);
+        recurse_download_targets(
+            &self.app_data,
+            &filter,
+            self.file_id.unwrap(),
+            hash,
+            Some(self.download_directory()),
+            true,
+        )
+        .await
+    }
+
+    /// The category the *arr added this transfer with, if it was added through us.
+    pub fn category(&self) -> Option<String> {
+        self.app_data.torrents.category(self.hash.as_deref())
+    }
+
+    /// Where the files of this transfer are downloaded to.
+    pub fn download_directory(&self) -> String {
+        self.app_data
+            .config
+            .category_directory(self.category().as_deref())
+    }
+
+    /// Whether `putio_transfer` has seeded enough, according to the limits set for it through
+    /// `torrent-set` or else the config.
+    pub fn seed_goal_reached(&self, putio_transfer: &PutIOTransfer) -> bool {
+        let seeding = &self.app_data.config.seeding;
+        let (ratio, time) = self.app_data.torrents.seed_limits(self.hash.as_deref());
+        let ratio_reached = ratio
+            .resolve(seeding.ratio_limit)
+            .zip(putio_transfer.current_ratio)
+            .is_some_and(|(limit, ratio)| ratio >= limit);
+        let time_reached = time
+            .resolve(seeding.time_limit)
+            .zip(putio_transfer.seconds_seeding)
+            .is_some_and(|(limit, seconds)| seconds >= limit * 60);
+        ratio_reached || time_reached
+    }
+
+    /// The *arr services that may import this transfer. When an instance is configured with the
+    /// category of the transfer, that's the only one that will.
+    fn arr_services(&self) -> Vec<arr::ArrService> {
+        let services = self.app_data.config.arr_services();
+        let Some(category) = self.category() else {
+            return services;
+        };
+        let (matching, other): (Vec<_>, Vec<_>) = services
+            .into_iter()
+            .partition(|s| s.category.as_deref() == Some(category.as_str()));
+        if matching.is_empty() {
+            other
+        } else {
+            matching
+        }
+    }
+
+    fn found_imported(&self, target: &DownloadTarget, service: &ArrService) {
+        info!(
+            "{}: found imported by {}",
+            target,
+            service.name.bright_blue()
+        );
+        self.app_data
+            .imports
+            .mark_imported(self.transfer_id, &target.to);
     }
 
     pub fn get_top_level(&self) -> DownloadTarget {
@@ -89,6 +198,24 @@ impl Transfer {
             .unwrap()
     }
 
+    /// What to clean up once the transfer has been imported: the top level target, and the
+    /// directory a transfer that's a single archive was extracted into.
+    pub fn cleanup_targets(&self) -> Vec<DownloadTarget> {
+        let top_level = self.get_top_level();
+        let extracted = self
+            .targets
+            .iter()
+            .flatten()
+            .filter(|t| {
+                t.target_type == TargetType::Directory
+                    && !t.top_level
+                    && !Path::new(&t.to).starts_with(&top_level.to)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        [vec![top_level], extracted].concat()
+    }
+
     pub fn from(app_data: Data<AppData>, transfer: &PutIOTransfer) -> Self {
         let default = &"Unknown".to_string();
         let name = transfer.name.as_ref().unwrap_or(default);
@@ -101,6 +228,22 @@ impl Transfer {
             app_data,
         }
     }
+
+    pub fn from_state(app_data: Data<AppData>, state: &TransferState) -> Self {
+        Self {
+            transfer_id: state.transfer_id,
+            name: state.name.clone(),
+            file_id: state.file_id,
+            targets: state.targets.clone(),
+            hash: state.hash.clone(),
+            app_data,
+        }
+    }
+}
+
+/// The last component of `path`, which may come from a Windows host.
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
 }
 
 impl Display for Transfer {
@@ -115,6 +258,7 @@ impl Display for Transfer {
 #[async_recursion]
 async fn recurse_download_targets(
     app_data: &Data<AppData>,
+    filter: &FileFilter,
     file_id: i64,
     hash: &str,
     override_base_path: Option<String>,
@@ -122,46 +266,47 @@ async fn recurse_download_targets(
 ) -> Result<Vec<DownloadTarget>> {
     let base_path = override_base_path.unwrap_or(app_data.config.download_directory.clone());
     let mut targets = Vec::<DownloadTarget>::new();
-    let response = putio::list_files(&app_data.config.putio.api_key, file_id).await?;
+    let response = app_data.putio.list_files(file_id).await?;
     let to = Path::new(&base_path)
         .join(&response.parent.name)
         .to_string_lossy()
         .to_string();
 
     match response.parent.file_type.as_str() {
-        "FOLDER" => {
+        "FOLDER"
             if !app_data
                 .config
                 .skip_directories
-                .contains(&response.parent.name.to_lowercase())
-            {
-                let new_base_path = to.clone();
-
-                targets.push(DownloadTarget {
-                    from: None,
-                    target_type: TargetType::Directory,
-                    to,
-                    top_level,
-                    transfer_hash: hash.to_string(),
-                });
-
-                for file in response.files {
-                    targets.append(
-                        &mut recurse_download_targets(
-                            app_data,
-                            file.id,
-                            hash,
-                            Some(new_base_path.clone()),
-                            false,
-                        )
-                        .await?,
-                    );
-                }
+                .contains(&response.parent.name.to_lowercase()) =>
+        {
+            let new_base_path = to.clone();
+
+            targets.push(DownloadTarget {
+                from: None,
+                target_type: TargetType::Directory,
+                to,
+                top_level,
+                transfer_hash: hash.to_string(),
+            });
+
+            for file in response.files {
+                targets.append(
+                    &mut recurse_download_targets(
+                        app_data,
+                        filter,
+                        file.id,
+                        hash,
+                        Some(new_base_path.clone()),
+                        false,
+                    )
+                    .await?,
+                );
             }
         }
-        "VIDEO" => {
+        "FOLDER" => {}
+        _ if filter.matches(&response.parent) => {
             // Get download URL for file
-            let url = putio::url(&app_data.config.putio.api_key, response.parent.id).await?;
+            let url = app_data.putio.url(response.parent.id).await?;
             targets.push(DownloadTarget {
                 from: Some(url),
                 target_type: TargetType::File,
@@ -211,17 +356,44 @@ pub async fn produce_transfers(app_data: Data<AppData>, tx: Sender<TransferMessa
     let putio_check_interval = std::time::Duration::from_secs(app_data.config.polling_interval);
     let mut seen = Vec::<u64>::new();
 
+    // Downloads that were due for deletion while we weren't running.
+    cleanup::delete_due(&app_data);
+
     info!("Checking unfinished transfers");
     // We only need to check if something has been imported. Just by looking at the filesystem we
     // can't determine if a transfer has been imported and removed or hasn't been downloaded.
     // This avoids downloading a tranfer that has already been imported. In case there is a download,
     // but it wasn't (completely) imported, we will attempt a (partial) download. Files that have
     // been completed downloading will be skipped.
-    for putio_transfer in &putio::list_transfers(&app_data.config.putio.api_key)
-        .await?
-        .transfers
-    {
+    let transfers = app_data.putio.list_transfers().await?.transfers;
+    for putio_transfer in &ownership::owned_transfers(&app_data, transfers).await {
         let name = putio_transfer.name.clone().unwrap_or("??".to_string());
+        if let Some(state) = app_data.state.get(putio_transfer.id) {
+            // We already know where this transfer is in the pipeline, resume from there.
+            let transfer = Transfer::from_state(app_data.clone(), &state);
+            match state.stage {
+                // Will be picked up again by the monitoring loop below.
+                TransferStage::QueuedForDownload => {
+                    info!("{}: resuming download", &transfer);
+                }
+                TransferStage::Downloaded => {
+                    info!("{}: resuming import watch", &transfer);
+                    seen.push(transfer.transfer_id);
+                    tx.send(TransferMessage::Downloaded(transfer)).await?;
+                }
+                TransferStage::Imported => {
+                    info!("{}: resuming seeding watch", &transfer);
+                    seen.push(transfer.transfer_id);
+                    tx.send(TransferMessage::Imported(transfer)).await?;
+                }
+                // Stays failed until it's removed from put.io.
+                TransferStage::Failed => {
+                    warn!("{}: previously failed", &transfer);
+                    seen.push(transfer.transfer_id);
+                }
+            }
+            continue;
+        }
         let mut transfer = Transfer::from(app_data.clone(), putio_transfer);
         if putio_transfer.is_downloadable() {
             info!("Getting download target for {name}");
@@ -235,6 +407,7 @@ pub async fn produce_transfers(app_data: Data<AppData>, tx: Sender<TransferMessa
             if transfer.is_imported().await {
                 info!("{}: already imported", &transfer);
                 seen.push(transfer.transfer_id);
+                app_data.state.update(&transfer, TransferStage::Imported);
                 tx.send(TransferMessage::Imported(transfer)).await?;
             } else {
                 info!("{}: not imported yet", &transfer);
@@ -247,47 +420,63 @@ pub async fn produce_transfers(app_data: Data<AppData>, tx: Sender<TransferMessa
     let mut start = std::time::Instant::now();
 
     loop {
-        if let Ok(list_transfer_response) =
-            putio::list_transfers(&app_data.config.putio.api_key).await
-        {
-            for putio_transfer in &list_transfer_response.transfers {
-                if seen.contains(&putio_transfer.id) || !putio_transfer.is_downloadable() {
-                    continue;
+        let list_transfer_response = match app_data.putio.list_transfers().await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("List put.io transfers failed. Retrying..");
+                app_data
+                    .errors
+                    .record("put.io", &format!("Unable to list transfers: {}", e));
+                if !sleep_unless_shutdown(&app_data, putio_check_interval).await {
+                    info!("Stopped monitoring transfers");
+                    return Ok(());
                 }
-                let transfer = Transfer::from(app_data.clone(), putio_transfer);
-
-                info!("{}: ready for download", transfer);
-                tx.send(TransferMessage::QueuedForDownload(transfer))
-                    .await?;
-                seen.push(putio_transfer.id);
+                continue;
             }
+        };
+        let active_hashes: Vec<String> = list_transfer_response
+            .transfers
+            .iter()
+            .filter_map(|t| t.hash.clone())
+            .collect();
+        let transfers =
+            ownership::owned_transfers(&app_data, list_transfer_response.transfers).await;
+        for putio_transfer in &transfers {
+            if seen.contains(&putio_transfer.id) || !putio_transfer.is_downloadable() {
+                continue;
+            }
+            let transfer = Transfer::from(app_data.clone(), putio_transfer);
 
-            // Remove any transfers from seen that are not in the active transfers
-            let active_ids: Vec<u64> = list_transfer_response
-                .transfers
+            info!("{}: ready for download", transfer);
+            app_data
+                .state
+                .update(&transfer, TransferStage::QueuedForDownload);
+            notify(&app_data, Event::new(EventKind::Queued, &transfer));
+            tx.send(TransferMessage::QueuedForDownload(transfer))
+                .await?;
+            seen.push(putio_transfer.id);
+        }
+
+        // Remove any transfers from seen that are not in the active transfers
+        let active_ids: Vec<u64> = transfers.iter().map(|t| t.id).collect();
+        seen.retain(|t| active_ids.contains(t));
+        app_data.state.retain(&active_ids);
+        app_data.torrents.retain(&active_hashes);
+        cleanup::delete_due(&app_data);
+
+        // Log status when 60 seconds have passed since last time
+        if start.elapsed().as_secs() >= 60 {
+            info!("Active transfers: {}", transfers.len());
+            transfers
                 .iter()
-                .map(|t| t.id)
-                .collect();
-            seen.retain(|t| active_ids.contains(t));
-
-            // Log status when 60 seconds have passed since last time
-            if start.elapsed().as_secs() >= 60 {
-                info!(
-                    "Active transfers: {}",
-                    list_transfer_response.transfers.len()
-                );
-                list_transfer_response
-                    .transfers
-                    .iter()
-                    .for_each(|t| info!("  {}", Transfer::from(app_data.clone(), t)));
+                .for_each(|t| info!("  {}", Transfer::from(app_data.clone(), t)));
 
-                start = std::time::Instant::now();
-            }
+            start = std::time::Instant::now();
+        }
 
-            sleep(putio_check_interval).await;
-        } else {
-            warn!("List put.io transfers failed. Retrying..");
-            continue;
-        };
+        if !sleep_unless_shutdown(&app_data, putio_check_interval).await {
+            info!("Stopped monitoring transfers");
+            return Ok(());
+        }
     }
 }
@@ -1,41 +1,30 @@
 #[cfg(test)]
 mod tests {
     use super::super::transfer::*;
-    use crate::{services::putio::PutIOTransfer, AppData, ArrConfig, Config, PutioConfig};
+    use crate::{
+        services::putio::{self, PutIOTransfer},
+        test_support, AppData, ArrConfig, Config,
+    };
     use actix_web::web;
 
     fn create_test_config() -> Config {
         Config {
-            bind_address: "127.0.0.1".to_string(),
-            download_directory: "/tmp/downloads".to_string(),
-            download_workers: 4,
-            loglevel: "info".to_string(),
-            orchestration_workers: 10,
-            password: "test".to_string(),
-            polling_interval: 10,
-            port: 9091,
-            skip_directories: vec!["sample".to_string(), "extras".to_string()],
-            uid: 1000,
-            username: "test".to_string(),
-            putio: PutioConfig {
-                api_key: "test_key".to_string(),
-            },
             sonarr: Some(ArrConfig {
                 url: "http://localhost:8989".to_string(),
                 api_key: "sonarr_key".to_string(),
+                category: None,
             }),
             radarr: Some(ArrConfig {
                 url: "http://localhost:7878".to_string(),
                 api_key: "radarr_key".to_string(),
+                category: None,
             }),
-            whisparr: None,
+            ..test_support::config(putio::API_URL, putio::UPLOAD_URL)
         }
     }
 
     fn create_test_app_data() -> web::Data<AppData> {
-        web::Data::new(AppData {
-            config: create_test_config(),
-        })
+        test_support::with_config(create_test_config())
     }
 
     #[test]
@@ -165,6 +154,9 @@ mod tests {
             error_message: None,
             file_id: Some(456),
             userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transfer = Transfer::from(app_data, &putio_transfer);
@@ -192,6 +184,9 @@ mod tests {
             error_message: None,
             file_id: Some(999),
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transfer = Transfer::from(app_data, &putio_transfer);
@@ -215,6 +210,9 @@ mod tests {
             error_message: None,
             file_id: Some(1),
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transfer = Transfer::from(app_data, &putio_transfer);
@@ -240,6 +238,9 @@ mod tests {
             error_message: None,
             file_id: Some(789),
             userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transfer = Transfer::from(app_data, &putio_transfer);
@@ -265,6 +266,9 @@ mod tests {
             error_message: None,
             file_id: Some(1),
             userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let mut transfer = Transfer::from(app_data, &putio_transfer);
@@ -306,6 +310,9 @@ mod tests {
             error_message: None,
             file_id: Some(1),
             userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transfer = Transfer::from(app_data, &putio_transfer);
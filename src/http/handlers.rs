@@ -1,30 +1,55 @@
 use crate::{
     // downloader::DownloadStatus,
-    services::putio::{self, PutIOTransfer},
-    services::transmission::{TransmissionRequest, TransmissionTorrent},
+    download_system::{ownership, state::TransferStage, torrents::SeedLimit},
+    services::putio::{PutIOTransfer, PutioClient},
+    services::transmission::{
+        LocalProgress, TransmissionConfig, TransmissionRequest, TransmissionTorrent,
+        TransmissionTorrentStatus,
+    },
     AppData,
 };
 use actix_web::web;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use base64::Engine;
 use colored::Colorize;
 use lava_torrent::torrent::v1::Torrent;
-use log::info;
+use log::{info, warn};
 use magnet_url::Magnet;
-use serde_json::json;
+use nix::sys::statvfs::statvfs;
+use serde_json::{json, Value};
+use std::path::Path;
 
 pub(crate) async fn handle_torrent_add(
-    api_token: &str,
+    app_data: &web::Data<AppData>,
     payload: &web::Json<TransmissionRequest>,
 ) -> Result<Option<serde_json::Value>> {
-    let arguments = payload.arguments.as_ref().unwrap().as_object().unwrap();
-    if arguments.contains_key("metainfo") {
+    let putio = &app_data.putio;
+    let arguments = payload
+        .arguments
+        .as_ref()
+        .and_then(|a| a.as_object())
+        .context("arguments missing")?;
+    let category = requested_category(arguments, &app_data.config.download_directory);
+    let parent_id = match ownership::save_folder(app_data, category.as_deref()).await {
+        Ok(id) => id,
+        Err(e) => {
+            warn!(
+                "Unable to find put.io folder, saving in the root folder: {}",
+                e
+            );
+            None
+        }
+    };
+
+    let hash = if arguments.contains_key("metainfo") {
         // .torrent files
-        let b64 = arguments["metainfo"].as_str().unwrap();
+        let b64 = arguments["metainfo"]
+            .as_str()
+            .context("metainfo argument is not a string")?;
         let bytes = base64::engine::general_purpose::STANDARD
             .decode(b64)
-            .unwrap();
-        putio::upload_file(api_token, &bytes).await?;
+            .context("metainfo argument is not base64")?;
+        putio.upload_file(&bytes, parent_id).await?;
 
         match Torrent::read_from_bytes(bytes) {
             Ok(t) => {
@@ -33,92 +58,444 @@ pub(crate) async fn handle_torrent_add(
                     "{}: torrent uploaded",
                     format!("[ffff: {}]", t.name).magenta()
                 );
+                Some(t.info_hash())
             }
-            Err(_) => info!("New torrent uploaded"),
-        };
+            Err(_) => {
+                info!("New torrent uploaded");
+                None
+            }
+        }
     } else {
         // Magnet links
-        let magnet_url = arguments["filename"].as_str().unwrap();
-        putio::add_transfer(api_token, magnet_url).await?;
+        let magnet_url = arguments
+            .get("filename")
+            .and_then(|f| f.as_str())
+            .context("filename argument missing")?;
+        putio.add_transfer(magnet_url, parent_id).await?;
         match Magnet::new(magnet_url) {
-            Ok(m) => match m.display_name() {
-                Some(name) => {
-                    info!(
-                        "{}: magnet link uploaded",
-                        format!("[ffff: {}]", urldecode::decode(name.to_string())).magenta()
-                    );
+            Ok(m) => {
+                match m.display_name() {
+                    Some(name) => {
+                        info!(
+                            "{}: magnet link uploaded",
+                            format!("[ffff: {}]", urldecode::decode(name.to_string())).magenta()
+                        );
+                    }
+                    None => {
+                        info!("unknown magnet link uploaded");
+                    }
                 }
-                None => {
-                    info!("unknown magnet link uploaded");
-                }
-            },
+                magnet_hash(&m)
+            }
             Err(_) => {
                 info!("unknown magnet link uploaded");
+                None
             }
         }
     };
+
+    if let Some(hash) = hash {
+        app_data.torrents.add(&hash, category);
+    }
     Ok(None)
 }
 
-pub(crate) async fn handle_torrent_remove(
-    api_token: &str,
-    payload: &web::Json<TransmissionRequest>,
-) -> Option<serde_json::Value> {
-    // TODO: leanup all the unwrap stuff
-    let arguments = payload.arguments.as_ref().unwrap().as_object().unwrap();
-    let ids: Vec<&str> = arguments
-        .get("ids")
-        .unwrap()
-        .as_array()
-        .unwrap()
-        .iter()
-        .map(|id| id.as_str().unwrap())
-        .collect();
-    let delete_local_data = arguments
-        .get("delete-local-data")
-        .unwrap()
-        .as_bool()
-        .unwrap();
-
-    let putio_transfers: Vec<PutIOTransfer> = putio::list_transfers(api_token)
-        .await
-        .unwrap()
-        .transfers
-        .into_iter()
-        .filter(|t| ids.contains(&t.hash.clone().unwrap_or(String::from("no_hash")).as_str()))
-        .collect();
+/// The info hash of magnet link `m` as lower case hex, which is how put.io reports it. Magnet
+/// links may carry it base32 encoded as well.
+pub(crate) fn magnet_hash(m: &Magnet) -> Option<String> {
+    let hash = m.hash()?;
+    if hash.len() == 32 {
+        if let Some(hex) = base32_to_hex(hash) {
+            return Some(hex);
+        }
+    }
+    Some(hash.to_lowercase())
+}
 
-    for t in putio_transfers {
-        putio::remove_transfer(api_token, t.id).await.unwrap();
+fn base32_to_hex(s: &str) -> Option<String> {
+    let mut hex = String::with_capacity(s.len() * 5 / 4);
+    let (mut bits, mut n) = (0u32, 0);
+    for c in s.bytes().map(|c| c.to_ascii_uppercase()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        bits = (bits << 5) | value as u32;
+        n += 5;
+        if n >= 8 {
+            n -= 8;
+            hex.push_str(&format!("{:02x}", (bits >> n) & 0xff));
+            bits &= (1 << n) - 1;
+        }
+    }
+    Some(hex)
+}
 
-        if t.userfile_exists && delete_local_data {
-            putio::delete_file(api_token, t.file_id.unwrap())
-                .await
-                .unwrap();
+/// The category an *arr asked for when adding a torrent: the first label, or else the
+/// `download-dir` relative to our download directory.
+pub(crate) fn requested_category(
+    arguments: &serde_json::Map<String, Value>,
+    download_directory: &str,
+) -> Option<String> {
+    let label = arguments
+        .get("labels")
+        .and_then(|l| l.as_array())
+        .and_then(|l| l.first())
+        .and_then(|l| l.as_str());
+    let category = match label {
+        Some(label) => label,
+        None => {
+            let dir = Path::new(arguments.get("download-dir")?.as_str()?);
+            // Paths from the *arr may use another mount point, fall back to the last component.
+            match dir.strip_prefix(download_directory) {
+                Ok(relative) => relative.components().next()?.as_os_str().to_str()?,
+                Err(_) => dir.file_name()?.to_str()?,
+            }
         }
+    };
+
+    // The category becomes a directory, so it has to be a single, harmless path component.
+    let category = category.trim().replace(['/', '\\'], "_");
+    if category.is_empty() || category == "." || category == ".." {
+        return None;
     }
+    Some(category)
+}
 
-    None
+pub(crate) async fn handle_torrent_remove(
+    app_data: &web::Data<AppData>,
+    payload: &web::Json<TransmissionRequest>,
+) -> Result<Option<serde_json::Value>> {
+    let putio = &app_data.putio;
+    let ids = required_ids(payload)?;
+    let delete_local_data = payload
+        .arguments
+        .as_ref()
+        .and_then(|a| a.get("delete-local-data"))
+        .and_then(|d| d.as_bool())
+        .unwrap_or(false);
+    let transfers = owned_transfers(app_data).await?;
+
+    for t in filter_transfers(Some(ids), transfers) {
+        putio.remove_transfer(t.id).await?;
+        if let Some(file_id) = t.file_id.filter(|_| t.userfile_exists && delete_local_data) {
+            putio.delete_file(file_id).await?;
+        }
+        info!(
+            "{}: removed",
+            format!("[{}: {}]", t.id, t.name.unwrap_or_default()).magenta()
+        );
+    }
+    Ok(None)
 }
 
 pub(crate) async fn handle_torrent_get(
-    api_token: &str,
+    putio: &PutioClient,
+    payload: &web::Json<TransmissionRequest>,
     app_data: &web::Data<AppData>,
-) -> Option<serde_json::Value> {
-    let transfers = putio::list_transfers(api_token).await.unwrap().transfers;
+) -> Result<Option<serde_json::Value>> {
+    let arguments = payload.arguments.as_ref();
+    let ids = arguments.and_then(|a| a.get("ids"));
+    let fields: Option<Vec<String>> = arguments
+        .and_then(|a| a.get("fields"))
+        .and_then(|f| f.as_array())
+        .map(|f| {
+            f.iter()
+                .filter_map(|f| f.as_str().map(String::from))
+                .collect()
+        });
+    let table = arguments
+        .and_then(|a| a.get("format"))
+        .and_then(|f| f.as_str())
+        == Some("table");
+
+    let transfers =
+        ownership::owned_transfers(app_data, putio.list_transfers().await?.transfers).await;
+    let transfers = filter_transfers(ids, transfers);
 
     let transmission_transfers = transfers.into_iter().map(|t| async {
+        let hash = t.hash.clone();
+        let category = app_data.torrents.category(hash.as_deref());
         let mut tt: TransmissionTorrent = t.into();
-        tt.download_dir = app_data.config.download_directory.clone();
+        tt.download_dir = app_data.config.category_directory(category.as_deref());
+        tt.labels = category.into_iter().collect();
+
+        let seeding = &app_data.config.seeding;
+        let (ratio, time) = app_data.torrents.seed_limits(hash.as_deref());
+        tt.seed_ratio_mode = ratio.mode();
+        tt.seed_ratio_limit = ratio.resolve(seeding.ratio_limit).unwrap_or(0.0) as f32;
+        tt.seed_idle_mode = time.mode();
+        tt.seed_idle_limit = time.resolve(seeding.time_limit).unwrap_or(0);
+
+        let state = app_data.state.get(tt.id);
+        let progress = hash.and_then(|h| app_data.progress.transfer(&h));
+        let local = LocalProgress {
+            downloaded: progress.as_ref().map(|p| p.downloaded).unwrap_or(0),
+            bytes_per_sec: progress.as_ref().map(|p| p.bytes_per_sec).unwrap_or(0),
+            done: state.as_ref().is_some_and(|s| {
+                matches!(s.stage, TransferStage::Downloaded | TransferStage::Imported)
+            }),
+        };
+        let mut tt = tt.with_local_progress(&local);
+
+        if let Some(state) = state {
+            if state.stage == TransferStage::Failed {
+                tt.status = TransmissionTorrentStatus::Stopped;
+                tt.error_string = state.error;
+            }
+        }
         tt
     });
     let transmission_transfers: Vec<TransmissionTorrent> =
         futures::future::join_all(transmission_transfers).await;
 
-    let torrents = json!(transmission_transfers);
+    let torrents = transmission_transfers
+        .into_iter()
+        .map(|t| json!(t))
+        .collect::<Vec<Value>>();
 
     let mut arguments = serde_json::Map::new();
-    arguments.insert(String::from("torrents"), torrents);
+    arguments.insert(
+        String::from("torrents"),
+        select_fields(torrents, fields.as_deref(), table),
+    );
+    // We don't keep track of removals, but clients asking for recently active torrents expect it.
+    if ids.and_then(|i| i.as_str()) == Some("recently-active") {
+        arguments.insert(String::from("removed"), json!([]));
+    }
+
+    Ok(Some(json!(arguments)))
+}
+
+/// Reduces the torrents to the requested `fields`, in either the `objects` (default) or `table`
+/// layout. In the table layout the first row holds the field names and every following row the
+/// values of one torrent.
+pub(crate) fn select_fields(torrents: Vec<Value>, fields: Option<&[String]>, table: bool) -> Value {
+    let fields: Vec<String> = match fields {
+        Some(fields) => fields.to_vec(),
+        None if table => torrents
+            .first()
+            .and_then(|t| t.as_object())
+            .map(|t| t.keys().cloned().collect())
+            .unwrap_or_default(),
+        None => return json!(torrents),
+    };
+
+    let objects = torrents.into_iter().map(|t| {
+        fields
+            .iter()
+            .filter_map(|f| t.get(f).map(|v| (f.clone(), v.clone())))
+            .collect::<serde_json::Map<String, Value>>()
+    });
+
+    if table {
+        // Unknown fields are left out, so only use the names of fields that exist.
+        let objects: Vec<_> = objects.collect();
+        let header: Vec<&String> = match objects.first() {
+            Some(first) => fields.iter().filter(|f| first.contains_key(*f)).collect(),
+            None => fields.iter().collect(),
+        };
+        let mut rows = vec![json!(header)];
+        rows.extend(
+            objects
+                .iter()
+                .map(|o| json!(header.iter().map(|f| &o[*f]).collect::<Vec<_>>())),
+        );
+        json!(rows)
+    } else {
+        json!(objects.collect::<Vec<_>>())
+    }
+}
+
+/// Picks the transfers matching the Transmission `ids` argument: absent means all transfers,
+/// otherwise a single id or a list of numeric ids and hash strings. `recently-active` selects
+/// transfers that are being worked on.
+pub(crate) fn filter_transfers(
+    ids: Option<&Value>,
+    transfers: Vec<PutIOTransfer>,
+) -> Vec<PutIOTransfer> {
+    let ids = match ids {
+        None | Some(Value::Null) => return transfers,
+        Some(Value::String(s)) if s == "recently-active" => {
+            return transfers.into_iter().filter(is_active).collect()
+        }
+        Some(Value::Array(ids)) => ids.clone(),
+        Some(id) => vec![id.clone()],
+    };
+
+    transfers
+        .into_iter()
+        .filter(|t| {
+            ids.iter().any(|id| match id {
+                Value::Number(n) => n.as_u64() == Some(t.id),
+                Value::String(s) => t.hash.as_ref().is_some_and(|h| h.eq_ignore_ascii_case(s)),
+                _ => false,
+            })
+        })
+        .collect()
+}
+
+/// The Transmission `ids` argument of methods that can't be undone. Leaving it out would select
+/// every transfer on put.io, so it has to be given.
+fn required_ids(payload: &TransmissionRequest) -> Result<&Value> {
+    payload
+        .arguments
+        .as_ref()
+        .and_then(|a| a.get("ids"))
+        .filter(|ids| !ids.is_null())
+        .context("ids argument missing")
+}
+
+/// The put.io transfers the proxy manages.
+async fn owned_transfers(app_data: &web::Data<AppData>) -> Result<Vec<PutIOTransfer>> {
+    let transfers = app_data.putio.list_transfers().await?.transfers;
+    Ok(ownership::owned_transfers(app_data, transfers).await)
+}
+
+fn is_active(transfer: &PutIOTransfer) -> bool {
+    matches!(
+        transfer.status.to_uppercase().as_str(),
+        "DOWNLOADING" | "IN_QUEUE" | "PREPARING_DOWNLOAD" | "COMPLETING" | "SEEDING"
+    )
+}
+
+/// Only the seed limits can be set, seedIdleLimit is used as the seeding time limit since put.io
+/// doesn't tell us how long a torrent has been idle.
+pub(crate) async fn handle_torrent_set(
+    app_data: &web::Data<AppData>,
+    payload: &web::Json<TransmissionRequest>,
+) -> Result<Option<serde_json::Value>> {
+    let arguments = payload.arguments.as_ref();
+    let argument = |name: &str| arguments.and_then(|a| a.get(name));
+    let ratio = SeedLimit::from_transmission(
+        argument("seedRatioMode").and_then(|m| m.as_u64()),
+        argument("seedRatioLimit").and_then(|l| l.as_f64()),
+    );
+    let time = SeedLimit::from_transmission(
+        argument("seedIdleMode").and_then(|m| m.as_u64()),
+        argument("seedIdleLimit").and_then(|l| l.as_u64()),
+    );
+    if ratio.is_none() && time.is_none() {
+        return Ok(None);
+    }
+
+    let transfers = owned_transfers(app_data).await?;
+    for t in filter_transfers(argument("ids"), transfers) {
+        let Some(hash) = &t.hash else {
+            continue;
+        };
+        if app_data.torrents.set_seed_limits(hash, ratio, time) {
+            info!(
+                "{}: seed limits set",
+                format!("[{}: {}]", t.id, t.name.unwrap_or_default()).magenta()
+            );
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) async fn handle_torrent_stop(
+    app_data: &web::Data<AppData>,
+    payload: &web::Json<TransmissionRequest>,
+) -> Result<Option<serde_json::Value>> {
+    let ids = required_ids(payload)?;
+    let transfers = owned_transfers(app_data).await?;
+
+    // put.io can't pause a transfer, cancelling is the closest we get.
+    for t in filter_transfers(Some(ids), transfers) {
+        app_data.putio.cancel_transfer(t.id).await?;
+        info!(
+            "{}: cancelled",
+            format!("[{}: {}]", t.id, t.name.unwrap_or_default()).magenta()
+        );
+    }
+    Ok(None)
+}
+
+pub(crate) async fn handle_torrent_start(
+    app_data: &web::Data<AppData>,
+    payload: &web::Json<TransmissionRequest>,
+) -> Result<Option<serde_json::Value>> {
+    let ids = payload.arguments.as_ref().and_then(|a| a.get("ids"));
+    let transfers = owned_transfers(app_data).await?;
+
+    // Everything else is already running on put.io, only failed transfers can be restarted.
+    for t in filter_transfers(ids, transfers)
+        .into_iter()
+        .filter(|t| t.status.eq_ignore_ascii_case("ERROR"))
+    {
+        app_data.putio.retry_transfer(t.id).await?;
+        info!(
+            "{}: retried",
+            format!("[{}: {}]", t.id, t.name.unwrap_or_default()).magenta()
+        );
+    }
+    Ok(None)
+}
+
+pub(crate) fn handle_session_get(
+    app_data: &web::Data<AppData>,
+) -> Result<Option<serde_json::Value>> {
+    let seeding = &app_data.config.seeding;
+    let mut config = TransmissionConfig {
+        download_dir: app_data.config.download_directory.clone(),
+        ..Default::default()
+    };
+    if let Some(ratio) = seeding.ratio_limit {
+        config.seed_ratio_limit = ratio as f32;
+        config.seed_ratio_limited = true;
+    }
+    if let Some(time) = seeding.time_limit {
+        config.idle_seeding_limit = time;
+        config.idle_seeding_limit_enabled = true;
+    }
+    Ok(Some(json!(config)))
+}
+
+pub(crate) async fn handle_session_stats(
+    app_data: &web::Data<AppData>,
+) -> Result<Option<serde_json::Value>> {
+    let transfers = owned_transfers(app_data).await?;
+    let active = transfers.iter().filter(|t| is_active(t)).count();
+    let downloaded: i64 = transfers.iter().map(|t| t.downloaded.unwrap_or(0)).sum();
+    let stats = json!({
+        "uploadedBytes": 0,
+        "downloadedBytes": downloaded,
+        "filesAdded": transfers.len(),
+        "sessionCount": 1,
+        "secondsActive": 0,
+    });
+
+    Ok(Some(json!({
+        "activeTorrentCount": active,
+        "pausedTorrentCount": transfers.len() - active,
+        "torrentCount": transfers.len(),
+        "downloadSpeed": app_data.progress.bytes_per_sec(),
+        "uploadSpeed": 0,
+        "cumulative-stats": stats,
+        "current-stats": stats,
+    })))
+}
+
+pub(crate) fn handle_free_space(
+    payload: &web::Json<TransmissionRequest>,
+    app_data: &web::Data<AppData>,
+) -> Result<Option<serde_json::Value>> {
+    let path = payload
+        .arguments
+        .as_ref()
+        .and_then(|a| a.get("path"))
+        .and_then(|p| p.as_str())
+        .unwrap_or(&app_data.config.download_directory)
+        .to_string();
+
+    let stat = statvfs(path.as_str()).with_context(|| format!("free-space {}", path))?;
+    let fragment_size = stat.fragment_size() as u64;
 
-    Some(json!(arguments))
+    Ok(Some(json!({
+        "path": path,
+        "size-bytes": stat.blocks_available() as u64 * fragment_size,
+        "total_size": stat.blocks() as u64 * fragment_size,
+    })))
 }
@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod tests {
+    use super::super::handlers::*;
+    use crate::services::putio::PutIOTransfer;
+    use serde_json::json;
+
+    fn create_transfer(id: u64, hash: &str, status: &str) -> PutIOTransfer {
+        PutIOTransfer {
+            id,
+            hash: Some(hash.to_string()),
+            name: Some(format!("Transfer {}", id)),
+            size: Some(1000),
+            downloaded: Some(500),
+            finished_at: None,
+            estimated_time: None,
+            status: status.to_string(),
+            started_at: None,
+            error_message: None,
+            file_id: None,
+            userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
+        }
+    }
+
+    fn create_transfers() -> Vec<PutIOTransfer> {
+        vec![
+            create_transfer(1, "aaaa1111", "DOWNLOADING"),
+            create_transfer(2, "bbbb2222", "COMPLETED"),
+            create_transfer(3, "cccc3333", "SEEDING"),
+        ]
+    }
+
+    fn ids(transfers: Vec<PutIOTransfer>) -> Vec<u64> {
+        transfers.into_iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_filter_transfers_without_ids() {
+        assert_eq!(
+            ids(filter_transfers(None, create_transfers())),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            ids(filter_transfers(Some(&json!(null)), create_transfers())),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_filter_transfers_by_numeric_id() {
+        assert_eq!(
+            ids(filter_transfers(Some(&json!(2)), create_transfers())),
+            vec![2]
+        );
+        assert_eq!(
+            ids(filter_transfers(Some(&json!([1, 3])), create_transfers())),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn test_filter_transfers_by_hash() {
+        assert_eq!(
+            ids(filter_transfers(
+                Some(&json!("bbbb2222")),
+                create_transfers()
+            )),
+            vec![2]
+        );
+        assert_eq!(
+            ids(filter_transfers(
+                Some(&json!(["AAAA1111", 3])),
+                create_transfers()
+            )),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn test_filter_transfers_recently_active() {
+        assert_eq!(
+            ids(filter_transfers(
+                Some(&json!("recently-active")),
+                create_transfers()
+            )),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn test_filter_transfers_no_match() {
+        assert!(filter_transfers(Some(&json!(["ffff0000", 42])), create_transfers()).is_empty());
+    }
+
+    fn create_torrents() -> Vec<serde_json::Value> {
+        vec![
+            json!({"id": 1, "name": "First", "status": 4, "totalSize": 100}),
+            json!({"id": 2, "name": "Second", "status": 6, "totalSize": 200}),
+        ]
+    }
+
+    #[test]
+    fn test_select_fields_all_fields() {
+        let selected = select_fields(create_torrents(), None, false);
+        assert_eq!(selected, json!(create_torrents()));
+    }
+
+    #[test]
+    fn test_select_fields_objects() {
+        let fields = vec!["id".to_string(), "status".to_string(), "bogus".to_string()];
+        let selected = select_fields(create_torrents(), Some(&fields), false);
+        assert_eq!(
+            selected,
+            json!([{"id": 1, "status": 4}, {"id": 2, "status": 6}])
+        );
+    }
+
+    #[test]
+    fn test_select_fields_table() {
+        let fields = vec!["name".to_string(), "id".to_string(), "bogus".to_string()];
+        let selected = select_fields(create_torrents(), Some(&fields), true);
+        assert_eq!(
+            selected,
+            json!([["name", "id"], ["First", 1], ["Second", 2]])
+        );
+    }
+
+    #[test]
+    fn test_select_fields_table_without_fields() {
+        let selected = select_fields(create_torrents(), None, true);
+        assert_eq!(
+            selected,
+            json!([
+                ["id", "name", "status", "totalSize"],
+                [1, "First", 4, 100],
+                [2, "Second", 6, 200]
+            ])
+        );
+    }
+
+    #[test]
+    fn test_select_fields_table_empty() {
+        let fields = vec!["id".to_string()];
+        assert_eq!(select_fields(vec![], Some(&fields), true), json!([["id"]]));
+    }
+
+    fn arguments(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_requested_category_from_labels() {
+        let args = arguments(json!({
+            "labels": ["tv-sonarr", "other"],
+            "download-dir": "/downloads/ignored",
+        }));
+        assert_eq!(
+            requested_category(&args, "/downloads"),
+            Some("tv-sonarr".to_string())
+        );
+    }
+
+    #[test]
+    fn test_requested_category_from_download_dir() {
+        let args = arguments(json!({ "download-dir": "/downloads/radarr/extra" }));
+        assert_eq!(
+            requested_category(&args, "/downloads"),
+            Some("radarr".to_string())
+        );
+        // The *arr may see the download directory under another path.
+        let args = arguments(json!({ "download-dir": "/data/torrents/radarr" }));
+        assert_eq!(
+            requested_category(&args, "/downloads"),
+            Some("radarr".to_string())
+        );
+    }
+
+    #[test]
+    fn test_requested_category_none() {
+        assert_eq!(
+            requested_category(&arguments(json!({})), "/downloads"),
+            None
+        );
+        let args = arguments(json!({ "download-dir": "/downloads" }));
+        assert_eq!(requested_category(&args, "/downloads"), None);
+        let args = arguments(json!({ "labels": [".."] }));
+        assert_eq!(requested_category(&args, "/downloads"), None);
+        let args = arguments(json!({ "labels": ["tv/../.."] }));
+        assert_eq!(
+            requested_category(&args, "/downloads"),
+            Some("tv_.._..".to_string())
+        );
+    }
+}
@@ -1,5 +1,13 @@
 pub mod handlers;
 pub mod routes;
+pub mod session;
+pub mod status;
 
+#[cfg(test)]
+mod handlers_tests;
 #[cfg(test)]
 mod routes_tests;
+#[cfg(test)]
+mod session_tests;
+#[cfg(test)]
+mod status_tests;
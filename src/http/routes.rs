@@ -1,57 +1,98 @@
 use crate::{
-    http::handlers::{handle_torrent_add, handle_torrent_get, handle_torrent_remove},
-    services::transmission::{TransmissionConfig, TransmissionRequest, TransmissionResponse},
+    http::{
+        handlers::{
+            handle_free_space, handle_session_get, handle_session_stats, handle_torrent_add,
+            handle_torrent_get, handle_torrent_remove, handle_torrent_set, handle_torrent_start,
+            handle_torrent_stop,
+        },
+        session::SESSION_ID_HEADER,
+        status::{self, DASHBOARD},
+    },
+    metrics::METRICS,
+    services::transmission::{TransmissionRequest, TransmissionResponse},
     AppData,
 };
 use actix_web::{
     get,
-    http::header::{ContentType, Header},
+    http::header::{ContentType, Header, WWW_AUTHENTICATE},
     post, web, HttpRequest, HttpResponse,
 };
 use actix_web_httpauth::headers::authorization::{Authorization, Basic};
 use anyhow::{bail, Context, Result};
-use log::error;
+use log::{error, warn};
 use serde_json::json;
 
-pub const SESSION_ID: &str = "useless-session-id";
-
 #[post("/transmission/rpc")]
 pub(crate) async fn rpc_post(
     payload: web::Json<TransmissionRequest>,
     req: HttpRequest,
     app_data: web::Data<AppData>,
 ) -> HttpResponse {
-    let putio_api_token = &app_data.config.putio.api_key;
+    let putio = &app_data.putio;
 
     // Not sure if necessary since we might just look at the session id.
-    if validate_user(req, &app_data).await.is_err() {
-        return HttpResponse::Conflict()
-            .content_type(ContentType::json())
-            .insert_header(("X-Transmission-Session-Id", SESSION_ID))
-            .body("");
+    if validate_user(&req, &app_data).await.is_err() {
+        return session_conflict(&app_data);
+    }
+
+    let session_id = req
+        .headers()
+        .get(SESSION_ID_HEADER)
+        .and_then(|v| v.to_str().ok());
+    if !session_id.is_some_and(|id| app_data.session.is_valid(id)) {
+        return session_conflict(&app_data);
     }
 
-    let arguments = match payload.method.as_str() {
-        "session-get" => Some(json!(TransmissionConfig {
-            download_dir: app_data.config.download_directory.clone(),
-            ..Default::default()
-        })),
-        "torrent-get" => handle_torrent_get(putio_api_token, &app_data).await,
-        "torrent-set" => None, // Nothing to do here
-        "queue-move-top" => None,
-        "torrent-remove" => handle_torrent_remove(putio_api_token, &payload).await,
-        "torrent-add" => match handle_torrent_add(putio_api_token, &payload).await {
-            Ok(v) => v,
+    let result = match payload.method.as_str() {
+        "session-get" => handle_session_get(&app_data),
+        "session-stats" => handle_session_stats(&app_data).await,
+        "torrent-get" => handle_torrent_get(putio, &payload, &app_data).await,
+        "torrent-remove" => handle_torrent_remove(&app_data, &payload).await,
+        "torrent-add" => match handle_torrent_add(&app_data, &payload).await {
+            Ok(v) => Ok(v),
             Err(e) => {
                 error!("{}", e);
                 return HttpResponse::BadRequest().body(e.to_string());
             }
         },
-        _ => panic!("Unknwon method {}", payload.method),
+        "torrent-start" | "torrent-start-now" => handle_torrent_start(&app_data, &payload).await,
+        "torrent-stop" => handle_torrent_stop(&app_data, &payload).await,
+        "torrent-set" => handle_torrent_set(&app_data, &payload).await,
+        "free-space" => handle_free_space(&payload, &app_data),
+        // put.io takes care of connectivity.
+        "port-test" => Ok(Some(json!({ "port-is-open": true }))),
+        "blocklist-update" => Ok(Some(json!({ "blocklist-size": 0 }))),
+        "group-get" => Ok(Some(json!({ "group": [] }))),
+        // Nothing to do here, put.io manages these itself.
+        "session-set"
+        | "session-close"
+        | "torrent-verify"
+        | "torrent-reannounce"
+        | "torrent-set-location"
+        | "torrent-rename-path"
+        | "queue-move-top"
+        | "queue-move-up"
+        | "queue-move-down"
+        | "queue-move-bottom"
+        | "group-set" => Ok(None),
+        method => {
+            warn!("Unknown method {}", method);
+            return transmission_response("method not recognized", None);
+        }
     };
 
+    match result {
+        Ok(arguments) => transmission_response("success", arguments),
+        Err(e) => {
+            error!("{}: {}", payload.method, e);
+            transmission_response(&e.to_string(), None)
+        }
+    }
+}
+
+fn transmission_response(result: &str, arguments: Option<serde_json::Value>) -> HttpResponse {
     let response = TransmissionResponse {
-        result: String::from("success"),
+        result: result.to_string(),
         arguments,
     };
 
@@ -63,18 +104,71 @@ pub(crate) async fn rpc_post(
 /// Pretty much only used for authentication.
 #[get("/transmission/rpc")]
 async fn rpc_get(req: HttpRequest, app_data: web::Data<AppData>) -> HttpResponse {
-    if validate_user(req, &app_data).await.is_err() {
+    if validate_user(&req, &app_data).await.is_err() {
         return HttpResponse::Forbidden().body("forbidden");
     }
 
+    session_conflict(&app_data)
+    // HttpResponse::Ok().body("Hello world!")
+}
+
+/// The dashboard.
+#[get("/")]
+pub(crate) async fn dashboard(req: HttpRequest, app_data: web::Data<AppData>) -> HttpResponse {
+    if validate_user(&req, &app_data).await.is_err() {
+        return unauthorized();
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(DASHBOARD)
+}
+
+/// Where every transfer is in the download pipeline, for the dashboard and scripts.
+#[get("/api/v1/status")]
+pub(crate) async fn api_status(req: HttpRequest, app_data: web::Data<AppData>) -> HttpResponse {
+    if validate_user(&req, &app_data).await.is_err() {
+        return unauthorized();
+    }
+
+    HttpResponse::Ok().json(status::status(&app_data).await)
+}
+
+/// Prometheus metrics.
+#[get("/metrics")]
+pub(crate) async fn metrics(req: HttpRequest, app_data: web::Data<AppData>) -> HttpResponse {
+    if validate_user(&req, &app_data).await.is_err() {
+        return unauthorized();
+    }
+
+    match METRICS.render(&app_data) {
+        Ok(metrics) => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .body(metrics),
+        Err(e) => {
+            error!("Unable to render metrics: {}", e);
+            HttpResponse::InternalServerError().body(e.to_string())
+        }
+    }
+}
+
+/// Asks browsers for the username and password.
+fn unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, r#"Basic realm="putioarr""#))
+        .body("unauthorized")
+}
+
+/// Tells the client which session id to use, like Transmission does.
+fn session_conflict(app_data: &web::Data<AppData>) -> HttpResponse {
     HttpResponse::Conflict()
         .content_type(ContentType::json())
-        .insert_header(("X-Transmission-Session-Id", SESSION_ID))
+        .insert_header((SESSION_ID_HEADER, app_data.session.current()))
         .body("")
-    // HttpResponse::Ok().body("Hello world!")
 }
-async fn validate_user(req: HttpRequest, app_data: &web::Data<AppData>) -> Result<()> {
-    let auth = Authorization::<Basic>::parse(&req)?;
+
+async fn validate_user(req: &HttpRequest, app_data: &web::Data<AppData>) -> Result<()> {
+    let auth = Authorization::<Basic>::parse(req)?;
     let user_username = auth.as_ref().user_id();
     let user_password = auth.as_ref().password().context("No password given")?;
     if user_username == app_data.config.username && user_password == app_data.config.password {
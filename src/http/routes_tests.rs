@@ -1,48 +1,40 @@
 #[cfg(test)]
 mod tests {
-    use super::super::routes::*;
+    use super::super::{routes::*, session::*};
     use crate::{
-        services::transmission::{TransmissionRequest, TransmissionResponse},
-        AppData, ArrConfig, Config, PutioConfig,
+        services::{
+            putio,
+            transmission::{TransmissionRequest, TransmissionResponse},
+        },
+        test_support, AppData, ArrConfig, Config,
     };
     use actix_web::{
-        http::header::{HeaderValue, AUTHORIZATION},
+        http::{
+            header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+            StatusCode,
+        },
         test, web, App,
     };
     use base64::Engine;
 
     fn create_test_config() -> Config {
         Config {
-            bind_address: "127.0.0.1".to_string(),
-            download_directory: "/tmp/downloads".to_string(),
-            download_workers: 4,
-            loglevel: "info".to_string(),
-            orchestration_workers: 10,
-            password: "testpass".to_string(),
-            polling_interval: 10,
-            port: 9091,
-            skip_directories: vec!["sample".to_string(), "extras".to_string()],
-            uid: 1000,
-            username: "testuser".to_string(),
-            putio: PutioConfig {
-                api_key: "test_api_key".to_string(),
-            },
             sonarr: Some(ArrConfig {
                 url: "http://localhost:8989".to_string(),
                 api_key: "sonarr_key".to_string(),
+                category: None,
             }),
             radarr: Some(ArrConfig {
                 url: "http://localhost:7878".to_string(),
                 api_key: "radarr_key".to_string(),
+                category: None,
             }),
-            whisparr: None,
+            ..test_support::config(putio::API_URL, putio::UPLOAD_URL)
         }
     }
 
     fn create_test_app_data() -> web::Data<AppData> {
-        web::Data::new(AppData {
-            config: create_test_config(),
-        })
+        test_support::with_config(create_test_config())
     }
 
     fn create_basic_auth_header(username: &str, password: &str) -> HeaderValue {
@@ -101,6 +93,7 @@ mod tests {
     #[actix_web::test]
     async fn test_rpc_post_session_get() {
         let app_data = create_test_app_data();
+        let session_id = app_data.session.current();
         let app = test::init_service(App::new().app_data(app_data).service(rpc_post)).await;
 
         let request_body = TransmissionRequest {
@@ -114,6 +107,7 @@ mod tests {
                 AUTHORIZATION,
                 create_basic_auth_header("testuser", "testpass"),
             ))
+            .insert_header((SESSION_ID_HEADER, session_id))
             .set_json(&request_body)
             .to_request();
 
@@ -122,6 +116,107 @@ mod tests {
         assert!(resp.arguments.is_some());
     }
 
+    async fn call_rpc_post(
+        method: &str,
+        arguments: Option<serde_json::Value>,
+    ) -> TransmissionResponse {
+        let app_data = create_test_app_data();
+        let session_id = app_data.session.current();
+        let app = test::init_service(App::new().app_data(app_data).service(rpc_post)).await;
+
+        let request_body = TransmissionRequest {
+            method: method.to_string(),
+            arguments,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/transmission/rpc")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("testuser", "testpass"),
+            ))
+            .insert_header((SESSION_ID_HEADER, session_id))
+            .set_json(&request_body)
+            .to_request();
+
+        test::call_and_read_body_json(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_unknown_method() {
+        let resp = call_rpc_post("torrent-frobnicate", None).await;
+        assert_eq!(resp.result, "method not recognized");
+        assert!(resp.arguments.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_no_op_methods() {
+        for method in ["session-set", "torrent-verify", "queue-move-bottom"] {
+            let resp = call_rpc_post(method, Some(serde_json::json!({"ids": [1]}))).await;
+            assert_eq!(resp.result, "success");
+            assert!(resp.arguments.is_none());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_port_test() {
+        let resp = call_rpc_post("port-test", None).await;
+        assert_eq!(resp.result, "success");
+        assert_eq!(resp.arguments.unwrap()["port-is-open"], true);
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_free_space() {
+        let dir = std::env::temp_dir().to_string_lossy().to_string();
+        let resp = call_rpc_post("free-space", Some(serde_json::json!({ "path": dir }))).await;
+        assert_eq!(resp.result, "success");
+        let arguments = resp.arguments.unwrap();
+        assert_eq!(arguments["path"], dir);
+        assert!(arguments["size-bytes"].is_u64());
+        assert!(arguments["total_size"].as_u64().unwrap() > 0);
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_free_space_missing_path() {
+        let resp = call_rpc_post(
+            "free-space",
+            Some(serde_json::json!({ "path": "/does/not/exist" })),
+        )
+        .await;
+        assert_ne!(resp.result, "success");
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_torrent_add_with_bad_metainfo() {
+        let app_data = create_test_app_data();
+        let session_id = app_data.session.current();
+        let app = test::init_service(App::new().app_data(app_data).service(rpc_post)).await;
+
+        for arguments in [
+            None,
+            Some(serde_json::json!({ "metainfo": "not base64!" })),
+            Some(serde_json::json!({ "metainfo": 42 })),
+            Some(serde_json::json!({})),
+        ] {
+            let request_body = TransmissionRequest {
+                method: "torrent-add".to_string(),
+                arguments,
+            };
+            let req = test::TestRequest::post()
+                .uri("/transmission/rpc")
+                .insert_header((
+                    AUTHORIZATION,
+                    create_basic_auth_header("testuser", "testpass"),
+                ))
+                .insert_header((SESSION_ID_HEADER, session_id.clone()))
+                .set_json(&request_body)
+                .to_request();
+
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
     #[actix_web::test]
     async fn test_rpc_post_invalid_auth() {
         let app_data = create_test_app_data();
@@ -164,8 +259,85 @@ mod tests {
     }
 
     #[actix_web::test]
-    async fn test_session_id_constant() {
-        assert_eq!(SESSION_ID, "useless-session-id");
+    async fn test_rpc_get_returns_session_id() {
+        let app_data = create_test_app_data();
+        let session_id = app_data.session.current();
+        let app = test::init_service(App::new().app_data(app_data).service(rpc_get)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/transmission/rpc")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("testuser", "testpass"),
+            ))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        assert_eq!(
+            resp.headers()
+                .get(SESSION_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            session_id
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_without_session_id() {
+        let app_data = create_test_app_data();
+        let session_id = app_data.session.current();
+        let app = test::init_service(App::new().app_data(app_data).service(rpc_post)).await;
+
+        let request_body = TransmissionRequest {
+            method: "session-get".to_string(),
+            arguments: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/transmission/rpc")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("testuser", "testpass"),
+            ))
+            .set_json(&request_body)
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        assert_eq!(
+            resp.headers()
+                .get(SESSION_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            session_id
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rpc_post_with_wrong_session_id() {
+        let app_data = create_test_app_data();
+        let app = test::init_service(App::new().app_data(app_data).service(rpc_post)).await;
+
+        let request_body = TransmissionRequest {
+            method: "session-get".to_string(),
+            arguments: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/transmission/rpc")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("testuser", "testpass"),
+            ))
+            .insert_header((SESSION_ID_HEADER, "useless-session-id"))
+            .set_json(&request_body)
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
     }
 
     #[actix_web::test]
@@ -190,4 +362,68 @@ mod tests {
         assert_eq!(app_data.config.password, "testpass");
         assert_eq!(app_data.config.download_directory, "/tmp/downloads");
     }
+
+    #[actix_web::test]
+    async fn test_dashboard_requires_auth() {
+        let app_data = create_test_app_data();
+        let app = test::init_service(App::new().app_data(app_data).service(dashboard)).await;
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        assert_eq!(
+            resp.headers().get(WWW_AUTHENTICATE).unwrap(),
+            r#"Basic realm="putioarr""#
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("testuser", "testpass"),
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8_lossy(&body).contains("api/v1/status"));
+    }
+
+    #[actix_web::test]
+    async fn test_api_status_requires_auth() {
+        let app_data = create_test_app_data();
+        let app = test::init_service(App::new().app_data(app_data).service(api_status)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/status")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("wronguser", "wrongpass"),
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        let app_data = create_test_app_data();
+        let app = test::init_service(App::new().app_data(app_data).service(metrics)).await;
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/metrics")
+            .insert_header((
+                AUTHORIZATION,
+                create_basic_auth_header("testuser", "testpass"),
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8_lossy(&body).contains("putioarr_transfers{stage=\"imported\"} 0"));
+    }
 }
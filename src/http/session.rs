@@ -0,0 +1,60 @@
+use rand::{distr::Alphanumeric, Rng};
+use std::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+pub const SESSION_ID_HEADER: &str = "X-Transmission-Session-Id";
+
+/// How long a session id stays valid before a new one is handed out.
+const SESSION_ID_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// Transmission's CSRF protection: every POST has to carry the current session id, which clients
+/// learn from the 409 response to a request without (or with an outdated) id.
+pub struct SessionIds {
+    current: RwLock<(String, Instant)>,
+    lifetime: Duration,
+}
+
+impl SessionIds {
+    pub fn new(lifetime: Duration) -> Self {
+        Self {
+            current: RwLock::new((generate(), Instant::now())),
+            lifetime,
+        }
+    }
+
+    /// Returns the current session id, rotating it first if it has expired.
+    pub fn current(&self) -> String {
+        {
+            let current = self.current.read().unwrap();
+            if current.1.elapsed() < self.lifetime {
+                return current.0.clone();
+            }
+        }
+        let mut current = self.current.write().unwrap();
+        // Another request might have rotated it in the meantime.
+        if current.1.elapsed() >= self.lifetime {
+            *current = (generate(), Instant::now());
+        }
+        current.0.clone()
+    }
+
+    pub fn is_valid(&self, session_id: &str) -> bool {
+        self.current() == session_id
+    }
+}
+
+impl Default for SessionIds {
+    fn default() -> Self {
+        Self::new(SESSION_ID_LIFETIME)
+    }
+}
+
+fn generate() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use super::super::session::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_session_id_is_random() {
+        let a = SessionIds::default().current();
+        let b = SessionIds::default().current();
+        assert_eq!(a.len(), 48);
+        assert!(a.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_session_id_is_stable_within_lifetime() {
+        let session = SessionIds::default();
+        let id = session.current();
+        assert_eq!(session.current(), id);
+        assert!(session.is_valid(&id));
+        assert!(!session.is_valid("useless-session-id"));
+    }
+
+    #[test]
+    fn test_session_id_rotates_after_lifetime() {
+        let session = SessionIds::new(Duration::ZERO);
+        let id = session.current();
+        assert_ne!(session.current(), id);
+        assert!(!session.is_valid(&id));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    download_system::{
+        errors::RecentError,
+        ownership,
+        state::{TransferStage, TransferState},
+        transfer::TargetType,
+    },
+    services::putio::PutIOTransfer,
+    AppData, VERSION,
+};
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The embedded dashboard, which polls `/api/v1/status`.
+pub const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Where a transfer is, as shown on the dashboard.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// put.io is still downloading the transfer.
+    WaitingOnPutio,
+    Downloading,
+    /// Downloaded, waiting for the *arr to import it.
+    AwaitingImport,
+    Seeding,
+    /// Imported and done seeding, about to be cleaned up.
+    Done,
+    Failed,
+}
+
+impl Stage {
+    pub fn of(state: Option<&TransferState>, putio_transfer: Option<&PutIOTransfer>) -> Self {
+        let putio_status = putio_transfer.map(|t| t.status.as_str());
+        match state.map(|s| s.stage) {
+            Some(TransferStage::Failed) => Stage::Failed,
+            Some(TransferStage::QueuedForDownload) => Stage::Downloading,
+            Some(TransferStage::Downloaded) => Stage::AwaitingImport,
+            Some(TransferStage::Imported) if putio_status == Some("SEEDING") => Stage::Seeding,
+            Some(TransferStage::Imported) => Stage::Done,
+            None if putio_status == Some("ERROR") => Stage::Failed,
+            // About to be picked up.
+            None if putio_transfer.is_some_and(|t| t.is_downloadable()) => Stage::Downloading,
+            None => Stage::WaitingOnPutio,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetStatus {
+    pub path: String,
+    pub total: u64,
+    pub downloaded: u64,
+    pub bytes_per_sec: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferStatus {
+    pub id: u64,
+    pub name: String,
+    pub hash: Option<String>,
+    pub stage: Stage,
+    /// The status put.io reports, missing when put.io no longer knows the transfer.
+    pub putio_status: Option<String>,
+    /// put.io's own download progress in percent.
+    pub putio_percent_done: Option<u8>,
+    pub error: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub total: u64,
+    pub downloaded: u64,
+    pub bytes_per_sec: u64,
+    pub targets: Vec<TargetStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub version: &'static str,
+    /// Combined speed of all downloads.
+    pub bytes_per_sec: u64,
+    /// Set when put.io couldn't be reached, the transfers are then only the ones we know about.
+    pub putio_error: Option<String>,
+    pub transfers: Vec<TransferStatus>,
+    pub errors: Vec<RecentError>,
+}
+
+/// Combines the put.io transfers with where they are in our pipeline.
+pub async fn status(app_data: &web::Data<AppData>) -> Status {
+    let (putio_transfers, putio_error) = match app_data.putio.list_transfers().await {
+        Ok(response) => (
+            ownership::owned_transfers(app_data, response.transfers).await,
+            None,
+        ),
+        Err(e) => (vec![], Some(e.to_string())),
+    };
+
+    let mut transfers: BTreeMap<u64, (Option<TransferState>, Option<PutIOTransfer>)> =
+        BTreeMap::new();
+    for state in app_data.state.all() {
+        transfers.insert(state.transfer_id, (Some(state), None));
+    }
+    for putio_transfer in putio_transfers {
+        let id = putio_transfer.id;
+        transfers.entry(id).or_default().1 = Some(putio_transfer);
+    }
+
+    Status {
+        version: VERSION,
+        bytes_per_sec: app_data.progress.bytes_per_sec(),
+        putio_error,
+        transfers: transfers
+            .into_iter()
+            .map(|(id, (state, putio_transfer))| {
+                transfer_status(app_data, id, state, putio_transfer)
+            })
+            .collect(),
+        errors: app_data.errors.all(),
+    }
+}
+
+fn transfer_status(
+    app_data: &AppData,
+    id: u64,
+    state: Option<TransferState>,
+    putio_transfer: Option<PutIOTransfer>,
+) -> TransferStatus {
+    let stage = Stage::of(state.as_ref(), putio_transfer.as_ref());
+    let targets: Vec<TargetStatus> = state
+        .iter()
+        .flat_map(|s| s.targets.iter().flatten())
+        .filter(|t| t.target_type == TargetType::File)
+        .map(|t| {
+            let progress = app_data.progress.target(t);
+            TargetStatus {
+                path: t.to.clone(),
+                total: progress.as_ref().map_or(0, |p| p.total),
+                downloaded: progress.as_ref().map_or(0, |p| p.downloaded),
+                bytes_per_sec: progress
+                    .as_ref()
+                    .filter(|p| p.downloaded < p.total)
+                    .map_or(0, |p| p.bytes_per_sec()),
+            }
+        })
+        .collect();
+
+    let name = state
+        .as_ref()
+        .map(|s| s.name.clone())
+        .or_else(|| putio_transfer.as_ref().and_then(|t| t.name.clone()))
+        .unwrap_or_default();
+    let error = state.as_ref().and_then(|s| s.error.clone()).or_else(|| {
+        putio_transfer
+            .as_ref()
+            .and_then(|t| t.error_message.clone())
+    });
+
+    TransferStatus {
+        id,
+        name,
+        hash: state
+            .as_ref()
+            .and_then(|s| s.hash.clone())
+            .or_else(|| putio_transfer.as_ref().and_then(|t| t.hash.clone())),
+        stage,
+        putio_status: putio_transfer.as_ref().map(|t| t.status.clone()),
+        putio_percent_done: putio_transfer.as_ref().map(putio_percent_done),
+        error,
+        updated_at: state.as_ref().map(|s| s.updated_at),
+        total: targets.iter().map(|t| t.total).sum(),
+        downloaded: targets.iter().map(|t| t.downloaded).sum(),
+        bytes_per_sec: targets.iter().map(|t| t.bytes_per_sec).sum(),
+        targets,
+    }
+}
+
+fn putio_percent_done(t: &PutIOTransfer) -> u8 {
+    match t.size {
+        _ if t.finished_at.is_some() => 100,
+        Some(size) if size > 0 => (t.downloaded.unwrap_or(0) * 100 / size).clamp(0, 100) as u8,
+        _ => 0,
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod tests {
+    use super::super::status::*;
+    use crate::{
+        download_system::{
+            orchestration,
+            state::TransferStage,
+            transfer::{self, DownloadTarget, TargetType, Transfer},
+        },
+        test_support::{
+            self,
+            putio::{FakePutio, ROOT},
+        },
+        AppData,
+    };
+    use actix_web::web;
+    use std::time::Duration;
+
+    fn create_transfer(app_data: &web::Data<AppData>, transfer_id: u64, name: &str) -> Transfer {
+        let hash = format!("{:04}abcd", transfer_id);
+        Transfer {
+            name: name.to_string(),
+            file_id: Some(456),
+            hash: Some(hash.clone()),
+            transfer_id,
+            targets: Some(vec![
+                DownloadTarget {
+                    from: None,
+                    to: format!("/tmp/downloads/{}", name),
+                    target_type: TargetType::Directory,
+                    top_level: true,
+                    transfer_hash: hash.clone(),
+                },
+                DownloadTarget {
+                    from: Some("https://example.com/file".to_string()),
+                    to: format!("/tmp/downloads/{}/file.mkv", name),
+                    target_type: TargetType::File,
+                    top_level: false,
+                    transfer_hash: hash,
+                },
+            ]),
+            app_data: app_data.clone(),
+        }
+    }
+
+    fn stage_of(status: &Status, id: u64) -> Stage {
+        status.transfers.iter().find(|t| t.id == id).unwrap().stage
+    }
+
+    #[actix_web::test]
+    async fn test_status_stages() {
+        let putio = FakePutio::start().await;
+        let app_data = test_support::app_data(&putio.url(), &putio.upload_url());
+        let file = putio.add_file(ROOT, "movie.mkv", "VIDEO", b"movie");
+
+        let waiting = putio.add_transfer("Waiting", "1111", "DOWNLOADING");
+        let downloading = putio.add_transfer("Downloading", "2222", "SEEDING");
+        putio.set_status(downloading, "SEEDING", Some(file));
+        let awaiting_import = putio.add_transfer("Awaiting", "3333", "COMPLETED");
+        putio.set_status(awaiting_import, "COMPLETED", Some(file));
+        let seeding = putio.add_transfer("Seeding", "4444", "SEEDING");
+        putio.set_status(seeding, "SEEDING", Some(file));
+        let done = putio.add_transfer("Done", "5555", "COMPLETED");
+        putio.set_status(done, "COMPLETED", Some(file));
+        let failed_on_putio = putio.add_transfer("Broken", "6666", "ERROR");
+
+        // Queued records have no targets until an orchestration worker picks them up.
+        let transfer = Transfer {
+            targets: None,
+            ..create_transfer(&app_data, downloading, "Downloading")
+        };
+        app_data
+            .state
+            .update(&transfer, TransferStage::QueuedForDownload);
+        for (id, stage) in [
+            (awaiting_import, TransferStage::Downloaded),
+            (seeding, TransferStage::Imported),
+            (done, TransferStage::Imported),
+        ] {
+            app_data
+                .state
+                .update(&create_transfer(&app_data, id, "Other"), stage);
+        }
+        // Known only to us, put.io no longer lists it.
+        app_data
+            .state
+            .fail(&create_transfer(&app_data, 999, "Failed"), "disk full");
+        app_data.errors.record("Failed", "disk full");
+
+        let status = status(&app_data).await;
+        assert!(status.putio_error.is_none());
+        assert_eq!(status.transfers.len(), 7);
+        assert_eq!(stage_of(&status, waiting), Stage::WaitingOnPutio);
+        assert_eq!(stage_of(&status, downloading), Stage::Downloading);
+        assert_eq!(stage_of(&status, awaiting_import), Stage::AwaitingImport);
+        assert_eq!(stage_of(&status, seeding), Stage::Seeding);
+        assert_eq!(stage_of(&status, done), Stage::Done);
+        assert_eq!(stage_of(&status, failed_on_putio), Stage::Failed);
+        assert_eq!(stage_of(&status, 999), Stage::Failed);
+
+        let downloading = status
+            .transfers
+            .iter()
+            .find(|t| t.id == downloading)
+            .unwrap();
+        assert_eq!(downloading.name, "Downloading");
+        assert_eq!(downloading.putio_status, Some("SEEDING".to_string()));
+
+        let failed = status.transfers.iter().find(|t| t.id == 999).unwrap();
+        assert_eq!(failed.error, Some("disk full".to_string()));
+        assert!(failed.putio_status.is_none());
+        assert_eq!(status.errors.len(), 1);
+        assert_eq!(status.errors[0].subject, "Failed");
+    }
+
+    #[actix_web::test]
+    async fn test_status_of_download_in_progress() {
+        let putio = FakePutio::start().await;
+        let app_data = test_support::app_data(&putio.url(), &putio.upload_url());
+        let folder = putio.add_file(ROOT, "Movie", "FOLDER", b"");
+        putio.add_file(folder, "movie.mkv", "VIDEO", b"movie");
+        let id = putio.add_transfer("Movie", "1111", "SEEDING");
+        putio.set_status(id, "SEEDING", Some(folder));
+
+        let (tx, rx) = async_channel::unbounded();
+        let (dtx, drx) = async_channel::unbounded();
+        orchestration::Worker::start(0, app_data.clone(), tx.clone(), rx, dtx);
+        let data = app_data.clone();
+        actix_rt::spawn(async move { transfer::produce_transfers(data, tx).await });
+
+        // Stand in for a download worker that's part way through the file.
+        let file = loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), drx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if msg.download_target.target_type == TargetType::File {
+                break msg.download_target;
+            }
+        };
+        app_data.progress.start(&file, 100, 40);
+
+        let status = status(&app_data).await;
+        app_data.shutdown.cancel();
+        let movie = status.transfers.iter().find(|t| t.id == id).unwrap();
+        assert_eq!(movie.stage, Stage::Downloading);
+        assert_eq!(movie.total, 100);
+        assert_eq!(movie.downloaded, 40);
+        assert_eq!(movie.targets.len(), 1);
+        assert_eq!(movie.targets[0].path, "/tmp/downloads/Movie/movie.mkv");
+        assert_eq!(movie.targets[0].downloaded, 40);
+    }
+
+    #[actix_web::test]
+    async fn test_status_without_putio() {
+        let app_data = test_support::app_data("http://127.0.0.1:1", "http://127.0.0.1:1");
+        let transfer = create_transfer(&app_data, 1, "Movie");
+        app_data.state.update(&transfer, TransferStage::Downloaded);
+
+        let status = status(&app_data).await;
+        assert!(status.putio_error.is_some());
+        assert_eq!(status.transfers.len(), 1);
+        assert_eq!(status.transfers[0].stage, Stage::AwaitingImport);
+        assert!(status.transfers[0].putio_status.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_status_serialization() {
+        let app_data = test_support::app_data("http://127.0.0.1:1", "http://127.0.0.1:1");
+        let transfer = create_transfer(&app_data, 1, "Movie");
+        app_data.state.update(&transfer, TransferStage::Imported);
+
+        let json = serde_json::to_value(status(&app_data).await).unwrap();
+        assert_eq!(json["transfers"][0]["stage"], "done");
+        assert_eq!(json["transfers"][0]["name"], "Movie");
+        assert!(json["errors"].as_array().unwrap().is_empty());
+        assert!(json["version"].is_string());
+    }
+}
@@ -1,28 +1,58 @@
-use crate::{http::routes, services::putio};
+use crate::{
+    download_system::{
+        cleanup::{LocalCleanup, RemoteCleanup},
+        errors::RecentErrors,
+        imports::ImportCache,
+        ownership::FolderCache,
+        progress::ProgressTracker,
+        state::StateStore,
+        torrents::TorrentStore,
+    },
+    http::{routes, session::SessionIds},
+    notify::{EventKind, NotifierKind},
+    services::{
+        arr::{ArrKind, ArrService},
+        putio::{self, PutioClient},
+    },
+};
 use actix_web::{web, App, HttpServer};
 use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
 use directories::ProjectDirs;
 use env_logger::TimestampPrecision;
 use figment::{
-    providers::{Format, Serialized, Toml},
+    providers::{Env, Format, Serialized, Toml},
     Figment,
 };
 use log::{error, info};
 use serde::{Deserialize, Serialize};
+use std::{path::Path, time::Duration};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
 use utils::{generate_config, get_token};
 
 mod download_system;
 mod http;
+mod metrics;
+mod notify;
+mod retry;
 mod services;
 mod utils;
 
 #[cfg(test)]
 mod config_tests;
 #[cfg(test)]
+mod metrics_tests;
+#[cfg(test)]
+mod notify_tests;
+#[cfg(test)]
+mod retry_tests;
+#[cfg(test)]
+mod test_support;
+#[cfg(test)]
 mod utils_tests;
 
-/// put.io to sonarr/radarr proxy
+/// put.io to sonarr/radarr/whisparr/lidarr/readarr proxy
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -50,58 +80,437 @@ struct RunArgs {
 pub struct Config {
     bind_address: String,
     download_directory: String,
+    download_segments: usize,
     download_workers: usize,
     loglevel: String,
+    max_download_attempts: u32,
+    max_retry_delay: u64,
+    min_segment_size_mb: u64,
+    /// Only download and clean up the transfers added through putioarr, leave the rest alone.
+    only_owned_transfers: bool,
     orchestration_workers: usize,
     password: String,
     polling_interval: u64,
     port: u16,
+    retry_delay: u64,
+    /// Seconds to wait for downloads in progress when shutting down.
+    shutdown_grace_period: u64,
     skip_directories: Vec<String>,
     uid: u32,
     username: String,
+    cleanup: CleanupConfig,
+    #[serde(default)]
+    extract: ExtractConfig,
+    #[serde(default)]
+    file_filter: FileFilterConfig,
+    #[serde(default)]
+    notifications: NotificationsConfig,
+    #[serde(default)]
+    post_download: PostDownloadConfig,
     putio: PutioConfig,
+    #[serde(default)]
+    seeding: SeedingConfig,
     sonarr: Option<ArrConfig>,
     radarr: Option<ArrConfig>,
     whisparr: Option<ArrConfig>,
+    lidarr: Option<ArrConfig>,
+    readarr: Option<ArrConfig>,
+    #[serde(default)]
+    arr: Vec<ArrInstanceConfig>,
+}
+
+impl Config {
+    /// All configured *arr services, the `[sonarr]` style tables first, then the `[[arr]]` ones.
+    pub fn arr_services(&self) -> Vec<ArrService> {
+        let instances = self.arr.iter().map(|a| ArrService {
+            name: a.name.clone(),
+            kind: a.kind,
+            url: a.url.clone(),
+            api_key: a.api_key.clone(),
+            category: a.category.clone(),
+        });
+        [
+            (ArrKind::Sonarr, &self.sonarr),
+            (ArrKind::Radarr, &self.radarr),
+            (ArrKind::Whisparr, &self.whisparr),
+            (ArrKind::Lidarr, &self.lidarr),
+            (ArrKind::Readarr, &self.readarr),
+        ]
+        .into_iter()
+        .filter_map(|(kind, arr)| {
+            arr.as_ref().map(|a| ArrService {
+                name: kind.to_string(),
+                kind,
+                url: a.url.clone(),
+                api_key: a.api_key.clone(),
+                category: a.category.clone(),
+            })
+        })
+        .chain(instances)
+        .collect()
+    }
+
+    /// Catches settings that only fail once a transfer gets that far.
+    pub fn validate(&self) -> Result<()> {
+        if self.cleanup.local == LocalCleanup::Move && self.cleanup.completed_directory.is_none() {
+            bail!("cleanup.completed_directory is required when cleanup.local is \"move\"");
+        }
+        for notifier in &self.notifications.notifiers {
+            match notifier.kind {
+                NotifierKind::Exec if notifier.command.is_none() => {
+                    bail!("notifications.notifiers: command is required for kind \"exec\"")
+                }
+                NotifierKind::Webhook | NotifierKind::Discord | NotifierKind::Slack
+                    if notifier.url.is_none() =>
+                {
+                    bail!(
+                        "notifications.notifiers: url is required for kind \"{:?}\"",
+                        notifier.kind
+                    )
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the files of a torrent in `category` are downloaded to.
+    pub fn category_directory(&self, category: Option<&str>) -> String {
+        match category {
+            Some(category) => Path::new(&self.download_directory)
+                .join(category)
+                .to_string_lossy()
+                .into_owned(),
+            None => self.download_directory.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PutioConfig {
     api_key: String,
+    /// Request timeout in seconds.
+    #[serde(default = "default_putio_timeout")]
+    timeout: u64,
+    /// How often a request is retried after a rate limit, server error or timeout.
+    #[serde(default = "default_putio_max_retries")]
+    max_retries: u32,
+    /// Base URL of the put.io API.
+    #[serde(default = "default_putio_api_url")]
+    api_url: String,
+    /// Base URL of the put.io upload API.
+    #[serde(default = "default_putio_upload_url")]
+    upload_url: String,
+    /// put.io folder the transfers added through putioarr are saved in, instead of the root folder.
+    #[serde(default)]
+    folder: Option<String>,
+}
+
+fn default_putio_timeout() -> u64 {
+    10
+}
+
+fn default_putio_max_retries() -> u32 {
+    3
+}
+
+fn default_putio_api_url() -> String {
+    putio::API_URL.to_string()
+}
+
+fn default_putio_upload_url() -> String {
+    putio::UPLOAD_URL.to_string()
+}
+
+/// The defaults of the settings that can be left out of the config file.
+fn config_defaults() -> Figment {
+    Figment::new()
+        .join(Serialized::default("bind_address", "0.0.0.0"))
+        .join(Serialized::default("download_segments", 1))
+        .join(Serialized::default("download_workers", 4))
+        .join(Serialized::default("orchestration_workers", 10))
+        .join(Serialized::default("loglevel", "info"))
+        .join(Serialized::default("max_download_attempts", 5))
+        .join(Serialized::default("max_retry_delay", 600))
+        .join(Serialized::default("min_segment_size_mb", 64))
+        .join(Serialized::default("only_owned_transfers", false))
+        .join(Serialized::default("polling_interval", 10))
+        .join(Serialized::default("port", 9091))
+        .join(Serialized::default("retry_delay", 10))
+        .join(Serialized::default("shutdown_grace_period", 30))
+        .join(Serialized::default("uid", 1000))
+        .join(Serialized::default(
+            "skip_directories",
+            vec!["sample", "extras"],
+        ))
+        .join(Serialized::default("cleanup", CleanupConfig::default()))
+}
+
+/// `PUTIO_API_URL` and `PUTIO_UPLOAD_URL` override the put.io base URLs from the config file, which
+/// comes in handy to point putioarr at a local put.io stand-in.
+fn putio_env() -> Env {
+    Env::prefixed("PUTIO_")
+        .only(&["api_url", "upload_url"])
+        .map(|key| format!("putio.{}", key).into())
+}
+
+/// Which files of a transfer get downloaded. A file is downloaded when it matches any of the
+/// `include_*` rules and none of the `exclude_*` rules. Matching is case-insensitive, content types
+/// and globs support `*` and `?` wildcards, globs are matched against the file name.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FileFilterConfig {
+    /// put.io file types, e.g. VIDEO or AUDIO.
+    #[serde(default = "default_include_file_types")]
+    include_file_types: Vec<String>,
+    /// File extensions, without the dot.
+    #[serde(default = "default_include_extensions")]
+    include_extensions: Vec<String>,
+    #[serde(default)]
+    include_content_types: Vec<String>,
+    #[serde(default)]
+    include_globs: Vec<String>,
+    #[serde(default)]
+    exclude_file_types: Vec<String>,
+    #[serde(default)]
+    exclude_extensions: Vec<String>,
+    #[serde(default)]
+    exclude_content_types: Vec<String>,
+    #[serde(default)]
+    exclude_globs: Vec<String>,
+}
+
+impl Default for FileFilterConfig {
+    fn default() -> Self {
+        Self {
+            include_file_types: default_include_file_types(),
+            include_extensions: default_include_extensions(),
+            include_content_types: vec![],
+            include_globs: vec![],
+            exclude_file_types: vec![],
+            exclude_extensions: vec![],
+            exclude_content_types: vec![],
+            exclude_globs: vec![],
+        }
+    }
+}
+
+fn default_include_file_types() -> Vec<String> {
+    vec!["VIDEO".to_string()]
+}
+
+fn default_include_extensions() -> Vec<String> {
+    ["srt", "ass", "ssa", "sub", "idx", "vtt", "sup", "nfo"]
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+/// What happens to the files of a transfer once it has been imported and put.io is done seeding.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CleanupConfig {
+    /// What to do with the downloaded files after the import.
+    #[serde(default)]
+    local: LocalCleanup,
+    /// Where `local = "move"` moves the downloaded files to.
+    #[serde(default)]
+    completed_directory: Option<String>,
+    /// How long `local = "delete_after"` keeps the downloaded files after the import.
+    #[serde(default = "default_delete_after_hours")]
+    delete_after_hours: u64,
+    /// What to do with the put.io files once seeding stops.
+    #[serde(default)]
+    remote: RemoteCleanup,
+    /// Only log what would be removed.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            local: LocalCleanup::default(),
+            completed_directory: None,
+            delete_after_hours: default_delete_after_hours(),
+            remote: RemoteCleanup::default(),
+            dry_run: false,
+        }
+    }
+}
+
+fn default_delete_after_hours() -> u64 {
+    24
+}
+
+/// Who gets told about what happens to transfers.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotificationsConfig {
+    /// Minutes after the download to notify about a transfer that still hasn't been imported. 0
+    /// disables it.
+    #[serde(default = "default_import_timeout")]
+    import_timeout: u64,
+    #[serde(default)]
+    notifiers: Vec<NotifierConfig>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            import_timeout: default_import_timeout(),
+            notifiers: vec![],
+        }
+    }
+}
+
+/// A command that is run once a transfer has been downloaded, before it's handed to the *arr.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PostDownloadConfig {
+    #[serde(default)]
+    command: Option<String>,
+    /// Seconds the command may take.
+    #[serde(default = "default_post_download_timeout")]
+    timeout: u64,
+}
+
+impl Default for PostDownloadConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            timeout: default_post_download_timeout(),
+        }
+    }
+}
+
+fn default_post_download_timeout() -> u64 {
+    3600
+}
+
+/// Extracting RAR and ZIP archives once they have been downloaded, so the *arr gets to see what's
+/// inside.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExtractConfig {
+    #[serde(default)]
+    enabled: bool,
+    /// The unrar binary RAR archives are extracted with.
+    #[serde(default = "default_unrar")]
+    unrar: String,
+    /// Seconds extracting a RAR archive may take.
+    #[serde(default = "default_post_download_timeout")]
+    timeout: u64,
+    /// Delete the archives once the extracted files have been imported.
+    #[serde(default = "default_delete_archives")]
+    delete_archives: bool,
+}
+
+impl Default for ExtractConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            unrar: default_unrar(),
+            timeout: default_post_download_timeout(),
+            delete_archives: true,
+        }
+    }
+}
+
+fn default_unrar() -> String {
+    "unrar".to_string()
+}
+
+fn default_delete_archives() -> bool {
+    true
+}
+
+fn default_import_timeout() -> u64 {
+    24 * 60
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotifierConfig {
+    kind: NotifierKind,
+    /// Where the webhook, Discord and Slack notifiers post to.
+    #[serde(default)]
+    url: Option<String>,
+    /// The command the exec notifier runs.
+    #[serde(default)]
+    command: Option<String>,
+    /// The events to notify about, all of them when empty.
+    #[serde(default)]
+    events: Vec<EventKind>,
+}
+
+/// When to stop seeding, unless the *arr set limits of its own for a torrent. put.io keeps seeding
+/// on its own schedule when neither is set.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SeedingConfig {
+    /// Stop seeding once this ratio is reached.
+    #[serde(default)]
+    ratio_limit: Option<f64>,
+    /// Stop seeding after this many minutes.
+    #[serde(default)]
+    time_limit: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ArrConfig {
     url: String,
     api_key: String,
+    /// The category (Transmission label) this instance adds its torrents with.
+    #[serde(default)]
+    category: Option<String>,
+}
+
+/// An `[[arr]]` entry, which allows for more than one instance of a kind.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ArrInstanceConfig {
+    name: String,
+    kind: ArrKind,
+    url: String,
+    api_key: String,
+    #[serde(default)]
+    category: Option<String>,
 }
 
 pub struct AppData {
     pub config: Config,
+    pub state: StateStore,
+    pub torrents: TorrentStore,
+    pub progress: ProgressTracker,
+    pub imports: ImportCache,
+    pub folders: FolderCache,
+    /// Recent errors, shown by the status API.
+    pub errors: RecentErrors,
+    pub session: SessionIds,
+    /// Cancelled when shutting down, everything that runs in the background stops on it.
+    pub shutdown: CancellationToken,
+    pub putio: PutioClient,
 }
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Waits for SIGTERM, e.g. from `docker stop`, or SIGINT.
+async fn shutdown_signal() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => info!("Received SIGTERM"),
+        result = tokio::signal::ctrl_c() => {
+            result?;
+            info!("Received SIGINT");
+        }
+    }
+    Ok(())
+}
+
 #[actix_web::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
         Commands::Run(args) => {
-            let config: Config = Figment::new()
-                .join(Serialized::default("bind_address", "0.0.0.0"))
-                .join(Serialized::default("download_workers", 4))
-                .join(Serialized::default("orchestration_workers", 10))
-                .join(Serialized::default("loglevel", "info"))
-                .join(Serialized::default("polling_interval", 10))
-                .join(Serialized::default("port", 9091))
-                .join(Serialized::default("uid", 1000))
-                .join(Serialized::default(
-                    "skip_directories",
-                    vec!["sample", "extras"],
-                ))
+            let config: Config = config_defaults()
                 .merge(Toml::file(&args.config_path))
+                .merge(putio_env())
                 .extract()?;
+            config.validate()?;
 
             let log_timestamp = if in_container::in_container() {
                 Some(TimestampPrecision::Seconds)
@@ -125,11 +534,28 @@ async fn main() -> Result<()> {
 
             info!("Starting putioarr, version {}", VERSION);
 
+            // Pipeline state lives next to the config file.
+            let state_path = Path::new(&args.config_path).with_file_name("state.json");
+            let state = StateStore::load(&state_path)
+                .with_context(|| format!("Unable to load state {}", state_path.display()))?;
+            let torrents_path = Path::new(&args.config_path).with_file_name("torrents.json");
+            let torrents = TorrentStore::load(&torrents_path)
+                .with_context(|| format!("Unable to load torrents {}", torrents_path.display()))?;
+
             let app_data = web::Data::new(AppData {
                 config: config.clone(),
+                state,
+                torrents,
+                progress: ProgressTracker::default(),
+                imports: ImportCache::default(),
+                folders: FolderCache::default(),
+                errors: RecentErrors::default(),
+                session: SessionIds::default(),
+                shutdown: CancellationToken::new(),
+                putio: PutioClient::new(&config.putio),
             });
 
-            match putio::account_info(&app_data.config.putio.api_key).await {
+            match app_data.putio.account_info().await {
                 Ok(_) => {}
                 Err(e) => {
                     error!("{}", e);
@@ -138,27 +564,48 @@ async fn main() -> Result<()> {
             }
 
             let data_for_download_system = app_data.clone();
-            download_system::start(data_for_download_system)
-                .await
-                .unwrap();
+            let download_system = download_system::start(data_for_download_system).await?;
 
             info!(
                 "Starting web server at http://{}:{}",
                 config.bind_address, config.port
             );
-            HttpServer::new(move || {
+            let data_for_server = app_data.clone();
+            let server = HttpServer::new(move || {
                 App::new()
                     // .wrap(Logger::new(
                     //     "%a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T",
                     // ))
-                    .app_data(app_data.clone())
+                    .app_data(data_for_server.clone())
                     .service(routes::rpc_post)
                     .service(routes::rpc_get)
+                    .service(routes::api_status)
+                    .service(routes::dashboard)
+                    .service(routes::metrics)
             })
+            // We handle the signals ourselves, so the downloads get to finish.
+            .disable_signals()
             .bind((config.bind_address, config.port))?
-            .run()
-            .await
-            .context("Unable to start http server")
+            .run();
+            let server_handle = server.handle();
+            let mut server_task = actix_rt::spawn(server);
+
+            tokio::select! {
+                result = &mut server_task => {
+                    return result?.context("Unable to start http server");
+                }
+                result = shutdown_signal() => result?,
+            }
+
+            info!("Shutting down");
+            app_data.shutdown.cancel();
+            server_handle.stop(true).await;
+            download_system
+                .drain(Duration::from_secs(config.shutdown_grace_period))
+                .await;
+            app_data.state.persist();
+            info!("Stopped");
+            Ok(())
         }
         Commands::GetToken => {
             get_token().await?;
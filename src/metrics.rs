@@ -0,0 +1,199 @@
+use crate::{download_system::state::TransferStage, AppData};
+use anyhow::Result;
+use prometheus::{
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, register_int_gauge_vec_with_registry,
+    register_int_gauge_with_registry, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Registry, TextEncoder,
+};
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+
+/// The metrics exposed on `/metrics`. Global, so the put.io and *arr clients can update them
+/// without having to know about `AppData`.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Reports how many messages are waiting in a queue.
+type QueueDepth = Box<dyn Fn() -> usize + Send + Sync>;
+
+pub struct Metrics {
+    registry: Registry,
+    transfers: IntGaugeVec,
+    queue_depth: IntGaugeVec,
+    download_speed: IntGauge,
+    pub downloaded_bytes: IntCounterVec,
+    pub download_retries: IntCounter,
+    pub download_failures: IntCounter,
+    pub transfer_failures: IntCounter,
+    pub putio_requests: IntCounterVec,
+    pub putio_request_duration: HistogramVec,
+    pub putio_errors: IntCounterVec,
+    pub putio_retries: IntCounterVec,
+    pub arr_history_duration: HistogramVec,
+    pub arr_history_errors: IntCounterVec,
+    queues: Mutex<HashMap<&'static str, QueueDepth>>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("putioarr".to_string()), None).unwrap();
+        Self {
+            transfers: register_int_gauge_vec_with_registry!(
+                "transfers",
+                "Transfers by pipeline stage",
+                &["stage"],
+                registry
+            )
+            .unwrap(),
+            queue_depth: register_int_gauge_vec_with_registry!(
+                "queue_depth",
+                "Messages waiting in the orchestration and download queues",
+                &["queue"],
+                registry
+            )
+            .unwrap(),
+            download_speed: register_int_gauge_with_registry!(
+                "download_speed_bytes",
+                "Combined speed of all downloads in bytes per second",
+                registry
+            )
+            .unwrap(),
+            downloaded_bytes: register_int_counter_vec_with_registry!(
+                "downloaded_bytes_total",
+                "Bytes downloaded by each download worker",
+                &["worker"],
+                registry
+            )
+            .unwrap(),
+            download_retries: register_int_counter_with_registry!(
+                "download_retries_total",
+                "Downloads of transfers that were retried",
+                registry
+            )
+            .unwrap(),
+            download_failures: register_int_counter_with_registry!(
+                "download_failures_total",
+                "Failed file downloads",
+                registry
+            )
+            .unwrap(),
+            transfer_failures: register_int_counter_with_registry!(
+                "transfer_failures_total",
+                "Transfers that failed after retrying",
+                registry
+            )
+            .unwrap(),
+            putio_requests: register_int_counter_vec_with_registry!(
+                "putio_requests_total",
+                "put.io API requests by endpoint and response status",
+                &["endpoint", "status"],
+                registry
+            )
+            .unwrap(),
+            putio_request_duration: register_histogram_vec_with_registry!(
+                "putio_request_duration_seconds",
+                "put.io API request latency",
+                &["endpoint"],
+                registry
+            )
+            .unwrap(),
+            putio_errors: register_int_counter_vec_with_registry!(
+                "putio_errors_total",
+                "put.io API requests that failed or got an error response",
+                &["endpoint"],
+                registry
+            )
+            .unwrap(),
+            putio_retries: register_int_counter_vec_with_registry!(
+                "putio_retries_total",
+                "put.io API requests that were retried",
+                &["endpoint"],
+                registry
+            )
+            .unwrap(),
+            arr_history_duration: register_histogram_vec_with_registry!(
+                "arr_history_duration_seconds",
+                "*arr history query latency",
+                &["service"],
+                registry
+            )
+            .unwrap(),
+            arr_history_errors: register_int_counter_vec_with_registry!(
+                "arr_history_errors_total",
+                "Failed *arr history queries",
+                &["service"],
+                registry
+            )
+            .unwrap(),
+            registry,
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reports the depth of queue `name` when scraped, replacing the previous one of that name.
+    pub fn watch_queue<T: Send + 'static>(
+        &self,
+        name: &'static str,
+        sender: &async_channel::Sender<T>,
+    ) {
+        let sender = sender.downgrade();
+        self.queues.lock().unwrap().insert(
+            name,
+            Box::new(move || sender.upgrade().map_or(0, |s| s.len())),
+        );
+    }
+
+    /// Records a put.io API request to `endpoint` that started at `start`. `status` is None when
+    /// no response was received.
+    pub fn putio_request(&self, endpoint: &str, status: Option<u16>, start: Instant) {
+        let status_label = status.map_or("error".to_string(), |s| s.to_string());
+        self.putio_requests
+            .with_label_values(&[endpoint, &status_label])
+            .inc();
+        self.putio_request_duration
+            .with_label_values(&[endpoint])
+            .observe(start.elapsed().as_secs_f64());
+        if status.is_none_or(|s| s >= 400) {
+            self.putio_errors.with_label_values(&[endpoint]).inc();
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text format, updating the ones that are sampled.
+    pub fn render(&self, app_data: &AppData) -> Result<String> {
+        let mut stages: HashMap<&str, i64> = HashMap::from([
+            (stage_label(TransferStage::QueuedForDownload), 0),
+            (stage_label(TransferStage::Downloaded), 0),
+            (stage_label(TransferStage::Imported), 0),
+            (stage_label(TransferStage::Failed), 0),
+        ]);
+        for state in app_data.state.all() {
+            *stages.entry(stage_label(state.stage)).or_default() += 1;
+        }
+        for (stage, count) in stages {
+            self.transfers.with_label_values(&[stage]).set(count);
+        }
+        for (name, depth) in self.queues.lock().unwrap().iter() {
+            self.queue_depth
+                .with_label_values(&[name])
+                .set(depth() as i64);
+        }
+        self.download_speed
+            .set(app_data.progress.bytes_per_sec() as i64);
+
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+fn stage_label(stage: TransferStage) -> &'static str {
+    match stage {
+        TransferStage::QueuedForDownload => "queued_for_download",
+        TransferStage::Downloaded => "downloaded",
+        TransferStage::Imported => "imported",
+        TransferStage::Failed => "failed",
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        download_system::{state::TransferStage, transfer::Transfer},
+        metrics::METRICS,
+        test_support::{self, putio::FakePutio},
+        AppData,
+    };
+    use actix_web::web;
+    use std::time::Instant;
+
+    fn create_transfer(app_data: &web::Data<AppData>, transfer_id: u64) -> Transfer {
+        Transfer {
+            name: format!("Transfer {}", transfer_id),
+            file_id: None,
+            hash: None,
+            transfer_id,
+            targets: None,
+            app_data: app_data.clone(),
+        }
+    }
+
+    /// The value of the sample starting with `prefix`, e.g. `putioarr_transfers{stage="failed"}`.
+    fn sample(metrics: &str, prefix: &str) -> Option<f64> {
+        metrics
+            .lines()
+            .find(|l| l.starts_with(prefix))
+            .and_then(|l| l.rsplit(' ').next())
+            .and_then(|v| v.parse().ok())
+    }
+
+    #[test]
+    fn test_putio_request_counts_errors() {
+        let start = Instant::now();
+        METRICS.putio_request("test/ok", Some(200), start);
+        METRICS.putio_request("test/error", Some(500), start);
+        METRICS.putio_request("test/error", None, start);
+
+        let ok = &["test/ok", "200"];
+        assert_eq!(METRICS.putio_requests.with_label_values(ok).get(), 1);
+        assert_eq!(
+            METRICS.putio_errors.with_label_values(&["test/ok"]).get(),
+            0
+        );
+        assert_eq!(
+            METRICS
+                .putio_errors
+                .with_label_values(&["test/error"])
+                .get(),
+            2
+        );
+        let error = &["test/error", "error"];
+        assert_eq!(METRICS.putio_requests.with_label_values(error).get(), 1);
+        assert_eq!(
+            METRICS
+                .putio_request_duration
+                .with_label_values(&["test/error"])
+                .get_sample_count(),
+            2
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_putio_client_is_measured() {
+        let putio = FakePutio::start().await;
+        let app_data = test_support::app_data(&putio.url(), &putio.upload_url());
+        let before = METRICS
+            .putio_requests
+            .with_label_values(&["transfers/list", "200"])
+            .get();
+
+        app_data.putio.list_transfers().await.unwrap();
+
+        let after = METRICS
+            .putio_requests
+            .with_label_values(&["transfers/list", "200"])
+            .get();
+        assert!(after > before);
+    }
+
+    #[test]
+    fn test_render() {
+        let app_data = test_support::app_data("http://127.0.0.1:1", "http://127.0.0.1:1");
+        app_data
+            .state
+            .update(&create_transfer(&app_data, 1), TransferStage::Downloaded);
+        app_data
+            .state
+            .update(&create_transfer(&app_data, 2), TransferStage::Downloaded);
+        app_data
+            .state
+            .fail(&create_transfer(&app_data, 3), "disk full");
+        let (sender, _receiver) = async_channel::unbounded();
+        METRICS.watch_queue("test", &sender);
+        sender.try_send(1).unwrap();
+        sender.try_send(2).unwrap();
+
+        let metrics = METRICS.render(&app_data).unwrap();
+        let downloaded = r#"putioarr_transfers{stage="downloaded"}"#;
+        assert_eq!(sample(&metrics, downloaded), Some(2.0));
+        let failed = r#"putioarr_transfers{stage="failed"}"#;
+        assert_eq!(sample(&metrics, failed), Some(1.0));
+        let imported = r#"putioarr_transfers{stage="imported"}"#;
+        assert_eq!(sample(&metrics, imported), Some(0.0));
+        let queue = r#"putioarr_queue_depth{queue="test"}"#;
+        assert_eq!(sample(&metrics, queue), Some(2.0));
+        assert!(metrics.contains("# TYPE putioarr_putio_request_duration_seconds histogram"));
+
+        // Dropped channels report an empty queue.
+        drop(sender);
+        let metrics = METRICS.render(&app_data).unwrap();
+        assert_eq!(sample(&metrics, queue), Some(0.0));
+    }
+}
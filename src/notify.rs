@@ -0,0 +1,183 @@
+use crate::{
+    download_system::{hook, transfer::Transfer},
+    AppData, NotificationsConfig, NotifierConfig,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{fmt::Display, time::Duration};
+
+/// How long a notifier gets to deliver an event.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A point in the life of a transfer that can be notified about.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// put.io is done, the download is about to start.
+    Queued,
+    Downloaded,
+    Imported,
+    /// put.io stopped seeding.
+    SeedingDone,
+    /// Cleaned up and forgotten about.
+    Removed,
+    /// Gave up on the transfer.
+    Failed,
+    /// Downloaded a while ago, but still not imported.
+    ImportStalled,
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            EventKind::Queued => "queued for download",
+            EventKind::Downloaded => "downloaded",
+            EventKind::Imported => "imported",
+            EventKind::SeedingDone => "done seeding",
+            EventKind::Removed => "removed",
+            EventKind::Failed => "failed",
+            EventKind::ImportStalled => "not imported yet",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifierKind {
+    /// POSTs the event as JSON.
+    Webhook,
+    Discord,
+    Slack,
+    /// Runs a command with the event in `PUTIOARR_*` environment variables.
+    Exec,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Event {
+    pub event: EventKind,
+    pub transfer_id: u64,
+    pub name: String,
+    pub hash: Option<String>,
+    pub message: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+impl Event {
+    pub fn new(event: EventKind, transfer: &Transfer) -> Self {
+        Self {
+            event,
+            transfer_id: transfer.transfer_id,
+            name: transfer.name.clone(),
+            hash: transfer.hash.clone(),
+            message: None,
+            at: Utc::now(),
+        }
+    }
+
+    pub fn with_message(self, message: &str) -> Self {
+        Self {
+            message: Some(message.to_string()),
+            ..self
+        }
+    }
+
+    /// A line for chat notifications.
+    pub fn text(&self) -> String {
+        match &self.message {
+            Some(message) => format!("putioarr: {} {}: {}", self.name, self.event, message),
+            None => format!("putioarr: {} {}", self.name, self.event),
+        }
+    }
+}
+
+impl NotifierConfig {
+    fn wants(&self, event: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Sends `event` to the notifiers that want it, in the background.
+pub fn notify(app_data: &AppData, event: Event) {
+    let notifiers: Vec<NotifierConfig> = app_data
+        .config
+        .notifications
+        .notifiers
+        .iter()
+        .filter(|n| n.wants(event.event))
+        .cloned()
+        .collect();
+    if notifiers.is_empty() {
+        return;
+    }
+
+    actix_rt::spawn(async move {
+        for notifier in &notifiers {
+            if let Err(e) = deliver(notifier, &event).await {
+                warn!(
+                    "Unable to notify {:?} of {} {}: {}",
+                    notifier.kind, event.name, event.event, e
+                );
+            }
+        }
+    });
+}
+
+/// Delivers `event` to `notifier`.
+pub async fn deliver(notifier: &NotifierConfig, event: &Event) -> Result<()> {
+    match notifier.kind {
+        NotifierKind::Webhook => post(notifier, serde_json::to_value(event)?).await,
+        NotifierKind::Discord => post(notifier, json!({ "content": event.text() })).await,
+        NotifierKind::Slack => post(notifier, json!({ "text": event.text() })).await,
+        NotifierKind::Exec => exec(notifier, event).await,
+    }
+}
+
+async fn post(notifier: &NotifierConfig, body: serde_json::Value) -> Result<()> {
+    let url = notifier.url.as_ref().context("No url configured")?;
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(TIMEOUT)
+        .json(&body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        bail!("url: {}, status: {}", url, response.status());
+    }
+    Ok(())
+}
+
+async fn exec(notifier: &NotifierConfig, event: &Event) -> Result<()> {
+    let command = notifier.command.as_ref().context("No command configured")?;
+    let env = [
+        (
+            "PUTIOARR_EVENT",
+            serde_json::to_value(event.event)?
+                .as_str()
+                .unwrap()
+                .to_string(),
+        ),
+        ("PUTIOARR_TRANSFER_ID", event.transfer_id.to_string()),
+        ("PUTIOARR_TRANSFER_NAME", event.name.clone()),
+        (
+            "PUTIOARR_TRANSFER_HASH",
+            event.hash.clone().unwrap_or_default(),
+        ),
+        (
+            "PUTIOARR_MESSAGE",
+            event.message.clone().unwrap_or_default(),
+        ),
+    ];
+    hook::run_command(command, &env, TIMEOUT).await?;
+    info!("Notified {} of {} {}", command, event.name, event.event);
+    Ok(())
+}
+
+/// Whether a transfer downloaded at `downloaded_at` should have been imported by now.
+pub fn import_stalled(config: &NotificationsConfig, downloaded_at: DateTime<Utc>) -> bool {
+    config.import_timeout > 0
+        && Utc::now() - downloaded_at >= chrono::Duration::minutes(config.import_timeout as i64)
+}
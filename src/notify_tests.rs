@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        notify::*, test_support::webhook::FakeWebhook, NotificationsConfig, NotifierConfig,
+    };
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    fn create_event(event: EventKind) -> Event {
+        Event {
+            event,
+            transfer_id: 42,
+            name: "Movie".to_string(),
+            hash: Some("abcd1234".to_string()),
+            message: None,
+            at: Utc::now(),
+        }
+    }
+
+    fn notifier(kind: &str, url: Option<String>, command: Option<&str>) -> NotifierConfig {
+        serde_json::from_value(serde_json::json!({
+            "kind": kind,
+            "url": url,
+            "command": command,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_event_text() {
+        assert_eq!(
+            create_event(EventKind::Imported).text(),
+            "putioarr: Movie imported"
+        );
+        assert_eq!(
+            create_event(EventKind::Failed)
+                .with_message("disk full")
+                .text(),
+            "putioarr: Movie failed: disk full"
+        );
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let json = serde_json::to_value(create_event(EventKind::SeedingDone)).unwrap();
+        assert_eq!(json["event"], "seeding_done");
+        assert_eq!(json["transfer_id"], 42);
+        assert_eq!(json["name"], "Movie");
+        assert_eq!(json["hash"], "abcd1234");
+        assert!(json["message"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn test_deliver_webhooks() {
+        let webhook = FakeWebhook::start().await;
+        let event = create_event(EventKind::Failed).with_message("disk full");
+
+        for kind in ["webhook", "discord", "slack"] {
+            deliver(&notifier(kind, Some(webhook.url()), None), &event)
+                .await
+                .unwrap();
+        }
+
+        let received = webhook.received();
+        assert_eq!(received.len(), 3);
+        assert_eq!(received[0]["event"], "failed");
+        assert_eq!(received[0]["message"], "disk full");
+        assert_eq!(received[1]["content"], "putioarr: Movie failed: disk full");
+        assert_eq!(received[2]["text"], "putioarr: Movie failed: disk full");
+    }
+
+    #[actix_web::test]
+    async fn test_deliver_webhook_error() {
+        let webhook = FakeWebhook::start().await;
+        let url = webhook.url().replace("/hook", "/missing");
+        let result = deliver(
+            &notifier("webhook", Some(url), None),
+            &create_event(EventKind::Queued),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_deliver_exec() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("event");
+        let command = format!(
+            "echo \"$PUTIOARR_EVENT $PUTIOARR_TRANSFER_ID $PUTIOARR_TRANSFER_NAME $PUTIOARR_TRANSFER_HASH $PUTIOARR_MESSAGE\" > {}",
+            out.display()
+        );
+        let event = create_event(EventKind::ImportStalled).with_message("waiting");
+
+        deliver(&notifier("exec", None, Some(&command)), &event)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(out).unwrap(),
+            "import_stalled 42 Movie abcd1234 waiting\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_deliver_exec_failure() {
+        let result = deliver(
+            &notifier("exec", None, Some("echo oops >&2; exit 3")),
+            &create_event(EventKind::Queued),
+        )
+        .await;
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("oops"), "{}", error);
+    }
+
+    #[test]
+    fn test_import_stalled() {
+        let config: NotificationsConfig = serde_json::from_value(serde_json::json!({
+            "import_timeout": 60,
+        }))
+        .unwrap();
+        assert!(!import_stalled(&config, Utc::now()));
+        assert!(!import_stalled(&config, Utc::now() - Duration::minutes(59)));
+        assert!(import_stalled(&config, Utc::now() - Duration::minutes(60)));
+
+        let disabled: NotificationsConfig = serde_json::from_value(serde_json::json!({
+            "import_timeout": 0,
+        }))
+        .unwrap();
+        assert!(!import_stalled(&disabled, Utc::now() - Duration::days(7)));
+    }
+}
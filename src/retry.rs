@@ -0,0 +1,35 @@
+use crate::Config;
+use std::time::Duration;
+
+/// Exponential backoff with jitter.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_attempts: config.max_download_attempts,
+            base_delay: Duration::from_secs(config.retry_delay),
+            max_delay: Duration::from_secs(config.max_retry_delay),
+        }
+    }
+
+    /// Whether another attempt is allowed after `attempt` attempts have been made.
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// The delay before the next attempt, after `attempt` attempts have been made. The delay
+    /// doubles with every attempt up to `max_delay`, and is randomized between half and the full
+    /// delay so that retries of different targets don't all hit put.io at the same time.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        let half = delay / 2;
+        half + delay.mul_f64(rand::random_range(0.0..=0.5))
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use crate::retry::RetryPolicy;
+    use std::time::Duration;
+
+    fn create_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = create_policy();
+        assert!(policy.should_retry(1));
+        assert!(policy.should_retry(4));
+        assert!(!policy.should_retry(5));
+        assert!(!policy.should_retry(6));
+    }
+
+    #[test]
+    fn test_delay_grows_exponentially() {
+        let policy = create_policy();
+        for (attempt, full) in [(1, 10), (2, 20), (3, 40)] {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_secs(full) / 2);
+            assert!(delay <= Duration::from_secs(full));
+        }
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        let policy = create_policy();
+        for attempt in [4, 10, 100] {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_secs(30));
+            assert!(delay <= Duration::from_secs(60));
+        }
+    }
+
+    #[test]
+    fn test_delay_has_jitter() {
+        let policy = create_policy();
+        let delays: Vec<Duration> = (0..20).map(|_| policy.delay(3)).collect();
+        assert!(delays.iter().any(|d| *d != delays[0]));
+    }
+}
@@ -1,6 +1,7 @@
+use crate::metrics::METRICS;
 use anyhow::{bail, Result};
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, time::Instant};
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -13,35 +14,172 @@ pub struct ArrHistoryResponse {
 #[serde(rename_all = "camelCase")]
 pub struct ArrHistoryRecord {
     pub event_type: String,
+    #[serde(default)]
+    pub download_id: Option<String>,
     pub data: HashMap<String, Option<String>>,
 }
 
-pub async fn check_imported(target: &str, api_key: &str, base_url: &str) -> Result<bool> {
-    let client = reqwest::Client::new();
-    let mut inspected = 0;
-    let mut page = 0;
-    loop {
-        let url = format!(
-            "{base_url}/api/v3/history?includeSeries=false&includeEpisode=false&page={page}&pageSize=1000");
-
-        let response = client.get(&url).header("X-Api-Key", api_key).send().await?;
+impl ArrHistoryRecord {
+    fn dropped_path(&self) -> Option<&str> {
+        self.data.get("droppedPath").and_then(|p| p.as_deref())
+    }
+}
 
-        let status = response.status();
+/// The flavours of *arr we know how to talk to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrKind {
+    Sonarr,
+    Radarr,
+    Whisparr,
+    Lidarr,
+    Readarr,
+}
 
-        if !status.is_success() {
-            bail!("url: {}, status: {}", url, status);
+impl ArrKind {
+    pub fn api_version(&self) -> &'static str {
+        match self {
+            ArrKind::Sonarr | ArrKind::Radarr | ArrKind::Whisparr => "v3",
+            ArrKind::Lidarr | ArrKind::Readarr => "v1",
         }
+    }
 
-        let bytes = response.bytes().await?;
-        let json: serde_json::Result<ArrHistoryResponse> = serde_json::from_slice(&bytes);
-        if json.is_err() {
-            bail!("url: {url}, status: {status}, body: {bytes:?}");
+    /// History event types that mean a downloaded file has been imported.
+    pub fn import_events(&self) -> &'static [&'static str] {
+        match self {
+            ArrKind::Sonarr | ArrKind::Radarr | ArrKind::Whisparr => &["downloadFolderImported"],
+            ArrKind::Lidarr => &["trackFileImported"],
+            ArrKind::Readarr => &["bookFileImported"],
         }
-        let history_response: ArrHistoryResponse = json?;
+    }
+}
+
+impl Display for ArrKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ArrKind::Sonarr => "Sonarr",
+            ArrKind::Radarr => "Radarr",
+            ArrKind::Whisparr => "Whisparr",
+            ArrKind::Lidarr => "Lidarr",
+            ArrKind::Readarr => "Readarr",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A configured *arr instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrService {
+    pub name: String,
+    pub kind: ArrKind,
+    pub url: String,
+    pub api_key: String,
+    /// Only torrents added with this category belong to this instance.
+    pub category: Option<String>,
+}
+
+const PAGE_SIZE: u32 = 1000;
+
+async fn history_page(
+    service: &ArrService,
+    page: u32,
+    download_id: Option<&str>,
+) -> Result<ArrHistoryResponse> {
+    let start = Instant::now();
+    let result = fetch_history_page(service, page, download_id).await;
+    METRICS
+        .arr_history_duration
+        .with_label_values(&[&service.name])
+        .observe(start.elapsed().as_secs_f64());
+    if result.is_err() {
+        METRICS
+            .arr_history_errors
+            .with_label_values(&[&service.name])
+            .inc();
+    }
+    result
+}
+
+async fn fetch_history_page(
+    service: &ArrService,
+    page: u32,
+    download_id: Option<&str>,
+) -> Result<ArrHistoryResponse> {
+    let base_url = &service.url;
+    let api_version = service.kind.api_version();
+    let mut url = format!(
+        "{base_url}/api/{api_version}/history?includeSeries=false&includeEpisode=false&page={page}&pageSize={PAGE_SIZE}&sortKey=date&sortDirection=descending");
+    if let Some(download_id) = download_id {
+        url.push_str(&format!("&downloadId={download_id}"));
+    }
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("X-Api-Key", &service.api_key)
+        .send()
+        .await?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        bail!("url: {}, status: {}", url, status);
+    }
+
+    let bytes = response.bytes().await?;
+    let json: serde_json::Result<ArrHistoryResponse> = serde_json::from_slice(&bytes);
+    if json.is_err() {
+        bail!("url: {url}, status: {status}, body: {bytes:?}");
+    }
+    Ok(json?)
+}
+
+/// Returns the paths `service` imported from the download with torrent hash `hash`, or `None` if
+/// `service` has no history for it at all, e.g. because it didn't add the torrent.
+///
+/// We deliberately don't filter on event type: the grab shows up in the history as soon as the
+/// torrent is added, which tells us the download is known and nothing has been imported yet.
+pub async fn imported_paths(hash: &str, service: &ArrService) -> Result<Option<Vec<String>>> {
+    // The *arrs store the hash the way Transmission reports it, upper case.
+    let download_id = hash.to_uppercase();
+    let import_events = service.kind.import_events();
+    let history = history_page(service, 1, Some(&download_id)).await?;
+
+    // Older versions ignore the downloadId filter, so check the records ourselves.
+    let records: Vec<ArrHistoryRecord> = history
+        .records
+        .into_iter()
+        .filter(|r| {
+            r.download_id
+                .as_ref()
+                .is_some_and(|id| id.eq_ignore_ascii_case(&download_id))
+        })
+        .collect();
+    if records.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        records
+            .iter()
+            .filter(|r| import_events.contains(&r.event_type.as_str()))
+            .filter_map(|r| r.dropped_path().map(|p| p.to_string()))
+            .collect(),
+    ))
+}
+
+/// Checks the whole history of `service` for an import of `target`. Expensive, only used when
+/// `imported_paths` doesn't know the download.
+pub async fn check_imported(target: &str, service: &ArrService) -> Result<bool> {
+    let import_events = service.kind.import_events();
+    let mut inspected = 0;
+    let mut page = 1;
+    loop {
+        let history_response = history_page(service, page, None).await?;
+        let records = history_response.records.len();
 
         for record in history_response.records {
-            if record.event_type == "downloadFolderImported"
-                && record.data["droppedPath"].as_ref().unwrap() == target
+            if import_events.contains(&record.event_type.as_str())
+                && record.dropped_path() == Some(target)
             {
                 return Ok(true);
             } else {
@@ -50,7 +188,7 @@ pub async fn check_imported(target: &str, api_key: &str, base_url: &str) -> Resu
             }
         }
 
-        if history_response.total_records < inspected {
+        if records > 0 && inspected < history_response.total_records {
             page += 1;
         } else {
             return Ok(false);
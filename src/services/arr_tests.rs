@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use super::super::arr::*;
+    use crate::test_support::arr::FakeArr;
+
+    fn create_service(kind: ArrKind, url: String) -> ArrService {
+        ArrService {
+            name: kind.to_string(),
+            kind,
+            url,
+            api_key: "key".to_string(),
+            category: None,
+        }
+    }
+
+    #[test]
+    fn test_arr_kind_api_version() {
+        assert_eq!(ArrKind::Sonarr.api_version(), "v3");
+        assert_eq!(ArrKind::Radarr.api_version(), "v3");
+        assert_eq!(ArrKind::Whisparr.api_version(), "v3");
+        assert_eq!(ArrKind::Lidarr.api_version(), "v1");
+        assert_eq!(ArrKind::Readarr.api_version(), "v1");
+    }
+
+    #[test]
+    fn test_arr_kind_import_events() {
+        assert_eq!(ArrKind::Sonarr.import_events(), ["downloadFolderImported"]);
+        assert_eq!(ArrKind::Lidarr.import_events(), ["trackFileImported"]);
+        assert_eq!(ArrKind::Readarr.import_events(), ["bookFileImported"]);
+    }
+
+    #[test]
+    fn test_arr_kind_deserialization() {
+        let kind: ArrKind = serde_json::from_str("\"lidarr\"").unwrap();
+        assert_eq!(kind, ArrKind::Lidarr);
+        assert_eq!(kind.to_string(), "Lidarr");
+        assert!(serde_json::from_str::<ArrKind>("\"prowlarr\"").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_check_imported_for_every_kind() {
+        for kind in [
+            ArrKind::Sonarr,
+            ArrKind::Radarr,
+            ArrKind::Whisparr,
+            ArrKind::Lidarr,
+            ArrKind::Readarr,
+        ] {
+            let arr = FakeArr::start(kind).await;
+            let service = create_service(kind, arr.url());
+            assert!(!check_imported("/downloads/file", &service).await.unwrap());
+
+            arr.import("/downloads/file");
+            assert!(check_imported("/downloads/file", &service).await.unwrap());
+            assert!(!check_imported("/downloads/other", &service).await.unwrap());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_check_imported_ignores_other_events() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/api/v1/history")
+            .match_query(mockito::Matcher::Any)
+            .match_header("x-api-key", "key")
+            .with_status(200)
+            .with_body(
+                r#"{"totalRecords": 2, "records": [
+                    {"eventType": "grabbed", "data": {"droppedPath": null}},
+                    {"eventType": "downloadFolderImported", "data": {"droppedPath": "/downloads/file"}}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+
+        let service = create_service(ArrKind::Lidarr, server.url());
+        assert!(!check_imported("/downloads/file", &service).await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_check_imported_error() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/api/v3/history")
+            .match_query(mockito::Matcher::Any)
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let service = create_service(ArrKind::Sonarr, server.url());
+        assert!(check_imported("/downloads/file", &service).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_imported_paths_by_download_id() {
+        let arr = FakeArr::start(ArrKind::Sonarr).await;
+        let service = create_service(ArrKind::Sonarr, arr.url());
+        assert_eq!(imported_paths("abcd", &service).await.unwrap(), None);
+
+        arr.grab("abcd");
+        arr.grab("ef01");
+        assert_eq!(
+            imported_paths("abcd", &service).await.unwrap(),
+            Some(vec![])
+        );
+
+        arr.import_download("abcd", "/downloads/episode1.mkv");
+        arr.import_download("ef01", "/downloads/other.mkv");
+        arr.import("/downloads/episode2.mkv");
+        assert_eq!(
+            imported_paths("ABCD", &service).await.unwrap(),
+            Some(vec!["/downloads/episode1.mkv".to_string()])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_imported_paths_without_download_id_filter() {
+        // Versions that don't know the filter return the whole history.
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/api/v3/history")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "downloadId".to_string(),
+                "ABCD".to_string(),
+            ))
+            .with_status(200)
+            .with_body(
+                r#"{"totalRecords": 1, "records": [
+                    {"eventType": "downloadFolderImported", "downloadId": "EF01",
+                     "data": {"droppedPath": "/downloads/other.mkv"}}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+
+        let service = create_service(ArrKind::Radarr, server.url());
+        assert_eq!(imported_paths("abcd", &service).await.unwrap(), None);
+    }
+
+    #[actix_web::test]
+    async fn test_check_imported_pages_through_history() {
+        let arr = FakeArr::start(ArrKind::Radarr).await;
+        arr.import("/downloads/old.mkv");
+        for i in 0..1000 {
+            arr.import(&format!("/downloads/{}.mkv", i));
+        }
+        let service = create_service(ArrKind::Radarr, arr.url());
+
+        assert!(check_imported("/downloads/old.mkv", &service)
+            .await
+            .unwrap());
+        assert_eq!(arr.requests(), 2);
+        assert!(!check_imported("/downloads/missing.mkv", &service)
+            .await
+            .unwrap());
+        assert_eq!(arr.requests(), 4);
+    }
+}
@@ -2,6 +2,8 @@ pub mod arr;
 pub mod putio;
 pub mod transmission;
 
+#[cfg(test)]
+mod arr_tests;
 #[cfg(test)]
 mod putio_tests;
 #[cfg(test)]
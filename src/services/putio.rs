@@ -1,7 +1,14 @@
+use crate::{metrics::METRICS, retry::RetryPolicy, PutioConfig};
 use anyhow::{bail, Result};
-use reqwest::multipart;
+use chrono::{DateTime, Utc};
+use log::warn;
+use reqwest::{header::RETRY_AFTER, multipart, Method, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -31,6 +38,14 @@ pub struct PutIOTransfer {
     pub error_message: Option<String>,
     pub file_id: Option<i64>,
     pub userfile_exists: bool,
+    /// The folder the files of the transfer are saved in.
+    #[serde(default)]
+    pub save_parent_id: Option<i64>,
+    /// Uploaded divided by downloaded.
+    #[serde(default)]
+    pub current_ratio: Option<f64>,
+    #[serde(default)]
+    pub seconds_seeding: Option<u64>,
 }
 
 impl PutIOTransfer {
@@ -42,21 +57,6 @@ impl PutIOTransfer {
 #[derive(Debug, Deserialize)]
 pub struct AccountInfoResponse {}
 
-pub async fn account_info(api_token: &str) -> Result<AccountInfoResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get("https://api.put.io/v2/account/info")
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        bail!("Error getting put.io account info: {}", response.status());
-    }
-
-    Ok(response.json().await?)
-}
-
 #[derive(Debug, Deserialize)]
 pub struct ListTransferResponse {
     pub transfers: Vec<PutIOTransfer>,
@@ -67,127 +67,321 @@ pub struct GetTransferResponse {
     pub transfer: PutIOTransfer,
 }
 
-/// Returns the user's transfers.
-pub async fn list_transfers(api_token: &str) -> Result<ListTransferResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get("https://api.put.io/v2/transfers/list")
-        .timeout(Duration::from_secs(10))
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
+pub const API_URL: &str = "https://api.put.io/v2";
+pub const UPLOAD_URL: &str = "https://upload.put.io/v2";
+
+/// put.io API client. Holds a single connection pool, and retries requests that were rate limited
+/// (429, honouring `Retry-After`) or couldn't connect. GETs are also retried on server errors and
+/// timeouts, other requests may have been carried out already.
+#[derive(Clone)]
+pub struct PutioClient {
+    client: reqwest::Client,
+    api_token: String,
+    api_url: String,
+    upload_url: String,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
 
-    if !response.status().is_success() {
-        bail!("Error getting put.io transfers: {}", response.status());
+impl PutioClient {
+    pub fn new(config: &PutioConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_token: config.api_key.clone(),
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            upload_url: config.upload_url.trim_end_matches('/').to_string(),
+            timeout: Duration::from_secs(config.timeout),
+            retry: RetryPolicy {
+                max_attempts: config.max_retries + 1,
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(60),
+            },
+        }
     }
 
-    Ok(response.json().await?)
-}
+    /// Sends the request produced by `build`, retrying when it makes sense. `build` is called for
+    /// every attempt, since multipart bodies can't be reused.
+    async fn send<F>(&self, endpoint: &str, build: F) -> Result<Response>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let request = build(&self.client)
+                .timeout(self.timeout)
+                .header("authorization", format!("Bearer {}", self.api_token))
+                .build()?;
+            let idempotent = request.method() == Method::GET;
+            let start = Instant::now();
+            let result = self.client.execute(request).await;
+            let status = result.as_ref().ok().map(|r| r.status().as_u16());
+            METRICS.putio_request(endpoint, status, start);
+
+            let delay = match &result {
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    retry_after(response)
+                        .map(|d| d.min(self.retry.max_delay))
+                        .unwrap_or_else(|| self.retry.delay(attempt))
+                }
+                Ok(response) if idempotent && response.status().is_server_error() => {
+                    self.retry.delay(attempt)
+                }
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                    self.retry.delay(attempt)
+                }
+                _ => return Ok(result?),
+            };
+            if !self.retry.should_retry(attempt) {
+                return Ok(result?);
+            }
+
+            let reason = match &result {
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            warn!(
+                "put.io request failed ({}), retrying in {}s (attempt {}/{})",
+                reason,
+                delay.as_secs(),
+                attempt + 1,
+                self.retry.max_attempts
+            );
+            METRICS.putio_retries.with_label_values(&[endpoint]).inc();
+            sleep(delay).await;
+        }
+    }
 
-pub async fn get_transfer(api_token: &str, transfer_id: u64) -> Result<GetTransferResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(format!("https://api.put.io/v2/transfers/{}", transfer_id))
-        .timeout(Duration::from_secs(10))
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
+    pub async fn account_info(&self) -> Result<AccountInfoResponse> {
+        let url = format!("{}/account/info", self.api_url);
+        let response = self.send("account/info", |c| c.get(&url)).await?;
 
-    if !response.status().is_success() {
-        bail!(
-            "Error getting put.io transfer id:{}: {}",
-            transfer_id,
-            response.status()
-        );
+        if !response.status().is_success() {
+            bail!("Error getting put.io account info: {}", response.status());
+        }
+
+        Ok(response.json().await?)
     }
 
-    Ok(response.json().await?)
-}
+    /// Returns the user's transfers.
+    pub async fn list_transfers(&self) -> Result<ListTransferResponse> {
+        let url = format!("{}/transfers/list", self.api_url);
+        let response = self.send("transfers/list", |c| c.get(&url)).await?;
 
-pub async fn remove_transfer(api_token: &str, transfer_id: u64) -> Result<()> {
-    let client = reqwest::Client::new();
-    let form = multipart::Form::new().text("transfer_ids", transfer_id.to_string());
-    let response = client
-        .post("https://api.put.io/v2/transfers/remove")
-        .timeout(Duration::from_secs(10))
-        .multipart(form)
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
+        if !response.status().is_success() {
+            bail!("Error getting put.io transfers: {}", response.status());
+        }
 
-    if !response.status().is_success() {
-        bail!(
-            "Error removing put.io transfer id:{}: {}",
-            transfer_id,
-            response.status()
-        );
+        Ok(response.json().await?)
     }
 
-    Ok(())
-}
+    pub async fn get_transfer(&self, transfer_id: u64) -> Result<GetTransferResponse> {
+        let url = format!("{}/transfers/{}", self.api_url, transfer_id);
+        let response = self.send("transfers/{id}", |c| c.get(&url)).await?;
 
-pub async fn delete_file(api_token: &str, file_id: i64) -> Result<()> {
-    let client = reqwest::Client::new();
-    let form = multipart::Form::new().text("file_ids", file_id.to_string());
-    let response = client
-        .post("https://api.put.io/v2/files/delete")
-        .timeout(Duration::from_secs(10))
-        .multipart(form)
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
+        if !response.status().is_success() {
+            bail!(
+                "Error getting put.io transfer id:{}: {}",
+                transfer_id,
+                response.status()
+            );
+        }
 
-    if !response.status().is_success() {
-        bail!(
-            "Error removing put.io file/direcotry id:{}: {}",
-            file_id,
-            response.status()
-        );
+        Ok(response.json().await?)
     }
 
-    Ok(())
-}
+    pub async fn remove_transfer(&self, transfer_id: u64) -> Result<()> {
+        let url = format!("{}/transfers/remove", self.api_url);
+        let response = self
+            .send("transfers/remove", |c| {
+                let form = multipart::Form::new().text("transfer_ids", transfer_id.to_string());
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error removing put.io transfer id:{}: {}",
+                transfer_id,
+                response.status()
+            );
+        }
+
+        Ok(())
+    }
 
-pub async fn add_transfer(api_token: &str, url: &str) -> Result<()> {
-    let client = reqwest::Client::new();
-    let form = multipart::Form::new().text("url", url.to_string());
-    let response = client
-        .post("https://api.put.io/v2/transfers/add")
-        .timeout(Duration::from_secs(10))
-        .multipart(form)
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
+    pub async fn cancel_transfer(&self, transfer_id: u64) -> Result<()> {
+        let url = format!("{}/transfers/cancel", self.api_url);
+        let response = self
+            .send("transfers/cancel", |c| {
+                let form = multipart::Form::new().text("transfer_ids", transfer_id.to_string());
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error cancelling put.io transfer id:{}: {}",
+                transfer_id,
+                response.status()
+            );
+        }
+
+        Ok(())
+    }
 
-    if !response.status().is_success() {
-        bail!("Error adding url: {} to put.io: {}", url, response.status());
+    pub async fn retry_transfer(&self, transfer_id: u64) -> Result<()> {
+        let url = format!("{}/transfers/retry", self.api_url);
+        let response = self
+            .send("transfers/retry", |c| {
+                let form = multipart::Form::new().text("id", transfer_id.to_string());
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error retrying put.io transfer id:{}: {}",
+                transfer_id,
+                response.status()
+            );
+        }
+
+        Ok(())
     }
 
-    Ok(())
-}
+    pub async fn delete_file(&self, file_id: i64) -> Result<()> {
+        let url = format!("{}/files/delete", self.api_url);
+        let response = self
+            .send("files/delete", |c| {
+                let form = multipart::Form::new().text("file_ids", file_id.to_string());
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error removing put.io file/direcotry id:{}: {}",
+                file_id,
+                response.status()
+            );
+        }
+
+        Ok(())
+    }
 
-pub async fn upload_file(api_token: &str, bytes: &[u8]) -> Result<()> {
-    let client = reqwest::Client::new();
-    let file_part = multipart::Part::bytes(bytes.to_owned()).file_name("foo.torrent");
+    /// Adds a transfer for `magnet_url`, saving its files in the folder `parent_id` if given.
+    pub async fn add_transfer(&self, magnet_url: &str, parent_id: Option<i64>) -> Result<()> {
+        let url = format!("{}/transfers/add", self.api_url);
+        let response = self
+            .send("transfers/add", |c| {
+                let mut form = multipart::Form::new().text("url", magnet_url.to_string());
+                if let Some(parent_id) = parent_id {
+                    form = form.text("save_parent_id", parent_id.to_string());
+                }
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error adding url: {} to put.io: {}",
+                magnet_url,
+                response.status()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Uploads a .torrent file, which put.io turns into a transfer saving its files in the folder
+    /// `parent_id` if given.
+    pub async fn upload_file(&self, bytes: &[u8], parent_id: Option<i64>) -> Result<()> {
+        let url = format!("{}/files/upload", self.upload_url);
+        let response = self
+            .send("files/upload", |c| {
+                let file_part = multipart::Part::bytes(bytes.to_owned()).file_name("foo.torrent");
+                let mut form = multipart::Form::new()
+                    .part("file", file_part)
+                    .text("filename", "foo.torrent");
+                if let Some(parent_id) = parent_id {
+                    form = form.text("parent_id", parent_id.to_string());
+                }
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!("Error uploading file to put.io: {}", response.status());
+        }
+        // Todo: error if invalid request
+        Ok(())
+    }
 
-    let form = reqwest::multipart::Form::new()
-        .part("file", file_part)
-        .text("filename", "foo.torrent");
+    pub async fn list_files(&self, file_id: i64) -> Result<ListFileResponse> {
+        let url = format!("{}/files/list?parent_id={}", self.api_url, file_id);
+        let response = self.send("files/list", |c| c.get(&url)).await?;
 
-    let response = client
-        .post("https://upload.put.io/v2/files/upload")
-        .timeout(Duration::from_secs(10))
-        .header("authorization", format!("Bearer {}", api_token))
-        .multipart(form)
-        .send()
-        .await?;
+        if !response.status().is_success() {
+            bail!(
+                "Error listing put.io file/direcotry id:{}: {}",
+                file_id,
+                response.status()
+            );
+        }
 
-    if !response.status().is_success() {
-        bail!("Error uploading file to put.io: {}", response.status());
+        Ok(response.json().await?)
+    }
+
+    pub async fn create_folder(&self, name: &str, parent_id: i64) -> Result<FileResponse> {
+        let url = format!("{}/files/create-folder", self.api_url);
+        let response = self
+            .send("files/create-folder", |c| {
+                let form = multipart::Form::new()
+                    .text("name", name.to_string())
+                    .text("parent_id", parent_id.to_string());
+                c.post(&url).multipart(form)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error creating put.io folder {} in id:{}: {}",
+                name,
+                parent_id,
+                response.status()
+            );
+        }
+
+        Ok(response.json::<CreateFolderResponse>().await?.file)
+    }
+
+    pub async fn url(&self, file_id: i64) -> Result<String> {
+        let url = format!("{}/files/{}/url", self.api_url, file_id);
+        let response = self.send("files/{id}/url", |c| c.get(&url)).await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Error getting url for put.io file id:{}: {}",
+                file_id,
+                response.status()
+            );
+        }
+
+        Ok(response.json::<URLResponse>().await?.url)
+    }
+}
+
+/// Parses a `Retry-After` header, which is either a number of seconds or an HTTP date.
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
     }
-    // Todo: error if invalid request
-    Ok(())
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
 }
+
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct UrlResponse {
@@ -208,26 +402,9 @@ pub struct FileResponse {
     pub file_type: String,
 }
 
-pub async fn list_files(api_token: &str, file_id: i64) -> Result<ListFileResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(format!(
-            "https://api.put.io/v2/files/list?parent_id={}",
-            file_id
-        ))
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        bail!(
-            "Error listing put.io file/direcotry id:{}: {}",
-            file_id,
-            response.status()
-        );
-    }
-
-    Ok(response.json().await?)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateFolderResponse {
+    pub file: FileResponse,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -235,25 +412,6 @@ pub struct URLResponse {
     pub url: String,
 }
 
-pub async fn url(api_token: &str, file_id: i64) -> Result<String> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(format!("https://api.put.io/v2/files/{}/url", file_id))
-        .header("authorization", format!("Bearer {}", api_token))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        bail!(
-            "Error getting url for put.io file id:{}: {}",
-            file_id,
-            response.status()
-        );
-    }
-
-    Ok(response.json::<URLResponse>().await?.url)
-}
-
 /// Returns a new OOB code.
 pub async fn get_oob() -> Result<String> {
     let response = reqwest::get("https://api.put.io/v2/oauth2/oob/code?app_id=6487").await?;
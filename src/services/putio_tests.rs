@@ -1,6 +1,19 @@
 #[cfg(test)]
 mod tests {
     use super::super::putio::*;
+    use crate::PutioConfig;
+
+    fn create_client(server: &mockito::ServerGuard, max_retries: u32) -> PutioClient {
+        let config = PutioConfig {
+            api_key: "test_key".to_string(),
+            timeout: 10,
+            max_retries,
+            api_url: server.url(),
+            upload_url: format!("{}/upload/", server.url()),
+            folder: None,
+        };
+        PutioClient::new(&config)
+    }
 
     #[test]
     fn test_putio_transfer_is_downloadable_with_file_id() {
@@ -17,6 +30,9 @@ mod tests {
             error_message: None,
             file_id: Some(123),
             userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         assert!(transfer.is_downloadable());
@@ -37,6 +53,9 @@ mod tests {
             error_message: None,
             file_id: None,
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         assert!(!transfer.is_downloadable());
@@ -266,6 +285,9 @@ mod tests {
             error_message: Some("Network timeout".to_string()),
             file_id: None,
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         assert!(!transfer.is_downloadable());
@@ -299,6 +321,9 @@ mod tests {
                 error_message: None,
                 file_id: None,
                 userfile_exists: false,
+                save_parent_id: None,
+                current_ratio: None,
+                seconds_seeding: None,
             };
 
             assert_eq!(transfer.status, status);
@@ -334,4 +359,290 @@ mod tests {
         let response: PutIOAccountResponse = serde_json::from_str(json).unwrap();
         assert!(!response.info.account_active);
     }
+
+    #[actix_web::test]
+    async fn test_client_sends_bearer_token() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/transfers/list")
+            .match_header("authorization", "Bearer test_key")
+            .with_status(200)
+            .with_body(r#"{"transfers": []}"#)
+            .create_async()
+            .await;
+
+        let response = create_client(&server, 0).list_transfers().await.unwrap();
+
+        mock.assert_async().await;
+        assert!(response.transfers.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_client_retries_rate_limited_requests() {
+        let mut server = mockito::Server::new_async().await;
+        let limited = server
+            .mock("GET", "/transfers/list")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(2)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", "/transfers/list")
+            .with_status(200)
+            .with_body(r#"{"transfers": []}"#)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 2).list_transfers().await.is_ok());
+
+        limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_client_retries_server_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let failing = server
+            .mock("GET", "/transfers/list")
+            .with_status(502)
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", "/transfers/list")
+            .with_status(200)
+            .with_body(r#"{"transfers": []}"#)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 1).list_transfers().await.is_ok());
+
+        failing.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_client_does_not_retry_posts_on_server_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/transfers/remove")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 3).remove_transfer(1).await.is_err());
+
+        mock.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_client_retries_rate_limited_posts() {
+        let mut server = mockito::Server::new_async().await;
+        let limited = server
+            .mock("POST", "/transfers/remove")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/transfers/remove")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 1).remove_transfer(1).await.is_ok());
+
+        limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_client_gives_up_after_max_retries() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/transfers/list")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(3)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 2).list_transfers().await.is_err());
+
+        mock.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_client_does_not_retry_client_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/transfers/1")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 3).get_transfer(1).await.is_err());
+
+        mock.assert_async().await;
+    }
+
+    fn file_json(id: i64, name: &str, file_type: &str) -> String {
+        format!(
+            r#"{{"content_type": "video/x-matroska", "id": {}, "name": "{}", "file_type": "{}"}}"#,
+            id, name, file_type
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_get_transfer() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/transfers/42")
+            .with_status(200)
+            .with_body(
+                r#"{"transfer": {"id": 42, "hash": "abc", "name": "Test", "status": "COMPLETED",
+                    "file_id": 7, "userfile_exists": true}}"#,
+            )
+            .create_async()
+            .await;
+
+        let transfer = create_client(&server, 0)
+            .get_transfer(42)
+            .await
+            .unwrap()
+            .transfer;
+        assert_eq!(transfer.id, 42);
+        assert_eq!(transfer.file_id, Some(7));
+        assert!(transfer.is_downloadable());
+    }
+
+    #[actix_web::test]
+    async fn test_list_files() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/files/list")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "parent_id".to_string(),
+                "7".to_string(),
+            ))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"files": [{}], "parent": {}}}"#,
+                file_json(8, "file.mkv", "VIDEO"),
+                file_json(7, "Test", "FOLDER")
+            ))
+            .create_async()
+            .await;
+
+        let response = create_client(&server, 0).list_files(7).await.unwrap();
+        assert_eq!(response.parent.file_type, "FOLDER");
+        assert_eq!(response.files.len(), 1);
+        assert_eq!(response.files[0].name, "file.mkv");
+    }
+
+    #[actix_web::test]
+    async fn test_url() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/files/8/url")
+            .with_status(200)
+            .with_body(r#"{"url": "https://example.com/file.mkv"}"#)
+            .create_async()
+            .await;
+
+        let url = create_client(&server, 0).url(8).await.unwrap();
+        assert_eq!(url, "https://example.com/file.mkv");
+    }
+
+    #[actix_web::test]
+    async fn test_add_transfer() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/transfers/add")
+            .match_body(mockito::Matcher::Regex(
+                "magnet:\\?xt=urn:btih:abc".to_string(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        create_client(&server, 0)
+            .add_transfer("magnet:?xt=urn:btih:abc", None)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_add_transfer_to_folder() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/transfers/add")
+            .match_body(mockito::Matcher::Regex(
+                "name=\"save_parent_id\"\r\n\r\n42".to_string(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        create_client(&server, 0)
+            .add_transfer("magnet:?xt=urn:btih:abc", Some(42))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_create_folder() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/files/create-folder")
+            .match_body(mockito::Matcher::Regex("tv-sonarr".to_string()))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"file": {}}}"#,
+                file_json(9, "tv-sonarr", "FOLDER")
+            ))
+            .create_async()
+            .await;
+
+        let folder = create_client(&server, 0)
+            .create_folder("tv-sonarr", 0)
+            .await
+            .unwrap();
+        assert_eq!(folder.id, 9);
+        assert_eq!(folder.file_type, "FOLDER");
+    }
+
+    #[actix_web::test]
+    async fn test_upload_file_uses_upload_url() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/upload/files/upload")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        create_client(&server, 0)
+            .upload_file(b"d8:announce0:e", None)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[actix_web::test]
+    async fn test_delete_file_error() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/files/delete")
+            .with_status(400)
+            .create_async()
+            .await;
+
+        assert!(create_client(&server, 0).delete_file(8).await.is_err());
+    }
 }
@@ -1,6 +1,6 @@
 use chrono::prelude::*;
 use log::warn;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::cmp::max;
 
 use super::putio::PutIOTransfer;
@@ -56,14 +56,18 @@ pub struct TransmissionTorrent {
     pub hash_string: Option<String>,
     pub name: String,
     pub download_dir: String,
+    pub labels: Vec<String>,
     pub total_size: i64,
     pub left_until_done: i64,
+    pub percent_done: f64,
     pub is_finished: bool,
     pub eta: i64,
     pub status: TransmissionTorrentStatus,
     pub seconds_downloading: i64,
     pub error_string: Option<String>,
     pub downloaded_ever: i64,
+    pub upload_ratio: f64,
+    pub seconds_seeding: u64,
     pub seed_ratio_limit: f32,
     pub seed_ratio_mode: u32,
     pub seed_idle_limit: u64,
@@ -85,19 +89,31 @@ impl From<PutIOTransfer> for TransmissionTorrent {
         let seconds_downloading = (now - started_at).num_seconds();
         let default = &"Unknown".to_string();
         let name = t.name.as_ref().unwrap_or(default);
+        let size = t.size.unwrap_or(0);
+        let percent_done = if t.finished_at.is_some() {
+            1.0
+        } else if size > 0 {
+            (t.downloaded.unwrap_or(0) as f64 / size as f64).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
         Self {
             id: t.id,
             hash_string: t.hash,
             name: name.clone(),
             download_dir: String::from(""),
+            labels: vec![],
             total_size: t.size.unwrap_or(0),
             left_until_done: max(t.size.unwrap_or(0) - t.downloaded.unwrap_or(0), 0),
+            percent_done,
             is_finished: t.finished_at.is_some(),
             eta: t.estimated_time.unwrap_or(0),
             status: TransmissionTorrentStatus::from(t.status),
             seconds_downloading,
             error_string: t.error_message,
             downloaded_ever: t.downloaded.unwrap_or(0),
+            upload_ratio: t.current_ratio.unwrap_or(0.0),
+            seconds_seeding: t.seconds_seeding.unwrap_or(0),
             seed_ratio_limit: 0.0,
             seed_ratio_mode: 0,
             seed_idle_limit: 0,
@@ -107,7 +123,47 @@ impl From<PutIOTransfer> for TransmissionTorrent {
     }
 }
 
-#[derive(Debug, Serialize)]
+/// How far putioarr is with copying a transfer from put.io to the download directory.
+#[derive(Debug, Clone, Default)]
+pub struct LocalProgress {
+    pub downloaded: u64,
+    pub bytes_per_sec: u64,
+    /// All targets are on disk.
+    pub done: bool,
+}
+
+impl TransmissionTorrent {
+    /// Combines the put.io progress with the local download progress, so the torrent is only
+    /// reported as finished once the files are on local disk. Both phases count for half of the
+    /// total progress.
+    pub fn with_local_progress(mut self, local: &LocalProgress) -> Self {
+        let size = self.total_size.max(0) as f64;
+        let putio_done = self.is_finished;
+        let local_fraction = if local.done {
+            1.0
+        } else if size > 0.0 {
+            (local.downloaded as f64 / size).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        self.percent_done = (self.percent_done + local_fraction) / 2.0;
+        self.left_until_done = (size * (1.0 - self.percent_done)).round() as i64;
+        self.is_finished = putio_done && local.done;
+
+        if putio_done && !local.done {
+            self.status = TransmissionTorrentStatus::Downloading;
+            let remaining = (size as u64).saturating_sub(local.downloaded);
+            self.eta = remaining
+                .checked_div(local.bytes_per_sec)
+                .map(|eta| eta as i64)
+                .unwrap_or(-1);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum TransmissionTorrentStatus {
     Stopped = 0,
     CheckWait = 1,
@@ -118,6 +174,13 @@ pub enum TransmissionTorrentStatus {
     Seeding = 6,
 }
 
+/// The RPC spec defines the status as a number.
+impl Serialize for TransmissionTorrentStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
 impl From<String> for TransmissionTorrentStatus {
     fn from(value: String) -> Self {
         match value.to_uppercase().as_str() {
@@ -31,6 +31,9 @@ mod tests {
             error_message: None,
             file_id: Some(456),
             userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transmission_torrent: TransmissionTorrent = putio_transfer.into();
@@ -65,6 +68,9 @@ mod tests {
             error_message: None,
             file_id: Some(999),
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transmission_torrent: TransmissionTorrent = putio_transfer.into();
@@ -95,6 +101,9 @@ mod tests {
             error_message: Some("Network error".to_string()),
             file_id: None,
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transmission_torrent: TransmissionTorrent = putio_transfer.into();
@@ -124,6 +133,9 @@ mod tests {
             error_message: None,
             file_id: None,
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transmission_torrent: TransmissionTorrent = putio_transfer.into();
@@ -148,6 +160,9 @@ mod tests {
             error_message: None,
             file_id: Some(1),
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transmission_torrent: TransmissionTorrent = putio_transfer.into();
@@ -315,6 +330,9 @@ mod tests {
             error_message: None,
             file_id: None,
             userfile_exists: false,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
         };
 
         let transmission_torrent: TransmissionTorrent = putio_transfer.into();
@@ -323,5 +341,120 @@ mod tests {
         assert_eq!(transmission_torrent.downloaded_ever, 0);
         assert_eq!(transmission_torrent.left_until_done, 0);
         assert_eq!(transmission_torrent.eta, 0);
+        assert_eq!(transmission_torrent.upload_ratio, 0.0);
+        assert_eq!(transmission_torrent.seconds_seeding, 0);
+    }
+
+    fn create_finished_transfer() -> PutIOTransfer {
+        PutIOTransfer {
+            id: 1,
+            hash: Some("abc123".to_string()),
+            name: Some("Finished".to_string()),
+            size: Some(1000),
+            downloaded: Some(1000),
+            finished_at: Some(Utc::now().format("%FT%T").to_string()),
+            estimated_time: Some(0),
+            status: "SEEDING".to_string(),
+            started_at: Some(Utc::now().format("%FT%T").to_string()),
+            error_message: None,
+            file_id: Some(2),
+            userfile_exists: true,
+            save_parent_id: None,
+            current_ratio: None,
+            seconds_seeding: None,
+        }
+    }
+
+    #[test]
+    fn test_transmission_torrent_percent_done() {
+        let torrent: TransmissionTorrent = create_finished_transfer().into();
+        assert_eq!(torrent.percent_done, 1.0);
+
+        let mut transfer = create_finished_transfer();
+        transfer.finished_at = None;
+        transfer.downloaded = Some(250);
+        let torrent: TransmissionTorrent = transfer.into();
+        assert_eq!(torrent.percent_done, 0.25);
+    }
+
+    #[test]
+    fn test_transmission_torrent_not_finished_until_local_copy_done() {
+        let torrent: TransmissionTorrent = create_finished_transfer().into();
+        let torrent = torrent.with_local_progress(&LocalProgress {
+            downloaded: 0,
+            bytes_per_sec: 0,
+            done: false,
+        });
+
+        assert!(!torrent.is_finished);
+        assert_eq!(torrent.percent_done, 0.5);
+        assert_eq!(torrent.left_until_done, 500);
+        assert_eq!(torrent.eta, -1);
+        assert!(matches!(
+            torrent.status,
+            TransmissionTorrentStatus::Downloading
+        ));
+    }
+
+    #[test]
+    fn test_transmission_torrent_local_download_in_progress() {
+        let torrent: TransmissionTorrent = create_finished_transfer().into();
+        let torrent = torrent.with_local_progress(&LocalProgress {
+            downloaded: 500,
+            bytes_per_sec: 100,
+            done: false,
+        });
+
+        assert!(!torrent.is_finished);
+        assert_eq!(torrent.percent_done, 0.75);
+        assert_eq!(torrent.left_until_done, 250);
+        assert_eq!(torrent.eta, 5);
+    }
+
+    #[test]
+    fn test_transmission_torrent_local_download_done() {
+        let torrent: TransmissionTorrent = create_finished_transfer().into();
+        let torrent = torrent.with_local_progress(&LocalProgress {
+            downloaded: 0,
+            bytes_per_sec: 0,
+            done: true,
+        });
+
+        assert!(torrent.is_finished);
+        assert_eq!(torrent.percent_done, 1.0);
+        assert_eq!(torrent.left_until_done, 0);
+        assert!(matches!(torrent.status, TransmissionTorrentStatus::Seeding));
+    }
+
+    #[test]
+    fn test_transmission_torrent_putio_still_downloading() {
+        let mut transfer = create_finished_transfer();
+        transfer.finished_at = None;
+        transfer.downloaded = Some(500);
+        transfer.estimated_time = Some(60);
+        transfer.status = "DOWNLOADING".to_string();
+        let torrent: TransmissionTorrent = transfer.into();
+        let torrent = torrent.with_local_progress(&LocalProgress::default());
+
+        assert!(!torrent.is_finished);
+        assert_eq!(torrent.percent_done, 0.25);
+        assert_eq!(torrent.left_until_done, 750);
+        assert_eq!(torrent.eta, 60);
+    }
+
+    #[test]
+    fn test_transmission_torrent_status_serializes_as_number() {
+        assert_eq!(
+            serde_json::to_string(&TransmissionTorrentStatus::Stopped).unwrap(),
+            "0"
+        );
+        assert_eq!(
+            serde_json::to_string(&TransmissionTorrentStatus::Downloading).unwrap(),
+            "4"
+        );
+        assert_eq!(
+            serde_json::to_string(&TransmissionTorrentStatus::Seeding).unwrap(),
+            "6"
+        );
     }
 }
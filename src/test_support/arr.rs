@@ -0,0 +1,118 @@
+use crate::services::arr::ArrKind;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+#[derive(Default)]
+struct FakeHistory {
+    records: Mutex<Vec<Value>>,
+    requests: AtomicUsize,
+}
+
+/// An in-process stand-in for the history API of a Sonarr/Radarr/Whisparr/Lidarr/Readarr.
+#[derive(Clone)]
+pub struct FakeArr {
+    url: String,
+    kind: ArrKind,
+    history: Arc<FakeHistory>,
+}
+
+impl FakeArr {
+    pub async fn start(kind: ArrKind) -> Self {
+        let history = Arc::new(FakeHistory::default());
+        let data = web::Data::from(history.clone());
+        let version = kind.api_version();
+        let server = HttpServer::new(move || {
+            App::new().app_data(data.clone()).service(
+                web::resource(format!("/api/{}/history", version))
+                    .route(web::get().to(get_history)),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_rt::spawn(server.run());
+
+        Self { url, kind, history }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn record(&self, event_type: &str, download_id: Option<&str>, path: Option<&str>) {
+        let mut records = self.history.records.lock().unwrap();
+        // Newest first, like the real thing.
+        records.insert(
+            0,
+            json!({
+                "eventType": event_type,
+                "downloadId": download_id.map(|id| id.to_uppercase()),
+                "data": { "droppedPath": path },
+            }),
+        );
+    }
+
+    /// Records that the file at `path` was imported, without a download id, as happens for
+    /// manual imports.
+    pub fn import(&self, path: &str) {
+        self.record(self.kind.import_events()[0], None, Some(path));
+    }
+
+    /// Records that the torrent with `hash` was sent to the download client.
+    pub fn grab(&self, hash: &str) {
+        self.record("grabbed", Some(hash), None);
+    }
+
+    /// Records that the file at `path` was imported from the torrent with `hash`.
+    pub fn import_download(&self, hash: &str, path: &str) {
+        self.record(self.kind.import_events()[0], Some(hash), Some(path));
+    }
+
+    /// Number of history requests served.
+    pub fn requests(&self) -> usize {
+        self.history.requests.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryQuery {
+    page: usize,
+    page_size: usize,
+    download_id: Option<String>,
+}
+
+async fn get_history(
+    history: web::Data<FakeHistory>,
+    query: web::Query<HistoryQuery>,
+) -> HttpResponse {
+    history.requests.fetch_add(1, Ordering::SeqCst);
+    let records: Vec<Value> = history
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|r| match &query.download_id {
+            Some(id) => r["downloadId"].as_str() == Some(id.as_str()),
+            None => true,
+        })
+        .cloned()
+        .collect();
+    let page: Vec<&Value> = records
+        .iter()
+        .skip(query.page.saturating_sub(1) * query.page_size)
+        .take(query.page_size)
+        .collect();
+    HttpResponse::Ok().json(json!({
+        "page": query.page,
+        "pageSize": query.page_size,
+        "totalRecords": records.len(),
+        "records": page,
+    }))
+}
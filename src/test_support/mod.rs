@@ -0,0 +1,98 @@
+//! Offline stand-ins for put.io, the *arr services and webhooks, so the whole pipeline can be
+//! exercised in `cargo test`, and the config and app data to run it with.
+
+use crate::{
+    download_system::{
+        errors::RecentErrors, imports::ImportCache, ownership::FolderCache,
+        progress::ProgressTracker, state::StateStore, torrents::TorrentStore,
+    },
+    http::session::SessionIds,
+    services::putio::PutioClient,
+    AppData, CleanupConfig, Config, ExtractConfig, FileFilterConfig, NotificationsConfig,
+    PostDownloadConfig, PutioConfig, SeedingConfig,
+};
+use actix_web::web;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+
+pub mod arr;
+pub mod putio;
+pub mod webhook;
+
+/// Polls `condition` until it holds, panicking with `what` after `timeout`.
+pub async fn wait_until(what: &str, timeout: Duration, condition: impl Fn() -> bool) {
+    let start = Instant::now();
+    while !condition() {
+        if start.elapsed() > timeout {
+            panic!("timed out waiting until {}", what);
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// A config with put.io at `api_url` and `upload_url` and no *arr services. Tests change what they
+/// need with `Config { ..config(api_url, upload_url) }`.
+pub fn config(api_url: &str, upload_url: &str) -> Config {
+    Config {
+        bind_address: "127.0.0.1".to_string(),
+        download_directory: "/tmp/downloads".to_string(),
+        download_segments: 1,
+        download_workers: 4,
+        loglevel: "info".to_string(),
+        max_download_attempts: 5,
+        max_retry_delay: 600,
+        min_segment_size_mb: 64,
+        only_owned_transfers: false,
+        orchestration_workers: 10,
+        password: "testpass".to_string(),
+        polling_interval: 10,
+        port: 9091,
+        retry_delay: 10,
+        shutdown_grace_period: 30,
+        skip_directories: vec!["sample".to_string(), "extras".to_string()],
+        uid: nix::unistd::getuid().as_raw(),
+        username: "testuser".to_string(),
+        cleanup: CleanupConfig::default(),
+        extract: ExtractConfig::default(),
+        file_filter: FileFilterConfig::default(),
+        notifications: NotificationsConfig::default(),
+        post_download: PostDownloadConfig::default(),
+        putio: PutioConfig {
+            api_key: "test_key".to_string(),
+            timeout: 10,
+            max_retries: 0,
+            api_url: api_url.to_string(),
+            upload_url: upload_url.to_string(),
+            folder: None,
+        },
+        seeding: SeedingConfig::default(),
+        sonarr: None,
+        radarr: None,
+        whisparr: None,
+        lidarr: None,
+        readarr: None,
+        arr: vec![],
+    }
+}
+
+/// App data with empty stores, running with `config`.
+pub fn with_config(config: Config) -> web::Data<AppData> {
+    web::Data::new(AppData {
+        putio: PutioClient::new(&config.putio),
+        config,
+        state: StateStore::default(),
+        torrents: TorrentStore::default(),
+        progress: ProgressTracker::default(),
+        imports: ImportCache::default(),
+        folders: FolderCache::default(),
+        errors: RecentErrors::default(),
+        session: SessionIds::default(),
+        shutdown: CancellationToken::new(),
+    })
+}
+
+/// App data with empty stores and the default test `config`.
+pub fn app_data(api_url: &str, upload_url: &str) -> web::Data<AppData> {
+    with_config(config(api_url, upload_url))
+}
@@ -0,0 +1,486 @@
+use actix_web::{get, post, web, App, HttpResponse, HttpServer};
+use magnet_url::Magnet;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+/// Id of the root folder, `add_file` with this parent creates a top level file.
+pub const ROOT: i64 = 0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FakeTransfer {
+    pub id: u64,
+    pub hash: Option<String>,
+    pub name: Option<String>,
+    pub size: Option<i64>,
+    pub downloaded: Option<i64>,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub file_id: Option<i64>,
+    pub userfile_exists: bool,
+    /// The folder the files of the transfer are saved in.
+    pub save_parent_id: Option<i64>,
+    pub current_ratio: Option<f64>,
+    pub seconds_seeding: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FakeFile {
+    pub id: i64,
+    pub parent_id: i64,
+    pub name: String,
+    pub file_type: String,
+    pub content: Vec<u8>,
+}
+
+/// A scripted state transition, applied when the transfer is read through the API.
+#[derive(Debug, Clone)]
+pub struct TransferStep {
+    pub status: &'static str,
+    pub file_id: Option<i64>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    base_url: String,
+    transfers: BTreeMap<u64, FakeTransfer>,
+    files: BTreeMap<i64, FakeFile>,
+    scripts: HashMap<u64, VecDeque<TransferStep>>,
+    next_id: u64,
+    removed_transfers: Vec<u64>,
+    deleted_files: Vec<i64>,
+    uploads: usize,
+    /// Number of requests still to fail by endpoint, see `FakePutio::fail`.
+    failures: HashMap<&'static str, usize>,
+}
+
+impl FakeState {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn new_transfer(&mut self, name: &str, hash: &str, status: &str) -> u64 {
+        let id = self.next_id();
+        self.transfers.insert(
+            id,
+            FakeTransfer {
+                id,
+                hash: Some(hash.to_string()),
+                name: Some(name.to_string()),
+                size: Some(0),
+                downloaded: Some(0),
+                status: status.to_string(),
+                error_message: None,
+                file_id: None,
+                userfile_exists: false,
+                save_parent_id: None,
+                current_ratio: None,
+                seconds_seeding: None,
+            },
+        );
+        id
+    }
+
+    /// Applies the next scripted step of transfer `id`, if any.
+    fn advance(&mut self, id: u64) {
+        let Some(step) = self.scripts.get_mut(&id).and_then(|s| s.pop_front()) else {
+            return;
+        };
+        if let Some(transfer) = self.transfers.get_mut(&id) {
+            transfer.status = step.status.to_string();
+            if step.file_id.is_some() {
+                transfer.file_id = step.file_id;
+                transfer.userfile_exists = true;
+            }
+        }
+    }
+
+    /// Whether this request to `endpoint` should fail.
+    fn fails(&mut self, endpoint: &'static str) -> bool {
+        match self.failures.get_mut(endpoint) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn size(&self, file_id: i64) -> i64 {
+        let own = self
+            .files
+            .get(&file_id)
+            .map_or(0, |f| f.content.len() as i64);
+        own + self
+            .files
+            .values()
+            .filter(|f| f.parent_id == file_id && f.id != file_id)
+            .map(|f| self.size(f.id))
+            .sum::<i64>()
+    }
+
+    fn delete_file(&mut self, file_id: i64) {
+        let children: Vec<i64> = self
+            .files
+            .values()
+            .filter(|f| f.parent_id == file_id)
+            .map(|f| f.id)
+            .collect();
+        for child in children {
+            self.delete_file(child);
+        }
+        if self.files.remove(&file_id).is_some() {
+            self.deleted_files.push(file_id);
+        }
+    }
+}
+
+/// An in-process stand-in for the put.io API. Point `PutioConfig::api_url` at `url()` and
+/// `PutioConfig::upload_url` at `upload_url()`. Files are served from `url()/download/{id}`.
+#[derive(Clone)]
+pub struct FakePutio {
+    url: String,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakePutio {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(FakeState::default()));
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .service(account_info)
+                .service(list_transfers)
+                .service(get_transfer)
+                .service(add_transfer)
+                .service(remove_transfer)
+                .service(list_files)
+                .service(create_folder)
+                .service(file_url)
+                .service(delete_files)
+                .service(upload)
+                .service(download)
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_rt::spawn(server.run());
+
+        state.lock().unwrap().base_url = url.clone();
+        Self { url, state }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn upload_url(&self) -> String {
+        format!("{}/upload", self.url)
+    }
+
+    /// Adds a file or folder and returns its id.
+    pub fn add_file(&self, parent_id: i64, name: &str, file_type: &str, content: &[u8]) -> i64 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id() as i64;
+        state.files.insert(
+            id,
+            FakeFile {
+                id,
+                parent_id,
+                name: name.to_string(),
+                file_type: file_type.to_string(),
+                content: content.to_vec(),
+            },
+        );
+        id
+    }
+
+    /// Adds a transfer and returns its id.
+    pub fn add_transfer(&self, name: &str, hash: &str, status: &str) -> u64 {
+        self.state.lock().unwrap().new_transfer(name, hash, status)
+    }
+
+    /// Moves transfer `id` to `status`, pointing it at `file_id` when given.
+    pub fn set_status(&self, id: u64, status: &str, file_id: Option<i64>) {
+        let mut state = self.state.lock().unwrap();
+        let size = file_id.map(|f| state.size(f));
+        let transfer = state.transfers.get_mut(&id).unwrap();
+        transfer.status = status.to_string();
+        if file_id.is_some() {
+            transfer.file_id = file_id;
+            transfer.userfile_exists = true;
+            transfer.size = size;
+            transfer.downloaded = size;
+        }
+    }
+
+    /// Sets how much and how long transfer `id` has been seeding.
+    pub fn set_seeding(&self, id: u64, current_ratio: f64, seconds_seeding: u64) {
+        let mut state = self.state.lock().unwrap();
+        let transfer = state.transfers.get_mut(&id).unwrap();
+        transfer.current_ratio = Some(current_ratio);
+        transfer.seconds_seeding = Some(seconds_seeding);
+    }
+
+    /// Saves the files of transfer `id` in folder `parent_id`.
+    pub fn set_save_parent_id(&self, id: u64, parent_id: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.transfers.get_mut(&id).unwrap().save_parent_id = Some(parent_id);
+    }
+
+    /// Queues state transitions for transfer `id`. Every time the transfer is read through the API
+    /// the next step is applied, so tests can walk a transfer through its life cycle.
+    pub fn script(&self, id: u64, steps: Vec<TransferStep>) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripts
+            .entry(id)
+            .or_default()
+            .extend(steps);
+    }
+
+    /// Answers the next `times` requests to `endpoint`, e.g. "transfers/list", with a 503.
+    pub fn fail(&self, endpoint: &'static str, times: usize) {
+        self.state.lock().unwrap().failures.insert(endpoint, times);
+    }
+
+    pub fn transfer(&self, id: u64) -> Option<FakeTransfer> {
+        self.state.lock().unwrap().transfers.get(&id).cloned()
+    }
+
+    pub fn transfers(&self) -> Vec<FakeTransfer> {
+        self.state
+            .lock()
+            .unwrap()
+            .transfers
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Id of the folder `name` in `parent_id`.
+    pub fn folder(&self, parent_id: i64, name: &str) -> Option<i64> {
+        self.state
+            .lock()
+            .unwrap()
+            .files
+            .values()
+            .find(|f| f.parent_id == parent_id && f.name == name && f.file_type == "FOLDER")
+            .map(|f| f.id)
+    }
+
+    pub fn file_exists(&self, id: i64) -> bool {
+        self.state.lock().unwrap().files.contains_key(&id)
+    }
+
+    pub fn removed_transfers(&self) -> Vec<u64> {
+        self.state.lock().unwrap().removed_transfers.clone()
+    }
+
+    pub fn deleted_files(&self) -> Vec<i64> {
+        self.state.lock().unwrap().deleted_files.clone()
+    }
+
+    pub fn uploads(&self) -> usize {
+        self.state.lock().unwrap().uploads
+    }
+}
+
+type State = web::Data<Mutex<FakeState>>;
+
+/// Extracts a text field from a multipart/form-data body. Good enough for the forms putioarr
+/// sends.
+fn form_field(body: &[u8], name: &str) -> Option<String> {
+    let body = String::from_utf8_lossy(body);
+    let marker = format!("name=\"{}\"", name);
+    let start = body.find(&marker)? + marker.len();
+    let value_start = body[start..].find("\r\n\r\n")? + start + 4;
+    let value_end = body[value_start..].find("\r\n")? + value_start;
+    Some(body[value_start..value_end].to_string())
+}
+
+fn form_ids(body: &[u8], name: &str) -> Vec<i64> {
+    form_field(body, name)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+#[get("/account/info")]
+async fn account_info() -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "info": {"username": "fake", "mail": "fake@example.com", "account_active": true}
+    }))
+}
+
+#[get("/transfers/list")]
+async fn list_transfers(state: State) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    if state.fails("transfers/list") {
+        return HttpResponse::ServiceUnavailable().finish();
+    }
+    let ids: Vec<u64> = state.transfers.keys().copied().collect();
+    for id in ids {
+        state.advance(id);
+    }
+    let transfers: Vec<&FakeTransfer> = state.transfers.values().collect();
+    HttpResponse::Ok().json(json!({ "transfers": transfers }))
+}
+
+#[get("/transfers/{id}")]
+async fn get_transfer(state: State, id: web::Path<u64>) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    if state.fails("transfers/{id}") {
+        return HttpResponse::ServiceUnavailable().finish();
+    }
+    state.advance(*id);
+    match state.transfers.get(&id) {
+        Some(transfer) => HttpResponse::Ok().json(json!({ "transfer": transfer })),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[post("/transfers/add")]
+async fn add_transfer(state: State, body: web::Bytes) -> HttpResponse {
+    let Some(url) = form_field(&body, "url") else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let (name, hash) = match Magnet::new(&url) {
+        Ok(m) => (
+            m.display_name().unwrap_or(&url).to_string(),
+            m.hash().unwrap_or_default().to_string(),
+        ),
+        Err(_) => (url.clone(), String::new()),
+    };
+    let mut state = state.lock().unwrap();
+    let id = state.new_transfer(&name, &hash, "IN_QUEUE");
+    state.transfers.get_mut(&id).unwrap().save_parent_id =
+        form_field(&body, "save_parent_id").and_then(|p| p.parse().ok());
+    HttpResponse::Ok().json(json!({ "transfer": { "id": id } }))
+}
+
+#[post("/transfers/remove")]
+async fn remove_transfer(state: State, body: web::Bytes) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    for id in form_ids(&body, "transfer_ids") {
+        if state.transfers.remove(&(id as u64)).is_some() {
+            state.removed_transfers.push(id as u64);
+        }
+    }
+    HttpResponse::Ok().json(json!({}))
+}
+
+#[derive(Deserialize)]
+struct ListFilesQuery {
+    parent_id: i64,
+}
+
+#[get("/files/list")]
+async fn list_files(state: State, query: web::Query<ListFilesQuery>) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    if state.fails("files/list") {
+        return HttpResponse::ServiceUnavailable().finish();
+    }
+    let root = FakeFile {
+        id: ROOT,
+        parent_id: ROOT,
+        name: "Your Files".to_string(),
+        file_type: "FOLDER".to_string(),
+        content: vec![],
+    };
+    let parent = match state.files.get(&query.parent_id) {
+        Some(parent) => parent,
+        None if query.parent_id == ROOT => &root,
+        None => return HttpResponse::NotFound().finish(),
+    };
+    let file_json = |f: &FakeFile| {
+        json!({
+            "content_type": if f.file_type == "FOLDER" { "application/x-directory" } else { "video/x-matroska" },
+            "id": f.id,
+            "name": f.name,
+            "file_type": f.file_type,
+        })
+    };
+    let files: Vec<_> = state
+        .files
+        .values()
+        .filter(|f| f.parent_id == parent.id)
+        .map(file_json)
+        .collect();
+    HttpResponse::Ok().json(json!({ "files": files, "parent": file_json(parent) }))
+}
+
+#[post("/files/create-folder")]
+async fn create_folder(state: State, body: web::Bytes) -> HttpResponse {
+    let (Some(name), Some(parent_id)) = (
+        form_field(&body, "name"),
+        form_field(&body, "parent_id").and_then(|p| p.parse::<i64>().ok()),
+    ) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let mut state = state.lock().unwrap();
+    let id = state.next_id() as i64;
+    state.files.insert(
+        id,
+        FakeFile {
+            id,
+            parent_id,
+            name: name.clone(),
+            file_type: "FOLDER".to_string(),
+            content: vec![],
+        },
+    );
+    HttpResponse::Ok().json(json!({
+        "file": {
+            "content_type": "application/x-directory",
+            "id": id,
+            "name": name,
+            "file_type": "FOLDER",
+        }
+    }))
+}
+
+#[get("/files/{id}/url")]
+async fn file_url(state: State, id: web::Path<i64>) -> HttpResponse {
+    let state = state.lock().unwrap();
+    if !state.files.contains_key(&id) {
+        return HttpResponse::NotFound().finish();
+    }
+    HttpResponse::Ok().json(json!({ "url": format!("{}/download/{}", state.base_url, id) }))
+}
+
+#[post("/files/delete")]
+async fn delete_files(state: State, body: web::Bytes) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    for id in form_ids(&body, "file_ids") {
+        state.delete_file(id);
+    }
+    HttpResponse::Ok().json(json!({}))
+}
+
+#[post("/upload/files/upload")]
+async fn upload(state: State, body: web::Bytes) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    state.uploads += 1;
+    let name = format!("Upload {}", state.uploads);
+    let id = state.new_transfer(&name, "", "IN_QUEUE");
+    state.transfers.get_mut(&id).unwrap().save_parent_id =
+        form_field(&body, "parent_id").and_then(|p| p.parse().ok());
+    HttpResponse::Ok().json(json!({ "transfer": { "id": id } }))
+}
+
+#[get("/download/{id}")]
+async fn download(state: State, id: web::Path<i64>) -> HttpResponse {
+    match state.lock().unwrap().files.get(&id) {
+        Some(file) => HttpResponse::Ok().body(file.content.clone()),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
@@ -0,0 +1,43 @@
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+/// An in-process webhook receiver that records what was posted to it.
+#[derive(Clone)]
+pub struct FakeWebhook {
+    url: String,
+    received: Arc<Mutex<Vec<Value>>>,
+}
+
+impl FakeWebhook {
+    pub async fn start() -> Self {
+        let received = Arc::new(Mutex::new(vec![]));
+        let data = web::Data::from(received.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/hook", web::post().to(receive))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}/hook", server.addrs()[0]);
+        actix_rt::spawn(server.run());
+
+        Self { url, received }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// The bodies posted so far, oldest first.
+    pub fn received(&self) -> Vec<Value> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+async fn receive(received: web::Data<Mutex<Vec<Value>>>, body: web::Json<Value>) -> HttpResponse {
+    received.lock().unwrap().push(body.into_inner());
+    HttpResponse::NoContent().finish()
+}
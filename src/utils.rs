@@ -39,11 +39,124 @@ orchestration_workers = 10
 # Optional number of download workers, default 4. This controls how many downloads we run in parallel.
 download_workers = 4
 
+# Optional number of connections used to download a single file, default 1. put.io limits the speed
+# per connection, so splitting large files into segments that are fetched in parallel can be a lot
+# faster. Each download worker opens at most this many connections, so the total number of
+# connections is bounded by download_workers * download_segments.
+download_segments = 1
+
+# Optional minimum size of a segment in MB, default 64. Files smaller than two segments are
+# downloaded over a single connection.
+min_segment_size_mb = 64
+
+# Optional number of times a file is attempted to be downloaded before the transfer is marked as
+# failed, default 5. Failed transfers are reported to sonarr/radarr/whisparr with an error.
+max_download_attempts = 5
+
+# Optional delay in secs before retrying a failed download, default 10. The delay doubles with every
+# attempt, up to max_retry_delay secs (default 600).
+retry_delay = 10
+max_retry_delay = 600
+
+# Optional number of secs to wait for downloads in progress to finish when shutting down (SIGTERM or
+# SIGINT), default 30. Downloads that don't finish in time are resumed on the next start.
+shutdown_grace_period = 30
+
+# Optional, default false. Only download and clean up the transfers added by sonarr/radarr/etc.
+# through the proxy, leaving transfers added some other way, e.g. from the put.io website, alone.
+# only_owned_transfers = false
+
 [putio]
 # Required. Putio API key. You can generate one using `putioarr get-token`
 api_key =  "{putio_api_key}"
+# Optional timeout in secs for put.io API requests, default 10
+timeout = 10
+# Optional number of times a put.io API request is retried when rate limited or unable to connect,
+# and for reads also on server errors or timeouts, default 3
+max_retries = 3
+# Optional put.io API base URLs, e.g. to use a local put.io stand-in. Can also be set with the
+# PUTIO_API_URL and PUTIO_UPLOAD_URL environment variables.
+# api_url = "https://api.put.io/v2"
+# upload_url = "https://upload.put.io/v2"
+# Optional put.io folder to save transfers in, default the root folder. With only_owned_transfers,
+# every transfer saved in this folder counts as added through the proxy.
+# folder = "putioarr"
+
+# Optional, decides which files of a transfer are downloaded. A file is downloaded when it matches
+# any of the include_* rules and none of the exclude_* rules. Matching is case-insensitive,
+# content types and globs support * and ? wildcards. Globs are matched against the file name.
+[file_filter]
+# put.io file types (VIDEO, AUDIO, TEXT, ...), default ["VIDEO"]
+include_file_types = ["VIDEO"]
+# File extensions, default subtitles and NFOs: ["srt", "ass", "ssa", "sub", "idx", "vtt", "sup", "nfo"]
+include_extensions = ["srt", "ass", "ssa", "sub", "idx", "vtt", "sup", "nfo"]
+# include_content_types = ["audio/*"]
+# include_globs = ["poster.jpg"]
+# exclude_file_types = []
+# exclude_extensions = []
+# exclude_content_types = []
+# exclude_globs = ["*sample*"]
+
+# Optional, decides what happens to the files once they have been imported and put.io is done seeding.
+# [cleanup]
+# What to do with the downloaded files: "delete" (default), "keep", "move" to completed_directory or
+# "delete_after" delete_after_hours (default 24). Pending delete_after deletions are kept in
+# state.json, so they survive a restart.
+# local = "delete"
+# completed_directory = "/completed"
+# delete_after_hours = 24
+# What to do with the put.io files: "delete" (default) or "keep". The transfer is removed either way.
+# remote = "delete"
+# Only log what would be deleted or moved, default false
+# dry_run = false
 
-# Both [sonarr] and [radarr] are optional, but you'll need at least one of them
+# Optional, stops seeding on put.io once a limit is reached, unless sonarr/radarr/etc. set limits of
+# their own for a torrent. Without limits put.io decides when to stop.
+# [seeding]
+# ratio_limit = 1.0
+# Seeding time in minutes
+# time_limit = 1440
+
+# Optional, extracts RAR and ZIP archives once they have been downloaded, so sonarr/radarr/etc. get to
+# import what's inside. Archives are downloaded along with the other files and extracted next to them,
+# multi-volume RARs included. RAR archives are extracted with unrar, which the docker image comes with.
+# [extract]
+# enabled = false
+# unrar = "unrar"
+# Seconds unrar may take per archive, default 3600
+# timeout = 3600
+# Delete the archives once the extracted files have been imported, default true
+# delete_archives = true
+
+# Optional command that is run with sh once all files of a transfer have been downloaded, before
+# sonarr/radarr/etc. get to import them, e.g. to unpack or validate the files. The transfer is passed
+# in PUTIOARR_TRANSFER_ID, PUTIOARR_TRANSFER_NAME, PUTIOARR_TRANSFER_HASH and PUTIOARR_PATH (the
+# downloaded file or directory). A non-zero exit marks the transfer as failed.
+# [post_download]
+# command = "/config/post-download.sh"
+# Seconds the command may take, default 3600
+# timeout = 3600
+
+# Optional notifications about transfers. Events: "queued", "downloaded", "imported", "seeding_done",
+# "removed", "failed" and "import_stalled".
+# [notifications]
+# Minutes after the download to send "import_stalled" if the transfer still hasn't been imported,
+# default 1440, 0 disables it.
+# import_timeout = 1440
+# Any number of notifiers of kind "webhook" (posts the event as JSON), "discord", "slack" or "exec".
+# events is optional and defaults to all events.
+# [[notifications.notifiers]]
+# kind = "discord"
+# url = "https://discord.com/api/webhooks/..."
+# events = ["failed", "import_stalled"]
+# exec runs the command with sh, passing the event in PUTIOARR_EVENT, PUTIOARR_TRANSFER_ID,
+# PUTIOARR_TRANSFER_NAME, PUTIOARR_TRANSFER_HASH and PUTIOARR_MESSAGE.
+# [[notifications.notifiers]]
+# kind = "exec"
+# command = "/config/notify.sh"
+
+# [sonarr], [radarr], [whisparr], [lidarr] and [readarr] are all optional, but you'll need at least
+# one of them
 [sonarr]
 url = "http://mysonarrhost:8989/sonarr"
 # Can be found in Settings -> General
@@ -59,6 +172,31 @@ url = "http://mywhisparrhost:6969/radarr"
 # Can be found in Settings -> General
 api_key = "MYWHISPARRAPIKEY"
 
+# Lidarr imports audio, add "AUDIO" to file_filter.include_file_types
+# [lidarr]
+# url = "http://mylidarrhost:8686"
+# api_key = "MYLIDARRAPIKEY"
+
+# Readarr imports ebooks and audiobooks, add e.g. "epub", "mobi" and "azw3" to
+# file_filter.include_extensions
+# [readarr]
+# url = "http://myreadarrhost:8787"
+# api_key = "MYREADARRAPIKEY"
+
+# Running more than one instance of a kind, e.g. an HD and a 4K Sonarr? Add an [[arr]] entry for each
+# instance. kind is one of sonarr, radarr, whisparr, lidarr or readarr. These can be combined with the
+# tables above.
+# [[arr]]
+# name = "sonarr-4k"
+# kind = "sonarr"
+# url = "http://mysonarr4khost:8989"
+# api_key = "MYSONARR4KAPIKEY"
+#
+# Every *arr table can have an optional category, the one set in the *arr's download client settings.
+# Torrents added with a category are saved in a put.io folder and a download_directory subdirectory
+# of that name, and only the instances with that category are asked about their imports.
+# category = "tv-sonarr4k"
+
 "#;
 
 #[derive(Serialize, Deserialize)]